@@ -0,0 +1,7 @@
+mod segment;
+mod sink;
+mod tail;
+
+pub use segment::{CdcSegment, load_manifest};
+pub use sink::*;
+pub use tail::*;
@@ -0,0 +1,68 @@
+use crate::adapter::journal::{io_error, json_error};
+use crate::domain::PaymentError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = "manifest.json";
+
+/// A completed (rotated) CDC segment, as listed in the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CdcSegment {
+    /// File name, relative to the sink directory
+    pub file: String,
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    pub events: u64,
+    pub bytes: u64,
+    /// Hex SHA-256 of the file, for consumers copying segments elsewhere
+    pub sha256: String,
+}
+
+pub(crate) fn segment_file_name(first_sequence: u64) -> String {
+    format!("cdc-{:020}.jsonl", first_sequence)
+}
+
+/// Segment files of a sink directory, completed or not, ordered by first sequence
+pub(crate) fn list_segments(dir: &Path) -> Result<Vec<(u64, PathBuf)>, PaymentError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut segments: Vec<(u64, PathBuf)> = fs::read_dir(dir)
+        .map_err(io_error)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter_map(|path| {
+            let first_sequence = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix("cdc-")?
+                .strip_suffix(".jsonl")?
+                .parse()
+                .ok()?;
+            Some((first_sequence, path))
+        })
+        .collect();
+    segments.sort();
+    Ok(segments)
+}
+
+/// Completed segments of a sink directory, oldest first
+pub fn load_manifest(dir: &Path) -> Result<Vec<CdcSegment>, PaymentError> {
+    let path = dir.join(MANIFEST);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read(&path).map_err(io_error)?;
+    serde_json::from_slice(&content).map_err(json_error)
+}
+
+/// Replace the manifest atomically (tmp file + rename)
+pub(crate) fn save_manifest(dir: &Path, segments: &[CdcSegment]) -> Result<(), PaymentError> {
+    let path = dir.join(MANIFEST);
+    let tmp_path = path.with_extension("json.tmp");
+    let content = serde_json::to_vec_pretty(segments).map_err(json_error)?;
+
+    fs::write(&tmp_path, content).map_err(io_error)?;
+    fs::rename(&tmp_path, &path).map_err(io_error)
+}
@@ -0,0 +1,370 @@
+use super::segment::{CdcSegment, list_segments, load_manifest, save_manifest, segment_file_name};
+use crate::adapter::journal::{blocking, io_error, json_error};
+use crate::domain::{
+    Chargebacked, Deposited, Disputed, EngineError, EventEnvelope, JournalCursor, JournalQuery,
+    PaymentError, Resolved, Withdrawn,
+};
+use crate::port::{CallbackContext, EventCallback, Journal};
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// Tuning of a CDC sink
+#[derive(Debug, Clone, Copy)]
+pub struct CdcSinkConfig {
+    /// Size after which the active segment is completed and a new one started
+    pub max_segment_bytes: u64,
+    /// Events read from the journal per query when filling a gap
+    pub page_size: usize,
+}
+
+impl Default for CdcSinkConfig {
+    fn default() -> Self {
+        Self {
+            max_segment_bytes: 64 * 1024 * 1024,
+            page_size: 1000,
+        }
+    }
+}
+
+/// Change data capture sink: every persisted event, as JSON Lines in rotating segments
+///
+/// Registered as an EventCallback, it appends each envelope to the active segment
+/// `cdc-<first sequence>.jsonl`. Full segments are synced and listed in `manifest.json`.
+/// What was written is tracked by journal sequence: an envelope arriving past a gap (a
+/// failed write, out-of-order publication) fills the gap from the journal first, and
+/// `catch_up` does the same on startup. Events the journal no longer has are never skipped:
+/// the gap is raised as `HistoryGap`. Write failures are logged rather than returned,
+/// since the event is already persisted and the next delivery repairs the stream. File I/O
+/// runs on tokio's blocking pool.
+pub struct CdcSink {
+    config: CdcSinkConfig,
+    state: Arc<Mutex<SinkState>>,
+}
+
+struct SinkState {
+    dir: PathBuf,
+    max_segment_bytes: u64,
+    /// Last sequence number fully written
+    last_sequence: u64,
+    active: Option<ActiveSegment>,
+    manifest: Vec<CdcSegment>,
+}
+
+struct ActiveSegment {
+    path: PathBuf,
+    first_sequence: u64,
+    events: u64,
+    /// Length of the complete lines written so far
+    bytes: u64,
+    /// None after a failed write: the file is truncated back to `bytes` before reuse
+    file: Option<File>,
+}
+
+impl CdcSink {
+    /// Open a sink directory, resuming after the last complete line
+    pub fn open(dir: impl Into<PathBuf>, config: CdcSinkConfig) -> Result<Self, PaymentError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(io_error)?;
+
+        let manifest = load_manifest(&dir)?;
+        let mut last_sequence = manifest.last().map_or(0, |segment| segment.last_sequence);
+
+        // The active segment is the newest file not listed in the manifest
+        let active = match list_segments(&dir)?.pop() {
+            Some((first_sequence, path))
+                if !manifest
+                    .iter()
+                    .any(|segment| dir.join(&segment.file) == path) =>
+            {
+                let (events, bytes, last) = scan_segment(&path)?;
+                last_sequence = last_sequence.max(last);
+                Some(ActiveSegment {
+                    path,
+                    first_sequence,
+                    events,
+                    bytes,
+                    file: None,
+                })
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            config,
+            state: Arc::new(Mutex::new(SinkState {
+                dir,
+                max_segment_bytes: config.max_segment_bytes,
+                last_sequence,
+                active,
+                manifest,
+            })),
+        })
+    }
+
+    /// Last sequence number written to the sink
+    pub async fn last_sequence(&self) -> u64 {
+        self.state.lock().await.last_sequence
+    }
+
+    /// Write every journal event the sink has not seen yet
+    ///
+    /// Fails with `HistoryGap` when the journal no longer has the next events.
+    pub async fn catch_up(
+        &self,
+        journal: &(dyn Journal + Send + Sync),
+    ) -> Result<u64, PaymentError> {
+        let state = self.state.clone().lock_owned().await;
+        let state = self.fill(state, journal, None).await?;
+        Ok(state.last_sequence)
+    }
+
+    /// Complete the active segment now, e.g. before shutting down
+    pub async fn rotate(&self) -> Result<(), PaymentError> {
+        let mut state = self.state.clone().lock_owned().await;
+        blocking(move || state.complete_active()).await
+    }
+
+    async fn publish(&self, ctx: &CallbackContext) -> Result<(), PaymentError> {
+        if let Err(e) = self.deliver(ctx).await {
+            tracing::warn!(
+                "CDC sink failed at sequence {}, retrying with the next event: {}",
+                ctx.envelope.sequence_nr,
+                e
+            );
+        }
+        Ok(())
+    }
+
+    async fn deliver(&self, ctx: &CallbackContext) -> Result<(), PaymentError> {
+        // The lock keeps writes in sequence order; the writes themselves run on the
+        // blocking pool, carrying the guard there and back
+        let mut state = self.state.clone().lock_owned().await;
+        let sequence_nr = ctx.envelope.sequence_nr;
+
+        if sequence_nr <= state.last_sequence {
+            return Ok(());
+        }
+        if sequence_nr > state.last_sequence + 1 {
+            state = self
+                .fill(state, ctx.journal.as_ref(), Some(sequence_nr - 1))
+                .await?;
+            // Still missing: truncated, or not readable yet; the next delivery retries
+            if sequence_nr > state.last_sequence + 1 {
+                return Err(history_gap(state.last_sequence + 1, sequence_nr - 1));
+            }
+        }
+        let envelope = ctx.envelope.clone();
+        blocking(move || state.write(&envelope)).await
+    }
+
+    /// Copy journal events after the last written one, up to `to_sequence`
+    ///
+    /// Fails with `HistoryGap` rather than skip events the journal no longer has.
+    async fn fill(
+        &self,
+        mut state: OwnedMutexGuard<SinkState>,
+        journal: &(dyn Journal + Send + Sync),
+        to_sequence: Option<u64>,
+    ) -> Result<OwnedMutexGuard<SinkState>, PaymentError> {
+        loop {
+            let page = journal
+                .query(&JournalQuery {
+                    after: Some(JournalCursor::after(state.last_sequence)),
+                    to_sequence,
+                    limit: self.config.page_size.max(1),
+                    ..Default::default()
+                })
+                .await?;
+
+            if let Some(first) = page.envelopes.first()
+                && first.sequence_nr > state.last_sequence + 1
+            {
+                return Err(history_gap(state.last_sequence + 1, first.sequence_nr - 1));
+            }
+            let envelopes = page.envelopes;
+            state = blocking(move || {
+                for envelope in &envelopes {
+                    state.write(envelope)?;
+                }
+                Ok(state)
+            })
+            .await?;
+            if page.next_cursor.is_none() {
+                return Ok(state);
+            }
+        }
+    }
+}
+
+impl SinkState {
+    fn write(&mut self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        let mut line = serde_json::to_vec(envelope).map_err(json_error)?;
+        line.push(b'\n');
+
+        let active = self.active.get_or_insert_with(|| ActiveSegment {
+            path: self.dir.join(segment_file_name(envelope.sequence_nr)),
+            first_sequence: envelope.sequence_nr,
+            events: 0,
+            bytes: 0,
+            file: None,
+        });
+        if let Err(e) = active.append(&line) {
+            active.file = None;
+            return Err(e);
+        }
+        self.last_sequence = envelope.sequence_nr;
+
+        if active.bytes >= self.max_segment_bytes {
+            self.complete_active()?;
+        }
+        Ok(())
+    }
+
+    /// Sync the active segment and record it in the manifest
+    fn complete_active(&mut self) -> Result<(), PaymentError> {
+        let Some(mut active) = self.active.take() else {
+            return Ok(());
+        };
+        if active.events == 0 {
+            return Ok(());
+        }
+
+        let result = active.seal().and_then(|sha256| {
+            let file = active
+                .path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default()
+                .to_string();
+            self.manifest.push(CdcSegment {
+                file,
+                first_sequence: active.first_sequence,
+                last_sequence: self.last_sequence,
+                events: active.events,
+                bytes: active.bytes,
+                sha256,
+            });
+            save_manifest(&self.dir, &self.manifest).inspect_err(|_| {
+                self.manifest.pop();
+            })
+        });
+        if result.is_err() {
+            // Keep writing to the segment; completion is retried at the next rotation
+            active.file = None;
+            self.active = Some(active);
+        }
+        result
+    }
+}
+
+impl ActiveSegment {
+    fn append(&mut self, line: &[u8]) -> Result<(), PaymentError> {
+        if self.file.is_none() {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(false)
+                .open(&self.path)
+                .map_err(io_error)?;
+            // Drop a torn line left by a failed write or a crash
+            file.set_len(self.bytes).map_err(io_error)?;
+            file.seek(SeekFrom::Start(self.bytes)).map_err(io_error)?;
+            self.file = Some(file);
+        }
+
+        let file = self.file.as_mut().expect("segment file is open");
+        file.write_all(line).map_err(io_error)?;
+        self.bytes += line.len() as u64;
+        self.events += 1;
+        Ok(())
+    }
+
+    /// Sync the file to disk and return its hex SHA-256
+    fn seal(&mut self) -> Result<String, PaymentError> {
+        if let Some(file) = self.file.as_ref() {
+            file.sync_all().map_err(io_error)?;
+        }
+        let content = fs::read(&self.path).map_err(io_error)?;
+        Ok(Sha256::digest(&content)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect())
+    }
+}
+
+fn history_gap(from_sequence: u64, to_sequence: u64) -> PaymentError {
+    PaymentError::Engine(EngineError::HistoryGap {
+        from_sequence,
+        to_sequence,
+    })
+}
+
+/// Count the complete lines of a segment: (events, bytes, last sequence)
+fn scan_segment(path: &Path) -> Result<(u64, u64, u64), PaymentError> {
+    let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+    let (mut events, mut bytes, mut last_sequence) = (0, 0, 0);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line).map_err(io_error)?;
+        if read == 0 || !line.ends_with('\n') {
+            break;
+        }
+        let Ok(envelope) = serde_json::from_str::<EventEnvelope>(&line) else {
+            break;
+        };
+        events += 1;
+        bytes += read as u64;
+        last_sequence = envelope.sequence_nr;
+    }
+    Ok((events, bytes, last_sequence))
+}
+
+#[async_trait]
+impl EventCallback for CdcSink {
+    async fn on_deposited(
+        &self,
+        _event: &Deposited,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx).await
+    }
+
+    async fn on_withdrawn(
+        &self,
+        _event: &Withdrawn,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx).await
+    }
+
+    async fn on_disputed(
+        &self,
+        _event: &Disputed,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx).await
+    }
+
+    async fn on_resolved(
+        &self,
+        _event: &Resolved,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx).await
+    }
+
+    async fn on_chargebacked(
+        &self,
+        _event: &Chargebacked,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx).await
+    }
+}
@@ -0,0 +1,153 @@
+use super::segment::{list_segments, load_manifest};
+use crate::adapter::journal::{io_error, json_error};
+use crate::domain::{EventEnvelope, PaymentError};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Reader following a CDC sink directory, like `tail -f` across segment rotations
+///
+/// Only complete lines are returned; a line still being written is picked up once its
+/// newline lands. The consumer resumes from its own position: pass the last sequence it
+/// processed to `open`.
+pub struct CdcTail {
+    dir: PathBuf,
+    /// Last sequence number returned
+    position: u64,
+    segment: Option<OpenSegment>,
+    poll_interval: Duration,
+}
+
+struct OpenSegment {
+    first_sequence: u64,
+    file_name: String,
+    reader: BufReader<File>,
+    /// Bytes of a line whose newline has not been written yet
+    pending: String,
+    /// Listed in the manifest: no line will be added
+    completed: bool,
+}
+
+impl CdcTail {
+    /// Start reading after `after` (0 reads from the beginning)
+    pub fn open(dir: impl Into<PathBuf>, after: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            position: after,
+            segment: None,
+            poll_interval: Duration::from_millis(200),
+        }
+    }
+
+    /// How long `next` sleeps when the sink has nothing new
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Last sequence number returned
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Wait for the next event
+    pub async fn next(&mut self) -> Result<EventEnvelope, PaymentError> {
+        loop {
+            if let Some(envelope) = self.try_next()? {
+                return Ok(envelope);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Next event if one is available, without waiting
+    pub fn try_next(&mut self) -> Result<Option<EventEnvelope>, PaymentError> {
+        loop {
+            if self.segment.is_none() {
+                match self.first_segment()? {
+                    Some(segment) => self.segment = Some(segment),
+                    None => return Ok(None),
+                }
+            }
+
+            if let Some(envelope) = self.read_line()? {
+                if envelope.sequence_nr <= self.position {
+                    continue;
+                }
+                self.position = envelope.sequence_nr;
+                return Ok(Some(envelope));
+            }
+
+            // At the end of the segment: move on only once the sink has completed it. The
+            // manifest is written after the segment's last line, so drain it once more first.
+            let segment = self.segment.as_mut().expect("segment is open");
+            if !segment.completed {
+                segment.completed = load_manifest(&self.dir)?
+                    .iter()
+                    .any(|completed| completed.file == segment.file_name);
+                if segment.completed {
+                    continue;
+                }
+                return Ok(None);
+            }
+
+            let current = segment.first_sequence;
+            match self.next_segment(current)? {
+                Some(next) => self.segment = Some(next),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Next complete line of the open segment
+    fn read_line(&mut self) -> Result<Option<EventEnvelope>, PaymentError> {
+        let segment = self.segment.as_mut().expect("segment is open");
+        segment
+            .reader
+            .read_line(&mut segment.pending)
+            .map_err(io_error)?;
+        if !segment.pending.ends_with('\n') {
+            return Ok(None);
+        }
+
+        let envelope = serde_json::from_str(&segment.pending).map_err(json_error)?;
+        segment.pending.clear();
+        Ok(Some(envelope))
+    }
+
+    /// The newest segment that can contain the next position, or the oldest one if every
+    /// segment starts later
+    fn first_segment(&self) -> Result<Option<OpenSegment>, PaymentError> {
+        let segments = list_segments(&self.dir)?;
+        segments
+            .iter()
+            .rev()
+            .find(|(first, _)| *first <= self.position + 1)
+            .or(segments.first())
+            .map(|(first_sequence, path)| open_segment(*first_sequence, path))
+            .transpose()
+    }
+
+    fn next_segment(&self, current: u64) -> Result<Option<OpenSegment>, PaymentError> {
+        list_segments(&self.dir)?
+            .iter()
+            .find(|(first, _)| *first > current)
+            .map(|(first_sequence, path)| open_segment(*first_sequence, path))
+            .transpose()
+    }
+}
+
+fn open_segment(first_sequence: u64, path: &Path) -> Result<OpenSegment, PaymentError> {
+    Ok(OpenSegment {
+        first_sequence,
+        file_name: path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string(),
+        reader: BufReader::new(File::open(path).map_err(io_error)?),
+        pending: String::new(),
+        completed: false,
+    })
+}
@@ -1,7 +1,7 @@
 use crate::{
     domain::{
-        AccountState, ActiveAccountState, EngineError, FrozenAccountState, PaymentError,
-        TransactionError, TransactionTypeEvent, Withdraw, Withdrawn,
+        AccountState, ActiveAccountState, FrozenAccountState, PaymentError, TransactionError,
+        TransactionTypeEvent, Withdraw, Withdrawn,
     },
     port::{CommandHandler, TransactionLookup},
 };
@@ -34,9 +34,7 @@ impl CommandHandler for Withdraw {
         let available = match state {
             AccountState::Active(ActiveAccountState { available, .. }) => *available,
             AccountState::Frozen(FrozenAccountState { .. }) => {
-                return Err(PaymentError::Engine(EngineError::ValidationError(
-                    "Cannot withdraw from frozen account".to_string(),
-                )));
+                return Err(PaymentError::Transaction(TransactionError::AccountLocked));
             }
         };
 
@@ -0,0 +1,454 @@
+use crate::domain::{EngineError, PaymentError};
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Ciphertext plus everything needed to decrypt it except the key itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedPayload {
+    /// Id of the keyring entry used to encrypt
+    pub key_id: String,
+    /// Hex-encoded 96-bit AES-GCM nonce
+    pub nonce: String,
+    /// Hex-encoded ciphertext including the authentication tag
+    pub ciphertext: String,
+}
+
+/// AES-256-GCM keys loaded from a local keyfile
+///
+/// The keyfile holds one key per line as `<key_id> <64 hex chars>`; blank lines and
+/// lines starting with `#` are ignored. The last key is the active one used for
+/// encryption, older keys are kept so records written before a rotation stay readable.
+pub struct Keyring {
+    keys: HashMap<String, Aes256Gcm>,
+    active: String,
+}
+
+impl Keyring {
+    /// Load a keyring from a keyfile
+    pub fn load(path: &Path) -> Result<Self, PaymentError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| crypto_error(format!("Cannot read keyfile {}: {}", path.display(), e)))?;
+
+        let mut keys = HashMap::new();
+        let mut active = None;
+
+        for (line_num, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key_id, key_hex) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| crypto_error(format!("Malformed keyfile line {}", line_num + 1)))?;
+            let key_bytes = decode_hex(key_hex.trim())?;
+            let key = Key::<Aes256Gcm>::try_from(key_bytes.as_slice())
+                .map_err(|_| crypto_error(format!("Key {} must be 32 bytes", key_id)))?;
+
+            keys.insert(key_id.to_string(), Aes256Gcm::new(&key));
+            active = Some(key_id.to_string());
+        }
+
+        let active = active
+            .ok_or_else(|| crypto_error(format!("Keyfile {} has no keys", path.display())))?;
+        Ok(Self { keys, active })
+    }
+
+    /// Append a freshly generated key to the keyfile, making it the active key
+    ///
+    /// Creates the keyfile, readable by its owner only, if it does not exist. The new key
+    /// is `k<n>` for one past the highest `k<n>` id in the file, and is synced to disk
+    /// before its id is returned.
+    pub fn rotate(path: &Path) -> Result<String, PaymentError> {
+        let highest = match fs::read_to_string(path) {
+            Ok(content) => content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(|line| line.split_whitespace().next())
+                .filter_map(|key_id| key_id.strip_prefix('k')?.parse::<u64>().ok())
+                .max()
+                .unwrap_or(0),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(crypto_error(e.to_string())),
+        };
+        let key_id = format!("k{}", highest + 1);
+
+        let mut key = [0u8; 32];
+        rand::rng().fill(&mut key);
+
+        let mut file = key_file_options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| crypto_error(e.to_string()))?;
+        writeln!(file, "{} {}", key_id, encode_hex(&key))
+            .and_then(|_| file.sync_all())
+            .map_err(|e| crypto_error(e.to_string()))?;
+
+        Ok(key_id)
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active
+    }
+
+    /// Encrypt with the active key, binding the ciphertext to `aad`
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<SealedPayload, PaymentError> {
+        seal_with(&self.keys[&self.active], &self.active, plaintext, aad)
+    }
+
+    /// Decrypt a sealed payload with the key it was written with
+    pub fn open(&self, sealed: &SealedPayload, aad: &[u8]) -> Result<Vec<u8>, PaymentError> {
+        let cipher = self
+            .keys
+            .get(&sealed.key_id)
+            .ok_or_else(|| crypto_error(format!("Unknown key id {}", sealed.key_id)))?;
+
+        open_with(cipher, sealed, aad)
+    }
+}
+
+/// Per-client data keys enabling crypto-shredding
+///
+/// Every client gets its own AES-256-GCM key, created on first use and stored as
+/// `client-<id>.key` in the key directory (readable by its owner only, replaced atomically
+/// and synced before it is used). Erasing a client destroys its key, after
+/// which everything sealed with it is unreadable for good. The erased generation is
+/// remembered in `client-<id>.erased`, so a client that comes back later gets a new key
+/// whose id never matches the shredded records.
+pub struct ClientKeyStore {
+    dir: PathBuf,
+    keys: RwLock<HashMap<u16, ClientKey>>,
+}
+
+#[derive(Clone)]
+struct ClientKey {
+    key_id: String,
+    cipher: Aes256Gcm,
+}
+
+impl ClientKeyStore {
+    pub fn load(dir: impl Into<PathBuf>) -> Result<Self, PaymentError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| crypto_error(e.to_string()))?;
+
+        Ok(Self {
+            dir,
+            keys: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Encrypt with the client's data key, creating the key if the client has none yet
+    pub fn seal(
+        &self,
+        client_id: u16,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<SealedPayload, PaymentError> {
+        let key = match self.key(client_id)? {
+            Some(key) => key,
+            None => self.create_key(client_id)?,
+        };
+        seal_with(&key.cipher, &key.key_id, plaintext, aad)
+    }
+
+    /// Decrypt with the client's data key
+    ///
+    /// Returns None only if the payload was sealed with a key generation recorded as erased.
+    /// A missing or different key for any other generation (wrong key directory, edited key
+    /// file) is an error, so it can never pass for an erasure.
+    pub fn open(
+        &self,
+        client_id: u16,
+        sealed: &SealedPayload,
+        aad: &[u8],
+    ) -> Result<Option<Vec<u8>>, PaymentError> {
+        let generation = key_generation(client_id, &sealed.key_id)?;
+        let key = self.key(client_id)?;
+        if let Some(key) = &key
+            && key.key_id == sealed.key_id
+        {
+            return open_with(&key.cipher, sealed, aad).map(Some);
+        }
+
+        if generation > self.erased_generation(client_id)? {
+            return Err(crypto_error(format!(
+                "Key {} of client {} is missing but was never erased",
+                sealed.key_id, client_id
+            )));
+        }
+        // A live key opening it means the key id was relabelled to fake an erasure
+        if let Some(key) = &key
+            && open_with(&key.cipher, sealed, aad).is_ok()
+        {
+            return Err(crypto_error(format!(
+                "Record of client {} claims erased key {} but is sealed with {}",
+                client_id, sealed.key_id, key.key_id
+            )));
+        }
+        Ok(None)
+    }
+
+    /// Destroy the client's data key
+    ///
+    /// Returns false if the client had no key.
+    pub fn erase(&self, client_id: u16) -> Result<bool, PaymentError> {
+        let mut keys = self.keys.write().expect("client key lock poisoned");
+        keys.remove(&client_id);
+
+        let Some((generation, _)) = self.read_key_file(client_id)? else {
+            return Ok(false);
+        };
+
+        // Record the erasure durably before touching the key: a crash in between leaves a
+        // key file that `key` recognises as erased, never a missing key without a record
+        let erased_path = self.erased_path(client_id);
+        let tmp_path = erased_path.with_extension("erased.tmp");
+        let mut file = File::create(&tmp_path).map_err(|e| crypto_error(e.to_string()))?;
+        write!(file, "{}", generation)
+            .and_then(|_| file.sync_all())
+            .and_then(|_| fs::rename(&tmp_path, &erased_path))
+            .and_then(|_| File::open(&self.dir)?.sync_all())
+            .map_err(|e| crypto_error(e.to_string()))?;
+
+        self.destroy_key_file(client_id)?;
+        Ok(true)
+    }
+
+    /// Overwrite and unlink the client's key file
+    fn destroy_key_file(&self, client_id: u16) -> Result<(), PaymentError> {
+        let path = self.key_path(client_id);
+        // Overwrite before unlinking so the key bytes do not linger in the old blocks
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .map_err(|e| crypto_error(e.to_string()))?;
+        let len = file
+            .metadata()
+            .map_err(|e| crypto_error(e.to_string()))?
+            .len();
+        file.write_all(&vec![0u8; len as usize])
+            .and_then(|_| file.sync_all())
+            .and_then(|_| fs::remove_file(&path))
+            .and_then(|_| File::open(&self.dir)?.sync_all())
+            .map_err(|e| crypto_error(e.to_string()))
+    }
+
+    fn key(&self, client_id: u16) -> Result<Option<ClientKey>, PaymentError> {
+        if let Some(key) = self
+            .keys
+            .read()
+            .expect("client key lock poisoned")
+            .get(&client_id)
+        {
+            return Ok(Some(key.clone()));
+        }
+
+        let Some((generation, key_hex)) = self.read_key_file(client_id)? else {
+            return Ok(None);
+        };
+        // Left behind by an erasure interrupted after its record was written
+        if generation <= self.erased_generation(client_id)? {
+            self.destroy_key_file(client_id)?;
+            return Ok(None);
+        }
+        let key = client_key(client_id, generation, &key_hex)?;
+        self.keys
+            .write()
+            .expect("client key lock poisoned")
+            .insert(client_id, key.clone());
+        Ok(Some(key))
+    }
+
+    fn create_key(&self, client_id: u16) -> Result<ClientKey, PaymentError> {
+        let mut keys = self.keys.write().expect("client key lock poisoned");
+        if let Some(key) = keys.get(&client_id) {
+            return Ok(key.clone());
+        }
+
+        let generation = self.erased_generation(client_id)? + 1;
+
+        let mut key_bytes = [0u8; 32];
+        rand::rng().fill(&mut key_bytes);
+        let key_hex = encode_hex(&key_bytes);
+        // A crash must not leave a truncated key behind: the client's records would become
+        // unreadable without having been erased
+        let path = self.key_path(client_id);
+        let tmp_path = path.with_extension("key.tmp");
+        let mut file = key_file_options()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&tmp_path)
+            .map_err(|e| crypto_error(e.to_string()))?;
+        writeln!(file, "{} {}", generation, key_hex)
+            .and_then(|_| file.sync_all())
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .and_then(|_| File::open(&self.dir)?.sync_all())
+            .map_err(|e| crypto_error(e.to_string()))?;
+
+        let key = client_key(client_id, generation, &key_hex)?;
+        keys.insert(client_id, key.clone());
+        Ok(key)
+    }
+
+    /// Highest key generation of the client that was erased, 0 if none
+    fn erased_generation(&self, client_id: u16) -> Result<u64, PaymentError> {
+        match fs::read_to_string(self.erased_path(client_id)) {
+            Ok(content) => content.trim().parse().map_err(|_| {
+                crypto_error(format!("Malformed erasure record for client {}", client_id))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(crypto_error(e.to_string())),
+        }
+    }
+
+    fn read_key_file(&self, client_id: u16) -> Result<Option<(u64, String)>, PaymentError> {
+        let content = match fs::read_to_string(self.key_path(client_id)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(crypto_error(e.to_string())),
+        };
+
+        let (generation, key_hex) = content
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| crypto_error(format!("Malformed key file for client {}", client_id)))?;
+        let generation = generation
+            .parse()
+            .map_err(|_| crypto_error(format!("Malformed key file for client {}", client_id)))?;
+        Ok(Some((generation, key_hex.trim().to_string())))
+    }
+
+    fn key_path(&self, client_id: u16) -> PathBuf {
+        self.dir.join(format!("client-{}.key", client_id))
+    }
+
+    fn erased_path(&self, client_id: u16) -> PathBuf {
+        self.dir.join(format!("client-{}.erased", client_id))
+    }
+}
+
+/// Options for files holding key material: readable and writable by the owner only
+fn key_file_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+}
+
+fn client_key(client_id: u16, generation: u64, key_hex: &str) -> Result<ClientKey, PaymentError> {
+    let key_bytes = decode_hex(key_hex)?;
+    let key = Key::<Aes256Gcm>::try_from(key_bytes.as_slice())
+        .map_err(|_| crypto_error(format!("Key of client {} must be 32 bytes", client_id)))?;
+
+    Ok(ClientKey {
+        key_id: format!("client-{}:g{}", client_id, generation),
+        cipher: Aes256Gcm::new(&key),
+    })
+}
+
+/// Generation encoded in a client key id (`client-<id>:g<generation>`)
+fn key_generation(client_id: u16, key_id: &str) -> Result<u64, PaymentError> {
+    key_id
+        .strip_prefix(&format!("client-{}:g", client_id))
+        .and_then(|generation| generation.parse().ok())
+        .ok_or_else(|| {
+            crypto_error(format!(
+                "Key {} does not belong to client {}",
+                key_id, client_id
+            ))
+        })
+}
+
+fn seal_with(
+    cipher: &Aes256Gcm,
+    key_id: &str,
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<SealedPayload, PaymentError> {
+    let mut nonce_bytes = [0u8; 12];
+    rand::rng().fill(&mut nonce_bytes);
+    let nonce = Nonce::from(nonce_bytes);
+
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| crypto_error("Encryption failed".to_string()))?;
+
+    Ok(SealedPayload {
+        key_id: key_id.to_string(),
+        nonce: encode_hex(&nonce_bytes),
+        ciphertext: encode_hex(&ciphertext),
+    })
+}
+
+fn open_with(
+    cipher: &Aes256Gcm,
+    sealed: &SealedPayload,
+    aad: &[u8],
+) -> Result<Vec<u8>, PaymentError> {
+    let nonce_bytes = decode_hex(&sealed.nonce)?;
+    let nonce = Nonce::try_from(nonce_bytes.as_slice())
+        .map_err(|_| crypto_error("Nonce must be 12 bytes".to_string()))?;
+    let ciphertext = decode_hex(&sealed.ciphertext)?;
+
+    cipher
+        .decrypt(
+            &nonce,
+            Payload {
+                msg: &ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| {
+            crypto_error(format!(
+                "Decryption failed with key {} (tampered record or wrong key)",
+                sealed.key_id
+            ))
+        })
+}
+
+fn crypto_error(message: String) -> PaymentError {
+    PaymentError::Engine(EngineError::PersistenceError(message))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, PaymentError> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(crypto_error("Odd-length hex string".to_string()));
+    }
+
+    // Byte-wise, so non-ASCII input is rejected rather than split inside a character
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let digit = |b: u8| (b as char).to_digit(16);
+            match (digit(pair[0]), digit(pair[1])) {
+                (Some(high), Some(low)) => Ok((high * 16 + low) as u8),
+                _ => Err(crypto_error("Invalid hex string".to_string())),
+            }
+        })
+        .collect()
+}
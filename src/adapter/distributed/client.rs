@@ -1,16 +1,19 @@
 use crate::{
     adapter::{
-        CommandProcessor, DisputeIndexCallback, EngineContext, JournalTransactionLookup,
-        PaymentEngine,
+        ActorMetrics, CommandProcessor, DisputeIndexCallback, EngineContext,
+        JournalTransactionLookup, PaymentEngine, SnapshotPolicy, SnapshotTracker, recover_account,
     },
     domain::{
-        AccountState, ActiveAccountState, CommandMetadata, PaymentError, TransactionTypeCommand,
+        AccountState, AppendOutcome, CommandMetadata, EngineError, PaymentError,
+        TransactionTypeCommand,
     },
-    port::{DisputeIndex, Engine, Journal},
+    port::{DisputeIndex, Engine, EventCallback, Journal, Snapshotter},
 };
 use async_trait::async_trait;
 use ractor::{Actor, ActorProcessingErr, ActorRef, RpcReplyPort};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
 
 /// Messages that can be sent to a ClientActor
 pub enum ClientActorMessage {
@@ -20,6 +23,8 @@ pub enum ClientActorMessage {
         RpcReplyPort<Result<(), PaymentError>>,
     ),
     GetState(RpcReplyPort<AccountState>),
+    /// Periodic self-sent tick: passivate when idle for longer than the idle timeout
+    CheckIdle,
 }
 
 impl ractor::Message for ClientActorMessage {}
@@ -28,6 +33,15 @@ pub struct ClientActorArguments {
     pub client_id: u16,
     pub journal: Arc<dyn Journal + Send + Sync>,
     pub dispute_index: Arc<dyn DisputeIndex>,
+    /// Callbacks registered on the engine after the built-in ones
+    pub callbacks: Vec<Arc<dyn EventCallback>>,
+    /// Snapshots of this client; when set, the actor recovers its state on start
+    pub snapshotter: Option<Arc<dyn Snapshotter>>,
+    /// When the actor snapshots its account (only used with a snapshotter)
+    pub snapshot_policy: SnapshotPolicy,
+    /// Passivate (snapshot and stop) after this long without messages
+    pub idle_timeout: Option<Duration>,
+    pub metrics: Arc<ActorMetrics>,
 }
 
 pub struct ClientActorState {
@@ -39,6 +53,80 @@ pub struct ClientActorState {
     /// Used to guarantee events are applied in order: seq[n] > seq[n-1]
     /// Also enables idempotent handling of Kafka at-least-once duplicates
     pub last_sequence: u64,
+    snapshotter: Option<Arc<dyn Snapshotter>>,
+    snapshots: SnapshotTracker,
+    /// Background snapshot write in flight, if any
+    pending_snapshot: Option<JoinHandle<()>>,
+    idle_timeout: Option<Duration>,
+    last_message: Instant,
+    idle_timer: Option<JoinHandle<()>>,
+    metrics: Arc<ActorMetrics>,
+}
+
+/// post_stop does not run when a handler fails: the timer and an in-flight snapshot of
+/// the failed actor must not outlive it
+impl Drop for ClientActorState {
+    fn drop(&mut self) {
+        if let Some(timer) = self.idle_timer.take() {
+            timer.abort();
+        }
+        if let Some(pending) = self.pending_snapshot.take() {
+            pending.abort();
+        }
+    }
+}
+
+impl ClientActorState {
+    /// Snapshot the current account in the background
+    ///
+    /// At most one write is in flight per actor; while one is, the request is dropped and
+    /// the next applied event asks again.
+    fn start_snapshot(&mut self) {
+        let Some(snapshotter) = self.snapshotter.clone() else {
+            return;
+        };
+        if self
+            .pending_snapshot
+            .as_ref()
+            .is_some_and(|pending| !pending.is_finished())
+        {
+            return;
+        }
+
+        let (client_id, sequence_nr) = (self.client_id, self.last_sequence);
+        let account_state = self.account_state.clone();
+        self.snapshots.taken();
+        self.pending_snapshot = Some(tokio::spawn(async move {
+            match snapshotter
+                .save(client_id, sequence_nr, account_state)
+                .await
+            {
+                Ok(()) => tracing::debug!("Client {} snapshot at seq={}", client_id, sequence_nr),
+                Err(e) => tracing::warn!("Client {} snapshot failed: {}", client_id, e),
+            }
+        }));
+    }
+}
+
+/// Engine processing the commands of client accounts persisted to `journal`
+pub(crate) fn client_engine(
+    journal: &Arc<dyn Journal + Send + Sync>,
+    dispute_index: &Arc<dyn DisputeIndex>,
+    callbacks: Vec<Arc<dyn EventCallback>>,
+) -> Arc<dyn Engine<Context = EngineContext> + Send + Sync> {
+    let lookup = Arc::new(JournalTransactionLookup::new(
+        journal.clone(),
+        dispute_index.clone(),
+    ));
+    let processor = Arc::new(CommandProcessor::new(lookup));
+
+    // Register DisputeIndexCallback to maintain infrastructure index via callbacks
+    let dispute_callback = Arc::new(DisputeIndexCallback::new(dispute_index.clone()));
+    let engine = callbacks.into_iter().fold(
+        PaymentEngine::new(processor).with_callback(dispute_callback),
+        |engine, cb| engine.with_callback(cb),
+    );
+    Arc::new(engine)
 }
 
 /// ClientActor manages a single client's account
@@ -61,40 +149,92 @@ impl Actor for ClientActor {
             args.client_id
         );
 
-        let lookup = Arc::new(JournalTransactionLookup::new(
-            args.journal.clone(),
-            args.dispute_index.clone(),
-        ));
-        let processor = Arc::new(CommandProcessor::new(lookup));
-
-        // Register DisputeIndexCallback to maintain infrastructure index via callbacks
-        let dispute_callback = Arc::new(DisputeIndexCallback::new(args.dispute_index.clone()));
-        let engine = Arc::new(PaymentEngine::new(processor).with_callback(dispute_callback));
-
-        // TODO: This should be loaded from snapshot or database ->
-        // If snapshot, then should do snapshot + apply pending events from journal
-        let account_state = AccountState::Active(ActiveAccountState {
-            available: 0.0,
-            held: 0.0,
-            total: 0.0,
-            last_activity: chrono::Utc::now(),
-        });
+        let engine = client_engine(&args.journal, &args.dispute_index, args.callbacks);
+
+        // Latest snapshot (if any) + the client's later events from the journal
+        let recovered = recover_account(
+            args.journal.as_ref(),
+            args.client_id,
+            args.snapshotter.as_deref(),
+        )
+        .await?;
+        tracing::info!(
+            "Client {} recovered at sequence {} ({} events replayed)",
+            args.client_id,
+            recovered.last_sequence,
+            recovered.replayed
+        );
+        // Replayed events count towards the next snapshot
+        let snapshots = SnapshotTracker::new(args.snapshot_policy, recovered.replayed as u64);
 
         Ok(ClientActorState {
             client_id: args.client_id,
-            account_state,
+            account_state: recovered.state,
             engine,
             journal: args.journal,
-            last_sequence: 0, // Start from 0, first event will be sequence 1
+            last_sequence: recovered.last_sequence,
+            snapshotter: args.snapshotter,
+            snapshots,
+            pending_snapshot: None,
+            idle_timeout: args.idle_timeout,
+            last_message: Instant::now(),
+            idle_timer: None,
+            metrics: args.metrics,
         })
     }
 
-    async fn handle(
+    async fn post_start(
+        &self,
+        myself: ActorRef<Self::Msg>,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        // Checking twice per timeout passivates after between 1 and 1.5 timeouts of idleness
+        if let Some(timeout) = state.idle_timeout {
+            let period = (timeout / 2).max(Duration::from_millis(1));
+            state.idle_timer = Some(myself.send_interval(period, || ClientActorMessage::CheckIdle));
+        }
+        Ok(())
+    }
+
+    async fn post_stop(
         &self,
         _myself: ActorRef<Self::Msg>,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        if let Some(timer) = state.idle_timer.take() {
+            timer.abort();
+        }
+        if let Some(pending) = state.pending_snapshot.take() {
+            let _ = pending.await;
+        }
+        if let Some(snapshotter) = &state.snapshotter
+            && state.snapshots.due_on_stop()
+        {
+            // The actor is going away: write the final snapshot before it does
+            if let Err(e) = snapshotter
+                .save(
+                    state.client_id,
+                    state.last_sequence,
+                    state.account_state.clone(),
+                )
+                .await
+            {
+                tracing::warn!("Client {} snapshot on stop failed: {}", state.client_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        myself: ActorRef<Self::Msg>,
         message: Self::Msg,
         state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
+        if !matches!(message, ClientActorMessage::CheckIdle) {
+            state.last_message = Instant::now();
+        }
+
         match message {
             ClientActorMessage::ProcessCommand(command, metadata, reply) => {
                 // CRITICAL: This actor provides ordering guarantees (infrastructure concern)!
@@ -106,7 +246,7 @@ impl Actor for ClientActor {
                 // Flow: validate → persist → verify sequence → update state
                 // If validation fails: state unchanged, nothing persisted ✅
                 // If persistence fails: state unchanged ✅
-                // If sequence is wrong: PANIC (infrastructure bug) ✅
+                // If sequence is wrong: typed error + actor fails, supervisor restarts it ✅
                 // If success: state updated atomically ✅
 
                 let context = EngineContext {
@@ -119,42 +259,37 @@ impl Actor for ClientActor {
                     .process_command(command, metadata, &context)
                     .await
                 {
-                    Ok((envelope, new_state)) => {
+                    Ok((AppendOutcome::Duplicate(envelope), _)) => {
+                        // Redelivery of a command journaled earlier, however long ago:
+                        // it was applied then, so the state is left untouched
+                        tracing::debug!(
+                            "Client {} skipping duplicate command: seq={}",
+                            state.client_id,
+                            envelope.sequence_nr
+                        );
+                        let _ = reply.send(Ok(()));
+                    }
+                    Ok((AppendOutcome::Appended(envelope), new_state)) => {
                         // INFRASTRUCTURE GUARANTEE: Verify event ordering
                         // Sequence numbers are global (shared across all clients in journal),
-                        // so we verify monotonic ordering for this client's events.
-                        //
-                        // Cases:
-                        // 1. seq > last_sequence → Apply (normal case)
-                        // 2. seq == last_sequence → Skip (Kafka at-least-once duplicate)
-                        // 3. seq < last_sequence → PANIC (ordering violation)
-                        //
-                        // Example with Kafka at-least-once:
-                        //   Process seq 5 → state.last_sequence = 5
-                        //   Kafka redelivers → seq 5 again → SKIP (idempotent)
-                        //   Process seq 7 → state.last_sequence = 7 (skip 6, other client)
-
-                        if envelope.sequence_nr < state.last_sequence {
-                            panic!(
-                                "CRITICAL: Event ordering violation for client {}! \
-                                 Last sequence was {}, got {}. This indicates a bug in \
-                                 the infrastructure (out-of-order delivery).",
-                                state.client_id, state.last_sequence, envelope.sequence_nr
-                            );
-                        }
-
-                        if envelope.sequence_nr == state.last_sequence {
-                            // Duplicate event (Kafka at-least-once) - already applied, skip
-                            tracing::debug!(
-                                "Client {} skipping duplicate event: seq={}",
-                                state.client_id,
-                                envelope.sequence_nr
-                            );
-                            let _ = reply.send(Ok(()));
-                            return Ok(());
+                        // so a freshly appended event must come after this client's last one.
+                        // Duplicates never get here: the journal reports them as such.
+                        if envelope.sequence_nr <= state.last_sequence {
+                            // Infrastructure bug (out-of-order delivery): the in-memory state
+                            // can no longer be trusted. Tell the caller, then fail so the
+                            // supervisor restarts the actor with state rebuilt from the journal
+                            let error = PaymentError::Engine(EngineError::SequenceViolation {
+                                client_id: state.client_id,
+                                last_sequence: state.last_sequence,
+                                sequence_nr: envelope.sequence_nr,
+                            });
+                            tracing::error!("CRITICAL: {}", error);
+                            let _ = reply.send(Err(error.clone()));
+                            return Err(Box::new(error));
                         }
 
                         // Normal case: apply new event
+                        let previous = state.last_sequence;
                         state.account_state = new_state;
                         state.last_sequence = envelope.sequence_nr;
 
@@ -162,8 +297,11 @@ impl Actor for ClientActor {
                             "Client {} applied event: seq={} (previous={})",
                             state.client_id,
                             envelope.sequence_nr,
-                            state.last_sequence - 1 // Show actual previous
+                            previous
                         );
+                        if state.snapshots.record_event() {
+                            state.start_snapshot();
+                        }
                         let _ = reply.send(Ok(()));
                     }
                     Err(e) => {
@@ -181,6 +319,21 @@ impl Actor for ClientActor {
             ClientActorMessage::GetState(reply) => {
                 let _ = reply.send(state.account_state.clone());
             }
+
+            ClientActorMessage::CheckIdle => {
+                if let Some(timeout) = state.idle_timeout
+                    && state.last_message.elapsed() >= timeout
+                {
+                    // post_stop snapshots per policy; the registry respawns on the next command
+                    tracing::info!(
+                        "Client {} idle for {:?}, passivating",
+                        state.client_id,
+                        timeout
+                    );
+                    state.metrics.record_passivation();
+                    myself.stop(Some("passivated".to_string()));
+                }
+            }
         }
 
         Ok(())
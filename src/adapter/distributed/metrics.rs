@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Lifecycle counters of the client actors of a registry
+#[derive(Debug, Default)]
+pub struct ActorMetrics {
+    spawned: AtomicU64,
+    passivated: AtomicU64,
+    restarted: AtomicU64,
+    quarantined: AtomicU64,
+}
+
+impl ActorMetrics {
+    /// Client actors spawned (first use, respawn after passivation/shutdown, or restart)
+    pub fn spawned(&self) -> u64 {
+        self.spawned.load(Ordering::Relaxed)
+    }
+
+    /// Client actors stopped after being idle for the idle timeout
+    pub fn passivated(&self) -> u64 {
+        self.passivated.load(Ordering::Relaxed)
+    }
+
+    /// Client actors restarted by the supervisor after a failure
+    pub fn restarted(&self) -> u64 {
+        self.restarted.load(Ordering::Relaxed)
+    }
+
+    /// Clients quarantined after repeated failures
+    pub fn quarantined(&self) -> u64 {
+        self.quarantined.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_spawn(&self) {
+        self.spawned.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_passivation(&self) {
+        self.passivated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_restart(&self) {
+        self.restarted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_quarantine(&self) {
+        self.quarantined.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -1,5 +1,15 @@
 mod client;
+mod metrics;
+mod pool;
+mod recovery;
 mod registry;
+mod retry;
+mod supervisor;
 
 pub use client::*;
+pub use metrics::*;
+pub use pool::*;
+pub use recovery::*;
 pub use registry::*;
+pub use retry::*;
+pub use supervisor::*;
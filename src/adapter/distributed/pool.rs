@@ -0,0 +1,383 @@
+use crate::adapter::{
+    CallTimeouts, EngineContext, RetryPolicy, SnapshotPolicy, SnapshotTracker, client_engine,
+    recover_account,
+};
+use crate::domain::{
+    AccountState, AppendOutcome, CommandMetadata, EngineError, PaymentError, TransactionTypeCommand,
+};
+use crate::port::{ClientExecutor, DisputeIndex, Engine, EventCallback, Journal, Snapshotter};
+use async_trait::async_trait;
+use ractor::{Actor, ActorProcessingErr, ActorRef, RpcReplyPort, rpc::CallResult};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// Messages that can be sent to a PartitionWorker
+pub enum WorkerMessage {
+    ProcessCommand(
+        u16,
+        TransactionTypeCommand,
+        CommandMetadata,
+        RpcReplyPort<Result<(), PaymentError>>,
+    ),
+    GetStates(RpcReplyPort<HashMap<u16, AccountState>>),
+}
+
+impl ractor::Message for WorkerMessage {}
+
+pub struct PartitionWorkerArguments {
+    pub journal: Arc<dyn Journal + Send + Sync>,
+    pub engine: Arc<dyn Engine<Context = EngineContext> + Send + Sync>,
+    /// Snapshots clients are recovered from before replaying the journal
+    pub snapshotter: Option<Arc<dyn Snapshotter>>,
+    /// When each client's account is snapshotted (only used with a snapshotter)
+    pub snapshot_policy: SnapshotPolicy,
+}
+
+pub struct PartitionWorkerState {
+    journal: Arc<dyn Journal + Send + Sync>,
+    engine: Arc<dyn Engine<Context = EngineContext> + Send + Sync>,
+    snapshotter: Option<Arc<dyn Snapshotter>>,
+    snapshot_policy: SnapshotPolicy,
+    /// Accounts owned by this worker
+    accounts: HashMap<u16, PartitionAccount>,
+    /// Background snapshot write in flight, if any
+    pending_snapshot: Option<JoinHandle<()>>,
+}
+
+/// A client account owned by a partition worker
+struct PartitionAccount {
+    state: AccountState,
+    /// Last applied journal sequence
+    last_sequence: u64,
+    snapshots: SnapshotTracker,
+}
+
+/// PartitionWorker owns every client hashed to it and processes their commands one at a
+/// time, which keeps per-client ordering without an actor per client
+pub struct PartitionWorker;
+
+#[async_trait]
+impl Actor for PartitionWorker {
+    type Msg = WorkerMessage;
+    type State = PartitionWorkerState;
+    type Arguments = PartitionWorkerArguments;
+
+    async fn pre_start(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        args: Self::Arguments,
+    ) -> Result<Self::State, ActorProcessingErr> {
+        Ok(PartitionWorkerState {
+            journal: args.journal,
+            engine: args.engine,
+            snapshotter: args.snapshotter,
+            snapshot_policy: args.snapshot_policy,
+            accounts: HashMap::new(),
+            pending_snapshot: None,
+        })
+    }
+
+    async fn post_stop(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        if let Some(pending) = state.pending_snapshot.take() {
+            let _ = pending.await;
+        }
+        let Some(snapshotter) = &state.snapshotter else {
+            return Ok(());
+        };
+        // The worker is going away: write the final snapshots before it does
+        for (client_id, account) in &state.accounts {
+            if !account.snapshots.due_on_stop() {
+                continue;
+            }
+            if let Err(e) = snapshotter
+                .save(*client_id, account.last_sequence, account.state.clone())
+                .await
+            {
+                tracing::warn!("Client {} snapshot on stop failed: {}", client_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        message: Self::Msg,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        match message {
+            WorkerMessage::ProcessCommand(client_id, command, metadata, reply) => {
+                let _ = reply.send(state.process(client_id, command, metadata).await);
+            }
+            WorkerMessage::GetStates(reply) => {
+                let states = state
+                    .accounts
+                    .iter()
+                    .map(|(client_id, account)| (*client_id, account.state.clone()))
+                    .collect();
+                let _ = reply.send(states);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PartitionWorkerState {
+    async fn process(
+        &mut self,
+        client_id: u16,
+        command: TransactionTypeCommand,
+        metadata: CommandMetadata,
+    ) -> Result<(), PaymentError> {
+        // First command for this client: rebuild its account like a spawned ClientActor
+        if !self.accounts.contains_key(&client_id) {
+            let recovered = recover_account(
+                self.journal.as_ref(),
+                client_id,
+                self.snapshotter.as_deref(),
+            )
+            .await?;
+            self.accounts.insert(
+                client_id,
+                PartitionAccount {
+                    state: recovered.state,
+                    last_sequence: recovered.last_sequence,
+                    // Replayed events count towards the next snapshot
+                    snapshots: SnapshotTracker::new(
+                        self.snapshot_policy.clone(),
+                        recovered.replayed as u64,
+                    ),
+                },
+            );
+        }
+        let account = self
+            .accounts
+            .get_mut(&client_id)
+            .expect("account recovered above");
+
+        let context = EngineContext {
+            journal: self.journal.clone(),
+            current_state: account.state.clone(),
+        };
+        let (outcome, new_state) = self
+            .engine
+            .process_command(command, metadata, &context)
+            .await?;
+        let AppendOutcome::Appended(envelope) = outcome else {
+            // Redelivered command: applied when it was first journaled
+            return Ok(());
+        };
+
+        // Same ordering guarantee as ClientActor: sequences only move forward per client
+        if envelope.sequence_nr <= account.last_sequence {
+            let last_sequence = account.last_sequence;
+            // The event is journaled but the cached account cannot be trusted: drop it so
+            // the next command recovers the client from the journal, like a restarted actor
+            self.accounts.remove(&client_id);
+            return Err(PaymentError::Engine(EngineError::SequenceViolation {
+                client_id,
+                last_sequence,
+                sequence_nr: envelope.sequence_nr,
+            }));
+        }
+        account.state = new_state;
+        account.last_sequence = envelope.sequence_nr;
+        if account.snapshots.record_event() {
+            self.start_snapshot(client_id);
+        }
+        Ok(())
+    }
+
+    /// Snapshot a client's account in the background
+    ///
+    /// At most one write is in flight per worker; while one is, the request is dropped and
+    /// the client's next applied event asks again.
+    fn start_snapshot(&mut self, client_id: u16) {
+        let Some(snapshotter) = self.snapshotter.clone() else {
+            return;
+        };
+        if self
+            .pending_snapshot
+            .as_ref()
+            .is_some_and(|pending| !pending.is_finished())
+        {
+            return;
+        }
+        let Some(account) = self.accounts.get_mut(&client_id) else {
+            return;
+        };
+
+        let (sequence_nr, account_state) = (account.last_sequence, account.state.clone());
+        account.snapshots.taken();
+        self.pending_snapshot = Some(tokio::spawn(async move {
+            match snapshotter
+                .save(client_id, sequence_nr, account_state)
+                .await
+            {
+                Ok(()) => tracing::debug!("Client {} snapshot at seq={}", client_id, sequence_nr),
+                Err(e) => tracing::warn!("Client {} snapshot failed: {}", client_id, e),
+            }
+        }));
+    }
+}
+
+/// Fixed pool of PartitionWorkers, each owning the clients hashed to it
+///
+/// An alternative to one `ClientActor` per client: the number of actors stays constant
+/// however many clients are seen. Commands use the same `CallTimeouts` and `RetryPolicy`
+/// as the registry, and workers snapshot each client per `SnapshotPolicy`. There is no
+/// supervision: a worker that fails is not restarted, and its clients become unavailable.
+pub struct WorkerPool {
+    workers: Vec<ActorRef<WorkerMessage>>,
+    timeouts: CallTimeouts,
+    retry: RetryPolicy,
+}
+
+impl WorkerPool {
+    pub async fn start(
+        workers: usize,
+        journal: Arc<dyn Journal + Send + Sync>,
+        dispute_index: Arc<dyn DisputeIndex>,
+        callbacks: Vec<Arc<dyn EventCallback>>,
+        snapshotter: Option<Arc<dyn Snapshotter>>,
+        snapshot_policy: SnapshotPolicy,
+    ) -> Result<Self, PaymentError> {
+        // One engine shared by every worker: it holds no per-client state
+        let engine = client_engine(&journal, &dispute_index, callbacks);
+
+        let mut refs = Vec::with_capacity(workers.max(1));
+        for _ in 0..workers.max(1) {
+            let args = PartitionWorkerArguments {
+                journal: journal.clone(),
+                engine: engine.clone(),
+                snapshotter: snapshotter.clone(),
+                snapshot_policy: snapshot_policy.clone(),
+            };
+            let (worker, _handle) =
+                Actor::spawn(None, PartitionWorker, args)
+                    .await
+                    .map_err(|e| {
+                        PaymentError::Engine(EngineError::SpawnFailed(format!(
+                            "partition worker: {:?}",
+                            e
+                        )))
+                    })?;
+            refs.push(worker);
+        }
+        Ok(Self {
+            workers: refs,
+            timeouts: CallTimeouts::default(),
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Configure how long callers wait for a worker
+    pub fn with_timeouts(mut self, timeouts: CallTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Configure how failed commands are retried
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Index of the worker owning `client_id`
+    pub fn partition(&self, client_id: u16) -> usize {
+        // Fibonacci hashing spreads neighbouring ids without a hasher per call
+        let hash = (client_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        ((hash >> 32) % self.workers.len() as u64) as usize
+    }
+
+    /// Single attempt at processing a command
+    async fn send_command(
+        &self,
+        client_id: u16,
+        command: TransactionTypeCommand,
+        metadata: CommandMetadata,
+    ) -> Result<(), PaymentError> {
+        let worker = &self.workers[self.partition(client_id)];
+        let timeout = self.timeouts.command;
+        match worker
+            .call(
+                |reply| WorkerMessage::ProcessCommand(client_id, command, metadata, reply),
+                Some(timeout),
+            )
+            .await
+        {
+            Ok(CallResult::Success(result)) => result,
+            Ok(CallResult::Timeout) => Err(PaymentError::Engine(EngineError::CallTimeout {
+                client_id,
+                timeout_ms: timeout.as_millis() as u64,
+            })),
+            Ok(CallResult::SenderError) => {
+                Err(PaymentError::Engine(EngineError::ActorUnavailable {
+                    client_id,
+                    reason: "partition worker stopped before answering".to_string(),
+                }))
+            }
+            Err(e) => Err(PaymentError::Engine(EngineError::ActorUnavailable {
+                client_id,
+                reason: format!("failed to send command to partition worker: {:?}", e),
+            })),
+        }
+    }
+}
+
+#[async_trait]
+impl ClientExecutor for WorkerPool {
+    async fn process_command(
+        &self,
+        client_id: u16,
+        command: TransactionTypeCommand,
+        metadata: CommandMetadata,
+    ) -> Result<(), PaymentError> {
+        self.retry
+            .run(client_id, || {
+                self.send_command(client_id, command.clone(), metadata.clone())
+            })
+            .await
+    }
+
+    async fn get_all_states(&self) -> Result<HashMap<u16, AccountState>, PaymentError> {
+        let mut states = HashMap::new();
+        for worker in &self.workers {
+            match worker
+                .call(WorkerMessage::GetStates, Some(self.timeouts.state))
+                .await
+            {
+                Ok(CallResult::Success(worker_states)) => states.extend(worker_states),
+                Ok(_) => {
+                    return Err(PaymentError::Engine(EngineError::Unavailable(
+                        "partition worker did not return its states".to_string(),
+                    )));
+                }
+                Err(e) => {
+                    return Err(PaymentError::Engine(EngineError::Unavailable(format!(
+                        "failed to get states from partition worker: {:?}",
+                        e
+                    ))));
+                }
+            }
+        }
+        Ok(states)
+    }
+
+    async fn shutdown_all(&self) {
+        for worker in &self.workers {
+            if let Err(e) = worker.stop_and_wait(None, None).await {
+                tracing::warn!("Failed to stop partition worker: {:?}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::domain::{AccountState, EngineError, JournalCursor, JournalQuery, PaymentError};
+use crate::port::{EventHandler, Journal, Snapshotter};
+
+/// Events read from the journal per query while recovering
+const RECOVERY_PAGE_SIZE: usize = 1000;
+
+/// Account state of a client rebuilt from the journal
+pub struct RecoveredAccount {
+    pub state: AccountState,
+    /// Sequence of the last event included in `state` (0 for a new client)
+    pub last_sequence: u64,
+    /// Events replayed on top of the snapshot (or the empty account)
+    pub replayed: usize,
+}
+
+/// Rebuild a client's account by replaying its journal events
+///
+/// Starts from the client's latest snapshot when a snapshotter has one, and from an empty
+/// account otherwise.
+pub async fn recover_account(
+    journal: &(dyn Journal + Send + Sync),
+    client_id: u16,
+    snapshotter: Option<&dyn Snapshotter>,
+) -> Result<RecoveredAccount, PaymentError> {
+    let snapshot = match snapshotter {
+        Some(snapshotter) => snapshotter.load(client_id).await?,
+        None => None,
+    };
+    let (mut last_sequence, mut state) = snapshot.unwrap_or_else(|| (0, AccountState::empty()));
+
+    let mut replayed = 0;
+    loop {
+        let page = journal
+            .query(&JournalQuery {
+                client_id: Some(client_id),
+                after: Some(JournalCursor::after(last_sequence)),
+                limit: RECOVERY_PAGE_SIZE,
+                ..Default::default()
+            })
+            .await?;
+
+        for envelope in &page.envelopes {
+            // Every journaled event applied when it was written: one that no longer does
+            // means the history or the handlers changed, and skipping it would misstate
+            // the balance
+            state = envelope.apply(&state).ok_or(PaymentError::Engine(
+                EngineError::StateTransitionFailed {
+                    sequence_nr: envelope.sequence_nr,
+                },
+            ))?;
+            last_sequence = envelope.sequence_nr;
+            replayed += 1;
+        }
+        if page.next_cursor.is_none() {
+            break;
+        }
+    }
+
+    Ok(RecoveredAccount {
+        state,
+        last_sequence,
+        replayed,
+    })
+}
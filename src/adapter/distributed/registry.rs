@@ -1,15 +1,39 @@
-use crate::adapter::{ClientActorArguments, ClientActorMessage};
+use crate::adapter::{
+    ActorMetrics, CallTimeouts, ClientActorMessage, ClientActorTemplate, ClientSupervisor,
+    ClientSupervisorArguments, RetryPolicy, SnapshotPolicy, SupervisionConfig, SupervisorMessage,
+    WorkerPool, recover_account,
+};
 use crate::domain::{
     AccountState, CommandMetadata, EngineError, PaymentError, TransactionTypeCommand,
 };
-use crate::port::{DisputeIndex, Journal};
+use crate::port::{ClientExecutor, DisputeIndex, EventCallback, Journal, Snapshotter};
+use async_trait::async_trait;
 use ractor::{Actor, ActorRef, registry, rpc::CallResult};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::OnceCell;
 
 type ClientActorRef = ActorRef<ClientActorMessage>;
 
+/// Global name of a client's actor
+pub(crate) fn client_actor_name(namespace: &str, client_id: u16) -> String {
+    if namespace.is_empty() {
+        format!("client-{}", client_id)
+    } else {
+        format!("{}-client-{}", namespace, client_id)
+    }
+}
+
+/// Supervisor of a registry's actors, stopped with the last registry clone
+struct SupervisorHandle(ActorRef<SupervisorMessage>);
+
+impl Drop for SupervisorHandle {
+    fn drop(&mut self) {
+        self.0.stop(None);
+    }
+}
+
 /// ClientRegistry uses ractor's global registry for distributed actor lookup
 ///
 /// Instead of maintaining a local DashMap (split-brain risk), we rely on
@@ -25,6 +49,22 @@ pub struct ClientRegistry {
     dispute_index: Arc<dyn DisputeIndex>,
     /// Namespace prefix for actor names (for test isolation)
     namespace: String,
+    /// Extra callbacks registered on every spawned actor's engine
+    callbacks: Vec<Arc<dyn EventCallback>>,
+    /// Snapshot store actors recover from when spawned
+    snapshots: Option<Arc<dyn Snapshotter>>,
+    /// When spawned actors snapshot their account
+    snapshot_policy: SnapshotPolicy,
+    /// Idle time after which spawned actors passivate
+    idle_timeout: Option<Duration>,
+    metrics: Arc<ActorMetrics>,
+    supervision: SupervisionConfig,
+    timeouts: CallTimeouts,
+    retry: RetryPolicy,
+    /// Clients an actor was spawned for, including those passivated since
+    known_clients: Arc<Mutex<BTreeSet<u16>>>,
+    /// Started on the first spawn, with the configuration at that time
+    supervisor: Arc<OnceCell<SupervisorHandle>>,
 }
 
 impl ClientRegistry {
@@ -36,6 +76,16 @@ impl ClientRegistry {
             journal,
             dispute_index,
             namespace: String::new(),
+            callbacks: Vec::new(),
+            snapshots: None,
+            snapshot_policy: SnapshotPolicy::default(),
+            idle_timeout: None,
+            metrics: Arc::new(ActorMetrics::default()),
+            supervision: SupervisionConfig::default(),
+            timeouts: CallTimeouts::default(),
+            retry: RetryPolicy::default(),
+            known_clients: Arc::new(Mutex::new(BTreeSet::new())),
+            supervisor: Arc::new(OnceCell::new()),
         }
     }
 
@@ -51,150 +101,322 @@ impl ClientRegistry {
             journal,
             dispute_index,
             namespace,
+            callbacks: Vec::new(),
+            snapshots: None,
+            snapshot_policy: SnapshotPolicy::default(),
+            idle_timeout: None,
+            metrics: Arc::new(ActorMetrics::default()),
+            supervision: SupervisionConfig::default(),
+            timeouts: CallTimeouts::default(),
+            retry: RetryPolicy::default(),
+            known_clients: Arc::new(Mutex::new(BTreeSet::new())),
+            supervisor: Arc::new(OnceCell::new()),
         }
     }
 
+    /// Start spawned actors from their latest snapshot instead of replaying the whole journal
+    pub fn with_snapshots(mut self, snapshots: Arc<dyn Snapshotter>) -> Self {
+        self.snapshots = Some(snapshots);
+        self
+    }
+
+    /// Snapshot spawned actors according to `policy` (requires `with_snapshots`)
+    pub fn with_snapshot_policy(mut self, policy: SnapshotPolicy) -> Self {
+        self.snapshot_policy = policy;
+        self
+    }
+
+    /// Passivate actors idle for `timeout`: they snapshot per policy and stop, and the next
+    /// command for the client respawns and recovers them
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Restart failed actors and quarantine clients according to `config`
+    pub fn with_supervision(mut self, config: SupervisionConfig) -> Self {
+        self.supervision = config;
+        self
+    }
+
+    /// Wait for actors according to `timeouts`
+    pub fn with_timeouts(mut self, timeouts: CallTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Retry commands that failed transiently according to `policy`
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Spawn, passivation, restart and quarantine counters of this registry's actors
+    pub fn metrics(&self) -> &ActorMetrics {
+        &self.metrics
+    }
+
+    /// The dispute index this registry's actors maintain
+    pub fn dispute_index(&self) -> Arc<dyn DisputeIndex> {
+        self.dispute_index.clone()
+    }
+
+    /// Start a worker pool sharing this registry's journal, dispute index, callbacks,
+    /// snapshots, timeouts and retry policy, to execute commands with `workers` actors
+    /// instead of one per client
+    pub async fn worker_pool(&self, workers: usize) -> Result<WorkerPool, PaymentError> {
+        Ok(WorkerPool::start(
+            workers,
+            self.journal.clone(),
+            self.dispute_index.clone(),
+            self.callbacks.clone(),
+            self.snapshots.clone(),
+            self.snapshot_policy.clone(),
+        )
+        .await?
+        .with_timeouts(self.timeouts.clone())
+        .with_retry_policy(self.retry.clone()))
+    }
+
+    /// Register a callback on the engine of every client actor spawned from now on
+    pub fn with_callback(mut self, callback: Arc<dyn EventCallback>) -> Self {
+        self.callbacks.push(callback);
+        self
+    }
+
     /// Get or spawn a client actor using ractor's global registry
     ///
     /// This is cluster-safe: ActorRef::where_is() checks the global registry,
     /// preventing split-brain issues where multiple nodes spawn the same client actor.
     pub async fn get_or_spawn(&self, client_id: u16) -> Result<ClientActorRef, PaymentError> {
-        let actor_name = if self.namespace.is_empty() {
-            format!("client-{}", client_id)
-        } else {
-            format!("{}-client-{}", self.namespace, client_id)
-        };
-
         // Fast path: check ractor's global registry
-        if let Some(actor_ref) = ActorRef::<ClientActorMessage>::where_is(actor_name.clone()) {
+        let actor_name = client_actor_name(&self.namespace, client_id);
+        if let Some(actor_ref) = ActorRef::<ClientActorMessage>::where_is(actor_name) {
             return Ok(actor_ref);
         }
 
-        // Slow path: spawn actor with global name
-        // Race condition: another node might spawn it first and that's fine.
-        // The registry ensures only one actor with this name exists cluster-wide
-        let args = ClientActorArguments {
-            client_id,
-            journal: self.journal.clone(),
-            dispute_index: self.dispute_index.clone(),
-        };
-
-        match Actor::spawn(Some(actor_name.clone()), super::client::ClientActor, args).await {
-            Ok((actor_ref, _handle)) => Ok(actor_ref),
-            Err(e) => {
-                // Spawn failed - maybe another node just spawned it?
-                // Try lookup one more time before giving up
-                if let Some(actor_ref) = ActorRef::<ClientActorMessage>::where_is(actor_name) {
-                    Ok(actor_ref)
-                } else {
-                    Err(PaymentError::Engine(EngineError::ValidationError(format!(
-                        "Failed to spawn or find client actor: {:?}",
-                        e
-                    ))))
+        // Slow path: the supervisor spawns the actor with its global name, linked to itself.
+        // Spawns are serialized through it, and another node spawning it first is fine: the
+        // global registry ensures only one actor with this name exists cluster-wide
+        let supervisor = self.supervisor().await?;
+        let timeout = self.timeouts.spawn;
+        match supervisor
+            .call(
+                |reply| SupervisorMessage::GetOrSpawn(client_id, reply),
+                Some(timeout),
+            )
+            .await
+        {
+            Ok(CallResult::Success(result)) => {
+                if result.is_ok() {
+                    self.known_clients().insert(client_id);
                 }
+                result
             }
+            Ok(CallResult::Timeout) => Err(call_timeout(client_id, timeout)),
+            Ok(CallResult::SenderError) => Err(PaymentError::Engine(EngineError::Unavailable(
+                format!("client supervisor did not answer for client {}", client_id),
+            ))),
+            Err(e) => Err(PaymentError::Engine(EngineError::Unavailable(format!(
+                "client supervisor unreachable: {:?}",
+                e
+            )))),
         }
     }
 
+    /// True when the client was quarantined after repeated actor failures
+    pub async fn is_quarantined(&self, client_id: u16) -> Result<bool, PaymentError> {
+        let supervisor = self.supervisor().await?;
+        match supervisor
+            .call(
+                |reply| SupervisorMessage::IsQuarantined(client_id, reply),
+                Some(self.timeouts.spawn),
+            )
+            .await
+        {
+            Ok(CallResult::Success(quarantined)) => Ok(quarantined),
+            _ => Err(PaymentError::Engine(EngineError::Unavailable(
+                "client supervisor did not answer".to_string(),
+            ))),
+        }
+    }
+
+    /// Lift a client's quarantine: its next command spawns a fresh actor
+    pub async fn release(&self, client_id: u16) -> Result<(), PaymentError> {
+        let supervisor = self.supervisor().await?;
+        supervisor
+            .cast(SupervisorMessage::Release(client_id))
+            .map_err(|e| {
+                PaymentError::Engine(EngineError::Unavailable(format!(
+                    "failed to reach client supervisor: {:?}",
+                    e
+                )))
+            })
+    }
+
+    async fn supervisor(&self) -> Result<ActorRef<SupervisorMessage>, PaymentError> {
+        let handle = self
+            .supervisor
+            .get_or_try_init(|| async {
+                let args = ClientSupervisorArguments {
+                    template: ClientActorTemplate {
+                        namespace: self.namespace.clone(),
+                        journal: self.journal.clone(),
+                        dispute_index: self.dispute_index.clone(),
+                        callbacks: self.callbacks.clone(),
+                        snapshotter: self.snapshots.clone(),
+                        snapshot_policy: self.snapshot_policy.clone(),
+                        idle_timeout: self.idle_timeout,
+                        metrics: self.metrics.clone(),
+                    },
+                    config: self.supervision.clone(),
+                };
+                let (supervisor, _handle) = Actor::spawn(None, ClientSupervisor, args)
+                    .await
+                    .map_err(|e| {
+                        PaymentError::Engine(EngineError::SpawnFailed(format!(
+                            "client supervisor: {:?}",
+                            e
+                        )))
+                    })?;
+                Ok::<_, PaymentError>(SupervisorHandle(supervisor))
+            })
+            .await?;
+        Ok(handle.0.clone())
+    }
+
     /// Process a command for a client (get_or_spawn + send message)
+    ///
+    /// Retryable failures (`PaymentError::is_retryable`) are retried with backoff; the last
+    /// one is returned once the policy gives up.
     pub async fn process_command(
         &self,
         client_id: u16,
         command: TransactionTypeCommand,
         metadata: CommandMetadata,
     ) -> Result<(), PaymentError> {
-        let actor_ref = self.get_or_spawn(client_id).await?;
+        self.retry
+            .run(client_id, || {
+                self.send_command(client_id, command.clone(), metadata.clone())
+            })
+            .await
+    }
 
+    /// Single attempt at processing a command
+    async fn send_command(
+        &self,
+        client_id: u16,
+        command: TransactionTypeCommand,
+        metadata: CommandMetadata,
+    ) -> Result<(), PaymentError> {
+        let actor_ref = self.get_or_spawn(client_id).await?;
+        let timeout = self.timeouts.command;
         match actor_ref
             .call(
                 |reply| ClientActorMessage::ProcessCommand(command, metadata, reply),
-                Some(Duration::from_millis(500)),
+                Some(timeout),
             )
             .await
         {
-            Ok(CallResult::Success(Ok(()))) => Ok(()),
-            Ok(CallResult::Success(Err(e))) => Err(e),
-            Ok(CallResult::Timeout) => Err(PaymentError::Engine(EngineError::ValidationError(
-                "Actor call timeout".to_string(),
-            ))),
-            Ok(CallResult::SenderError) => Err(PaymentError::Engine(EngineError::ValidationError(
-                "Actor sender error".to_string(),
-            ))),
-            Err(e) => Err(PaymentError::Engine(EngineError::ValidationError(format!(
-                "Failed to send command to client actor: {:?}",
-                e
-            )))),
+            Ok(CallResult::Success(result)) => result,
+            Ok(CallResult::Timeout) => Err(call_timeout(client_id, timeout)),
+            // The actor stopped (e.g. passivated) before handling the command: it was not
+            // processed, and the next attempt respawns the actor
+            Ok(CallResult::SenderError) => {
+                Err(PaymentError::Engine(EngineError::ActorUnavailable {
+                    client_id,
+                    reason: "actor stopped before answering".to_string(),
+                }))
+            }
+            Err(e) => Err(PaymentError::Engine(EngineError::ActorUnavailable {
+                client_id,
+                reason: format!("failed to send command: {:?}", e),
+            })),
         }
     }
 
     /// Get state for a specific client (uses global registry lookup)
     pub async fn get_state(&self, client_id: u16) -> Result<Option<AccountState>, PaymentError> {
-        let actor_name = if self.namespace.is_empty() {
-            format!("client-{}", client_id)
-        } else {
-            format!("{}-client-{}", self.namespace, client_id)
-        };
+        let actor_name = client_actor_name(&self.namespace, client_id);
 
         if let Some(actor_ref) = ActorRef::<ClientActorMessage>::where_is(actor_name) {
+            let timeout = self.timeouts.state;
             match actor_ref
-                .call(
-                    ClientActorMessage::GetState,
-                    Some(Duration::from_millis(100)),
-                )
+                .call(ClientActorMessage::GetState, Some(timeout))
                 .await
             {
                 Ok(CallResult::Success(state)) => Ok(Some(state)),
-                Ok(CallResult::Timeout) => Err(PaymentError::Engine(EngineError::ValidationError(
-                    "Actor call timeout".to_string(),
-                ))),
-                Ok(CallResult::SenderError) => Err(PaymentError::Engine(
-                    EngineError::ValidationError("Actor sender error".to_string()),
-                )),
-                Err(e) => Err(PaymentError::Engine(EngineError::ValidationError(format!(
-                    "Failed to get state from client actor: {:?}",
-                    e
-                )))),
+                Ok(CallResult::Timeout) => Err(call_timeout(client_id, timeout)),
+                Ok(CallResult::SenderError) => {
+                    Err(PaymentError::Engine(EngineError::ActorUnavailable {
+                        client_id,
+                        reason: "actor stopped before answering".to_string(),
+                    }))
+                }
+                Err(e) => Err(PaymentError::Engine(EngineError::ActorUnavailable {
+                    client_id,
+                    reason: format!("failed to get state: {:?}", e),
+                })),
             }
         } else {
             Ok(None)
         }
     }
 
-    /// Get all client states by querying ractor's global registry
+    /// Get all client states: those of the actors in ractor's global registry, and those of
+    /// the clients this registry spawned an actor for since
     ///
-    /// Discovers all client actors by listing registered actors and filtering
-    /// for those matching our naming pattern.
+    /// Live actors are asked for their state; clients whose actor was passivated (or is
+    /// passivating) are recovered from their snapshot and the journal without respawning
+    /// them. Quarantined clients are left out. Fails if a state cannot be read, instead of
+    /// reporting the accounts without it.
     pub async fn get_all_states(&self) -> Result<HashMap<u16, AccountState>, PaymentError> {
-        let mut states = HashMap::new();
-
-        let registered_names = registry::registered();
-
         let prefix = if self.namespace.is_empty() {
             "client-".to_string()
         } else {
             format!("{}-client-", self.namespace)
         };
+        let mut clients = self.known_clients().clone();
+        clients.extend(registry::registered().iter().filter_map(|name| {
+            name.strip_prefix(&prefix)
+                .and_then(|id| id.parse::<u16>().ok())
+        }));
+        let mut states = HashMap::with_capacity(clients.len());
 
-        for name in registered_names {
-            if name.starts_with(&prefix) {
-                if let Some(id_str) = name.strip_prefix(&prefix) {
-                    if let Ok(client_id) = id_str.parse::<u16>() {
-                        if let Ok(Some(state)) = self.get_state(client_id).await {
-                            states.insert(client_id, state);
-                        } else {
-                            tracing::warn!("Failed to get state for client {}", client_id);
-                        }
+        for client_id in clients {
+            match self.get_state(client_id).await {
+                Ok(Some(state)) => {
+                    states.insert(client_id, state);
+                }
+                // The actor stopped since: its account is all in the snapshot and journal
+                Ok(None) | Err(PaymentError::Engine(EngineError::ActorUnavailable { .. })) => {
+                    if self.is_quarantined(client_id).await? {
+                        continue;
                     }
+                    let recovered = recover_account(
+                        self.journal.as_ref(),
+                        client_id,
+                        self.snapshots.as_deref(),
+                    )
+                    .await?;
+                    states.insert(client_id, recovered.state);
                 }
+                Err(e) => return Err(e),
             }
         }
 
         Ok(states)
     }
 
+    fn known_clients(&self) -> std::sync::MutexGuard<'_, BTreeSet<u16>> {
+        self.known_clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     /// Shutdown all client actors in this namespace
     ///
-    /// Discovers actors via ractor's global registry and stops them.
+    /// Discovers actors via ractor's global registry and stops them, waiting for each to exit.
     /// In a distributed system, this only affects actors visible in the global registry.
     pub async fn shutdown_all(&self) {
         let registered_names = registry::registered();
@@ -206,12 +428,42 @@ impl ClientRegistry {
         };
 
         for name in registered_names {
-            if name.starts_with(&prefix) {
-                if let Some(actor_ref) = ActorRef::<ClientActorMessage>::where_is(name.clone()) {
-                    actor_ref.stop(None);
-                    tracing::debug!("Stopped actor: {}", name);
+            if name.starts_with(&prefix)
+                && let Some(actor_ref) = ActorRef::<ClientActorMessage>::where_is(name.clone())
+            {
+                // Wait for the actor to exit so its name is free for a respawn
+                if let Err(e) = actor_ref.stop_and_wait(None, None).await {
+                    tracing::warn!("Failed to stop actor {}: {:?}", name, e);
                 }
+                tracing::debug!("Stopped actor: {}", name);
             }
         }
     }
 }
+
+fn call_timeout(client_id: u16, timeout: Duration) -> PaymentError {
+    PaymentError::Engine(EngineError::CallTimeout {
+        client_id,
+        timeout_ms: timeout.as_millis() as u64,
+    })
+}
+
+#[async_trait]
+impl ClientExecutor for ClientRegistry {
+    async fn process_command(
+        &self,
+        client_id: u16,
+        command: TransactionTypeCommand,
+        metadata: CommandMetadata,
+    ) -> Result<(), PaymentError> {
+        ClientRegistry::process_command(self, client_id, command, metadata).await
+    }
+
+    async fn get_all_states(&self) -> Result<HashMap<u16, AccountState>, PaymentError> {
+        ClientRegistry::get_all_states(self).await
+    }
+
+    async fn shutdown_all(&self) {
+        ClientRegistry::shutdown_all(self).await
+    }
+}
@@ -0,0 +1,118 @@
+use crate::domain::PaymentError;
+use rand::Rng;
+use std::time::Duration;
+
+/// How long the registry waits for a client actor to answer
+#[derive(Debug, Clone)]
+pub struct CallTimeouts {
+    /// Processing a command (includes journal persistence)
+    pub command: Duration,
+    /// Reading an account state
+    pub state: Duration,
+    /// Waiting for the supervisor to spawn an actor, which includes recovering its account
+    /// and any spawns queued before it
+    pub spawn: Duration,
+}
+
+impl Default for CallTimeouts {
+    fn default() -> Self {
+        Self {
+            command: Duration::from_millis(500),
+            state: Duration::from_millis(100),
+            spawn: Duration::from_secs(30),
+        }
+    }
+}
+
+/// How the registry retries a command after a transient failure
+///
+/// Only retryable errors (`ErrorCategory::InfrastructureTransient`: timeouts and actors
+/// that stopped or failed before answering) are retried. An attempt that timed out may
+/// still have been journaled; its retry is safe because the engine looks the command's
+/// deduplication key up before validating it, and answers it as a duplicate instead of
+/// checking it again against a state that already includes its effects.
+///
+/// The n-th retry waits `initial_backoff * multiplier^(n-1)`, capped at `max_backoff`,
+/// with up to `jitter` of that delay removed at random so callers do not retry in step.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: usize,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// Fraction (0.0 to 1.0) of each delay that is randomized
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(100),
+            multiplier: 2.0,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Give up on the first failure
+    pub fn never() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the given retry (1-based)
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let factor = self.multiplier.max(1.0).powi(exponent).min(1e9);
+        let delay = Duration::from_secs_f64(
+            (self.initial_backoff.as_secs_f64() * factor).min(self.max_backoff.as_secs_f64()),
+        );
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - jitter * rand::rng().random::<f64>())
+    }
+
+    /// Run `attempt` until it succeeds or fails for good
+    ///
+    /// Retryable failures (`PaymentError::is_retryable`) are retried with backoff; the last
+    /// one is returned once the policy gives up.
+    pub(crate) async fn run<F, Fut>(
+        &self,
+        client_id: u16,
+        mut attempt: F,
+    ) -> Result<(), PaymentError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<(), PaymentError>>,
+    {
+        let mut retry = 0;
+        loop {
+            let error = match attempt().await {
+                Err(error) if error.is_retryable() => error,
+                result => return result,
+            };
+
+            retry += 1;
+            if retry > self.max_retries {
+                return Err(error);
+            }
+            tracing::debug!(
+                "Client {} command failed ({}), retry {}/{}",
+                client_id,
+                error,
+                retry,
+                self.max_retries
+            );
+            tokio::time::sleep(self.backoff(retry)).await;
+        }
+    }
+}
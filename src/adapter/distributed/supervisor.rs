@@ -0,0 +1,321 @@
+use crate::adapter::{
+    ActorMetrics, ClientActor, ClientActorArguments, ClientActorMessage, SnapshotPolicy,
+    client_actor_name,
+};
+use crate::domain::{EngineError, PaymentError};
+use crate::port::{DisputeIndex, EventCallback, Journal, Snapshotter};
+use async_trait::async_trait;
+use ractor::{
+    Actor, ActorId, ActorProcessingErr, ActorRef, ActorStatus, RpcReplyPort, SupervisionEvent,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often a client actor may fail before its client is quarantined
+#[derive(Debug, Clone)]
+pub struct SupervisionConfig {
+    /// Restarts allowed within `window`; the next failure quarantines the client
+    pub max_restarts: usize,
+    pub window: Duration,
+}
+
+impl Default for SupervisionConfig {
+    fn default() -> Self {
+        Self {
+            max_restarts: 3,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Messages that can be sent to a ClientSupervisor
+pub enum SupervisorMessage {
+    /// Running actor of a client, spawned (and linked) if needed
+    GetOrSpawn(u16, SpawnReply),
+    IsQuarantined(u16, RpcReplyPort<bool>),
+    /// Lift a client's quarantine and forget its failures
+    Release(u16),
+    /// Sent by the supervisor to itself when a spawn started by GetOrSpawn (or a restart)
+    /// finished, so its outcome is recorded on the message loop
+    Spawned(u16, Result<ActorRef<ClientActorMessage>, String>),
+}
+
+type SpawnReply = RpcReplyPort<Result<ActorRef<ClientActorMessage>, PaymentError>>;
+
+impl ractor::Message for SupervisorMessage {}
+
+/// Everything needed to spawn (and respawn) the actor of any client
+#[derive(Clone)]
+pub(crate) struct ClientActorTemplate {
+    pub(crate) namespace: String,
+    pub(crate) journal: Arc<dyn Journal + Send + Sync>,
+    pub(crate) dispute_index: Arc<dyn DisputeIndex>,
+    pub(crate) callbacks: Vec<Arc<dyn EventCallback>>,
+    pub(crate) snapshotter: Option<Arc<dyn Snapshotter>>,
+    pub(crate) snapshot_policy: SnapshotPolicy,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) metrics: Arc<ActorMetrics>,
+}
+
+impl ClientActorTemplate {
+    fn arguments(&self, client_id: u16) -> ClientActorArguments {
+        ClientActorArguments {
+            client_id,
+            journal: self.journal.clone(),
+            dispute_index: self.dispute_index.clone(),
+            callbacks: self.callbacks.clone(),
+            snapshotter: self.snapshotter.clone(),
+            snapshot_policy: self.snapshot_policy.clone(),
+            idle_timeout: self.idle_timeout,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+pub struct ClientSupervisorArguments {
+    pub(crate) template: ClientActorTemplate,
+    pub(crate) config: SupervisionConfig,
+}
+
+pub struct ClientSupervisorState {
+    template: ClientActorTemplate,
+    config: SupervisionConfig,
+    /// Client of each running child
+    children: HashMap<ActorId, u16>,
+    /// Callers waiting for each client whose actor is being spawned
+    spawning: HashMap<u16, Vec<SpawnReply>>,
+    /// Recent failure times per client
+    failures: HashMap<u16, VecDeque<Instant>>,
+    quarantined: HashSet<u16>,
+}
+
+/// ClientSupervisor spawns the client actors, linked to itself
+///
+/// A spawn runs the actor's recovery (snapshot plus journal replay) in its `pre_start`,
+/// so it is started on its own task rather than awaited on the supervisor's message loop:
+/// cold starts of different clients recover concurrently, and concurrent requests for
+/// the same client wait for the one spawn.
+///
+/// A client actor that fails (an error or panic in its handler) is restarted with its
+/// state rebuilt from the journal. A client whose actor fails more than `max_restarts`
+/// times within the window is quarantined: it is not restarted and its commands are
+/// rejected with `ClientQuarantined` until released. Actors that stop normally
+/// (passivation, shutdown) are not restarted.
+pub struct ClientSupervisor;
+
+#[async_trait]
+impl Actor for ClientSupervisor {
+    type Msg = SupervisorMessage;
+    type State = ClientSupervisorState;
+    type Arguments = ClientSupervisorArguments;
+
+    async fn pre_start(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        args: Self::Arguments,
+    ) -> Result<Self::State, ActorProcessingErr> {
+        Ok(ClientSupervisorState {
+            template: args.template,
+            config: args.config,
+            children: HashMap::new(),
+            spawning: HashMap::new(),
+            failures: HashMap::new(),
+            quarantined: HashSet::new(),
+        })
+    }
+
+    async fn handle(
+        &self,
+        myself: ActorRef<Self::Msg>,
+        message: Self::Msg,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        match message {
+            SupervisorMessage::GetOrSpawn(client_id, reply) => {
+                state.get_or_spawn(&myself, client_id, Some(reply));
+            }
+            SupervisorMessage::IsQuarantined(client_id, reply) => {
+                let _ = reply.send(state.quarantined.contains(&client_id));
+            }
+            SupervisorMessage::Release(client_id) => {
+                state.failures.remove(&client_id);
+                if state.quarantined.remove(&client_id) {
+                    tracing::info!("Client {} released from quarantine", client_id);
+                }
+            }
+            SupervisorMessage::Spawned(client_id, result) => {
+                state.spawned(&myself, client_id, result);
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_supervisor_evt(
+        &self,
+        myself: ActorRef<Self::Msg>,
+        message: SupervisionEvent,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        match message {
+            SupervisionEvent::ActorTerminated(cell, _, _) => {
+                state.children.remove(&cell.get_id());
+            }
+            SupervisionEvent::ActorFailed(cell, error) => {
+                // A child can fail before its Spawned message recorded it: it was registered
+                // by name, and reachable, as soon as its pre_start finished
+                let client_id = state.children.remove(&cell.get_id()).or_else(|| {
+                    let name = cell.get_name()?;
+                    state.spawning.keys().copied().find(|client_id| {
+                        client_actor_name(&state.template.namespace, *client_id) == name
+                    })
+                });
+                let Some(client_id) = client_id else {
+                    return Ok(());
+                };
+                tracing::error!("Client {} actor failed: {}", client_id, error);
+                if state.record_failure(client_id) {
+                    // A command may have spawned a new actor before the failure was reported
+                    let name = client_actor_name(&state.template.namespace, client_id);
+                    if let Some(actor_ref) = ActorRef::<ClientActorMessage>::where_is(name) {
+                        actor_ref.stop(Some("quarantined".to_string()));
+                    }
+                    return Ok(());
+                }
+
+                // Restart now so the client's next command finds a recovered actor
+                state.template.metrics.record_restart();
+                state.get_or_spawn(&myself, client_id, None);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl ClientSupervisorState {
+    /// Answer `reply` with the client's running actor, spawning it if needed
+    ///
+    /// The spawn runs on its own task and reports back with `Spawned`; until then, later
+    /// requests for the client join the waiters.
+    fn get_or_spawn(
+        &mut self,
+        myself: &ActorRef<SupervisorMessage>,
+        client_id: u16,
+        reply: Option<SpawnReply>,
+    ) {
+        if self.quarantined.contains(&client_id) {
+            if let Some(reply) = reply {
+                let _ = reply.send(Err(PaymentError::Engine(EngineError::ClientQuarantined(
+                    client_id,
+                ))));
+            }
+            return;
+        }
+
+        let name = client_actor_name(&self.template.namespace, client_id);
+        // Spawned by an earlier request (or a restart) since the caller looked it up
+        if let Some(actor_ref) = ActorRef::<ClientActorMessage>::where_is(name.clone()) {
+            if let Some(reply) = reply {
+                let _ = reply.send(Ok(actor_ref));
+            }
+            return;
+        }
+
+        if let Some(waiters) = self.spawning.get_mut(&client_id) {
+            waiters.extend(reply);
+            return;
+        }
+        self.spawning.insert(client_id, reply.into_iter().collect());
+
+        let arguments = self.template.arguments(client_id);
+        let supervisor = myself.clone();
+        tokio::spawn(async move {
+            let result =
+                Actor::spawn_linked(Some(name), ClientActor, arguments, supervisor.get_cell())
+                    .await
+                    .map(|(actor_ref, _handle)| actor_ref)
+                    .map_err(|e| e.to_string());
+            // A stopped supervisor no longer tracks anything
+            let _ = supervisor.cast(SupervisorMessage::Spawned(client_id, result));
+        });
+    }
+
+    /// Record the outcome of a spawn and answer the callers waiting for it
+    fn spawned(
+        &mut self,
+        myself: &ActorRef<SupervisorMessage>,
+        client_id: u16,
+        result: Result<ActorRef<ClientActorMessage>, String>,
+    ) {
+        let result = match result {
+            // Quarantined by a failure reported while this spawn was running
+            Ok(actor_ref) if self.quarantined.contains(&client_id) => {
+                actor_ref.stop(Some("quarantined".to_string()));
+                Err(PaymentError::Engine(EngineError::ClientQuarantined(
+                    client_id,
+                )))
+            }
+            // Already stopped or failed (its failure resolved by name): spawn again rather
+            // than hand the waiters a dead actor
+            Ok(actor_ref)
+                if matches!(
+                    actor_ref.get_status(),
+                    ActorStatus::Stopping | ActorStatus::Stopped
+                ) =>
+            {
+                let waiters = self.spawning.remove(&client_id).unwrap_or_default();
+                if waiters.is_empty() {
+                    self.get_or_spawn(myself, client_id, None);
+                }
+                for reply in waiters {
+                    self.get_or_spawn(myself, client_id, Some(reply));
+                }
+                return;
+            }
+            Ok(actor_ref) => {
+                self.template.metrics.record_spawn();
+                self.children.insert(actor_ref.get_id(), client_id);
+                Ok(actor_ref)
+            }
+            Err(reason) => {
+                // Recovery failed in pre_start: counts towards quarantine like a crash
+                tracing::error!("Client {} actor spawn failed: {}", client_id, reason);
+                self.record_failure(client_id);
+                Err(PaymentError::Engine(EngineError::ActorFailed {
+                    client_id,
+                    reason,
+                }))
+            }
+        };
+
+        for reply in self.spawning.remove(&client_id).unwrap_or_default() {
+            let _ = reply.send(result.clone());
+        }
+    }
+
+    /// Record a failure of the client's actor; true when it quarantined the client
+    fn record_failure(&mut self, client_id: u16) -> bool {
+        let now = Instant::now();
+        let failures = self.failures.entry(client_id).or_default();
+        failures.push_back(now);
+        while failures
+            .front()
+            .is_some_and(|failed| now.duration_since(*failed) > self.config.window)
+        {
+            failures.pop_front();
+        }
+
+        if failures.len() > self.config.max_restarts && self.quarantined.insert(client_id) {
+            tracing::error!(
+                "Client {} quarantined after {} failures within {:?}",
+                client_id,
+                failures.len(),
+                self.config.window
+            );
+            self.template.metrics.record_quarantine();
+            return true;
+        }
+        self.quarantined.contains(&client_id)
+    }
+}
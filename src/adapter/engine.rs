@@ -1,7 +1,7 @@
 use crate::{
     domain::{
-        AccountState, CommandMetadata, EngineError, EventEnvelope, EventMetadata, PaymentError,
-        TransactionTypeCommand,
+        AccountState, AppendOutcome, CommandMetadata, Directive, EngineError, ErrorCategory,
+        EventEnvelope, EventMetadata, PaymentError, TransactionTypeCommand,
     },
     port::{Engine, EventCallback, EventHandler, Journal, Processor},
 };
@@ -41,16 +41,32 @@ impl PaymentEngine {
         self
     }
 
+    /// Load the command's dependencies and validate it against the current state
+    async fn validate(
+        &self,
+        command: &TransactionTypeCommand,
+        context: &EngineContext,
+    ) -> Result<Directive, PaymentError> {
+        // 1. Load phase: query dependencies (e.g., lookup disputed transaction)
+        //    Uses snapshot of current state - this can be slow (I/O)
+        //    Caller's serialization ensures state doesn't change during this
+        let stale_state = context.current_state.clone();
+        let validate_fn = self.processor.load(command.clone(), &stale_state).await?;
+
+        // 2. Validation phase: apply business rules to CURRENT state
+        //    Infrastructure guarantee: state hasn't changed since load phase
+        //    (ClientActor's sequential processing ensures this)
+        validate_fn.apply(&context.current_state)
+    }
+
     /// Invoke callbacks after event persistence
     ///
     /// Two types of callbacks are invoked:
     /// 1. Infrastructure callbacks (mandatory) - handled by Journal (e.g., dispute index)
     /// 2. User callbacks (optional) - custom business logic provided by user
-    async fn invoke_callbacks(
-        &self,
-        envelope: &EventEnvelope,
-        context: &EngineContext,
-    ) -> Result<(), PaymentError> {
+    ///
+    /// The event is already journaled: a failing callback is logged and the others still run.
+    async fn invoke_callbacks(&self, envelope: &EventEnvelope, context: &EngineContext) {
         use crate::domain::TransactionTypeEvent;
         use crate::port::CallbackContext;
 
@@ -66,26 +82,34 @@ impl PaymentEngine {
         // Invoke user-provided callbacks
         for callback in &self.user_callbacks {
             // Dispatch to appropriate callback method based on event type
-            match &envelope.event {
+            let result = match &envelope.event {
                 TransactionTypeEvent::Deposited(event) => {
-                    callback.on_deposited(event, &callback_ctx).await?;
+                    callback.on_deposited(event, &callback_ctx).await
                 }
                 TransactionTypeEvent::Withdrawn(event) => {
-                    callback.on_withdrawn(event, &callback_ctx).await?;
+                    callback.on_withdrawn(event, &callback_ctx).await
                 }
                 TransactionTypeEvent::Disputed(event) => {
-                    callback.on_disputed(event, &callback_ctx).await?;
+                    callback.on_disputed(event, &callback_ctx).await
                 }
                 TransactionTypeEvent::Resolved(event) => {
-                    callback.on_resolved(event, &callback_ctx).await?;
+                    callback.on_resolved(event, &callback_ctx).await
                 }
                 TransactionTypeEvent::Chargebacked(event) => {
-                    callback.on_chargebacked(event, &callback_ctx).await?;
+                    callback.on_chargebacked(event, &callback_ctx).await
                 }
+                // Tombstones only appear when replaying erased clients, never on append
+                TransactionTypeEvent::Redacted(_) => Ok(()),
+            };
+            if let Err(e) = result {
+                tracing::error!(
+                    "Callback for event {} of client {} failed: {}",
+                    envelope.sequence_nr,
+                    envelope.client_id,
+                    e
+                );
             }
         }
-
-        Ok(())
     }
 }
 
@@ -98,7 +122,17 @@ impl Engine for PaymentEngine {
     /// 2. Validation phase (apply business rules to current state)
     /// 3. Persist event to journal (journal assigns sequence number atomically)
     /// 4. Apply event to state (functional - returns new state)
-    /// 5. Execute effects (with new state)
+    /// 5. Invoke callbacks and execute effects (with new state); failures are logged, since
+    ///    the event is already journaled
+    ///
+    /// Redeliveries are recognised by the journal on append: a known deduplication key is
+    /// answered with the original envelope as `Duplicate`, or rejected as an
+    /// `IdempotencyConflict` if its payload differs. Callbacks, state transition and effects
+    /// are skipped for a duplicate, since they already ran for the original.
+    /// A redelivery may no longer validate against a state that includes its original's
+    /// effects (a spent withdrawal as insufficient funds, a charged back dispute as no
+    /// longer disputed), so a business rejection is only returned if its key was never
+    /// journaled; otherwise it is answered as `Duplicate` too.
     ///
     /// INFRASTRUCTURE CONTRACT (caller's responsibility):
     /// - Caller MUST provide serialization (e.g., actor model with sequential processing)
@@ -108,29 +142,38 @@ impl Engine for PaymentEngine {
     /// This separation keeps the engine pure (stateless business logic) while
     /// pushing ordering guarantees to infrastructure (ClientActor).
     ///
-    /// Returns (EventEnvelope, NewState) - includes sequence number for verification
+    /// Returns (AppendOutcome, NewState) - includes sequence number for verification;
+    /// NewState is the unchanged current state for a duplicate
     async fn process_command(
         &self,
         command: TransactionTypeCommand,
         metadata: CommandMetadata,
         context: &Self::Context,
-    ) -> Result<(EventEnvelope, AccountState), PaymentError> {
-        // 1. Load phase: query dependencies (e.g., lookup disputed transaction)
-        //    Uses snapshot of current state - this can be slow (I/O)
-        //    Caller's serialization ensures state doesn't change during this
-        let stale_state = context.current_state.clone();
-        let validate_fn = self.processor.load(command.clone(), &stale_state).await?;
-
-        // 2. Validation phase: apply business rules to CURRENT state
-        //    Infrastructure guarantee: state hasn't changed since load phase
-        //    (ClientActor's sequential processing ensures this)
-        let directive = validate_fn.apply(&context.current_state)?;
+    ) -> Result<(AppendOutcome, AccountState), PaymentError> {
+        let directive = match self.validate(&command, context).await {
+            Ok(directive) => directive,
+            // A redelivery whose original already changed the state
+            Err(rejection) if rejection.category() == ErrorCategory::BusinessRejection => {
+                return match context
+                    .journal
+                    .find_by_deduplication_key(command.client_id(), &metadata.deduplication_key)
+                    .await?
+                {
+                    Some(original) => Ok((
+                        AppendOutcome::Duplicate(original),
+                        context.current_state.clone(),
+                    )),
+                    None => Err(rejection),
+                };
+            }
+            Err(e) => return Err(e),
+        };
 
         // 3. Persistence phase: append event to journal
         //    Journal handles:
         //    - Idempotency check via deduplication_key
         //    - Atomic sequence number assignment (under journal's write lock)
-        //    - Returns existing envelope if duplicate
+        //    - Returns the existing envelope as Duplicate if already journaled
         let event = directive
             .events
             .into_iter()
@@ -144,24 +187,41 @@ impl Engine for PaymentEngine {
             timestamp: Utc::now(),
         };
 
-        let envelope = context.journal.append(event, event_metadata).await?;
-
-        // 3.5. Infrastructure callbacks: notify about event persistence
-        //      This is where infrastructure concerns (like dispute index) are updated
-        self.invoke_callbacks(&envelope, context).await?;
+        let envelope = match context.journal.append(event, event_metadata).await? {
+            AppendOutcome::Appended(envelope) => envelope,
+            // Redelivery: everything below already happened for the original
+            duplicate @ AppendOutcome::Duplicate(_) => {
+                return Ok((duplicate, context.current_state.clone()));
+            }
+        };
 
         // 4. State transition: apply event to get new state
         //    This is functional (pure) - returns new state, doesn't mutate
         let new_state = envelope
             .apply(&context.current_state)
-            .ok_or(PaymentError::Engine(EngineError::StateTransitionFailed))?;
+            .ok_or(PaymentError::Engine(EngineError::StateTransitionFailed {
+                sequence_nr: envelope.sequence_nr,
+            }))?;
+
+        // 4.5. Infrastructure callbacks: notify about event persistence
+        //      This is where infrastructure concerns (like dispute index) are updated.
+        //      The event is journaled and a retry would be answered as a duplicate, so
+        //      neither callbacks nor effects may keep the new state from the caller
+        self.invoke_callbacks(&envelope, context).await;
 
         // 5. Effects: execute side effects with new state
         for effect in directive.effects {
-            effect.execute(&new_state).await?;
+            if let Err(e) = effect.execute(&new_state).await {
+                tracing::error!(
+                    "Effect of event {} of client {} failed: {}",
+                    envelope.sequence_nr,
+                    envelope.client_id,
+                    e
+                );
+            }
         }
 
-        Ok((envelope, new_state))
+        Ok((AppendOutcome::Appended(envelope), new_state))
     }
 
     fn processor(&self) -> &dyn Processor {
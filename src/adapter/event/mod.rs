@@ -15,6 +15,8 @@ impl EventHandler for EventEnvelope {
             TransactionTypeEvent::Disputed(event) => event.apply(state),
             TransactionTypeEvent::Resolved(event) => event.apply(state),
             TransactionTypeEvent::Chargebacked(event) => event.apply(state),
+            // The original effect is unknown once the payload is shredded
+            TransactionTypeEvent::Redacted(_) => Some(state.clone()),
         }
     }
 }
@@ -44,4 +44,11 @@ impl DisputeIndex for InMemoryDisputeIndex {
         disputed.remove(&tx_id);
         Ok(())
     }
+
+    async fn list_disputed(&self) -> Result<Vec<u32>, PaymentError> {
+        let disputed = self.disputed_txs.read().await;
+        let mut tx_ids: Vec<u32> = disputed.keys().copied().collect();
+        tx_ids.sort_unstable();
+        Ok(tx_ids)
+    }
 }
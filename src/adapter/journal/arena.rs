@@ -0,0 +1,238 @@
+use crate::domain::{DeduplicationKey, EventEnvelope, TransactionTypeEvent};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Columnar storage for the envelopes of an in-memory journal
+///
+/// Every envelope field lives in its own column, indexed by position and kept sorted by
+/// sequence number. Chain hashes are stored as raw 32-byte digests and deduplication keys
+/// as an interned prefix plus a numeric suffix (`csv:<path>:` + line number), so the
+/// per-event footprint does not depend on the length of file paths or hex strings.
+/// Envelopes are only materialized when they cross the `Journal` boundary.
+#[derive(Default)]
+pub(crate) struct EventArena {
+    sequence_nrs: Vec<u64>,
+    events: Vec<TransactionTypeEvent>,
+    timestamps: Vec<DateTime<Utc>>,
+    client_ids: Vec<u16>,
+    tx_ids: Vec<u32>,
+    deduplication_keys: Vec<StoredKey>,
+    hashes: Vec<[u8; 32]>,
+    previous_hashes: Vec<[u8; 32]>,
+    /// Original hash strings of envelopes whose hashes are not SHA-256 hex digests
+    /// (legacy envelopes without a chain, or tampered imports), keyed by sequence number
+    irregular_hashes: HashMap<u64, (String, String)>,
+    prefixes: KeyPrefixes,
+}
+
+impl EventArena {
+    pub(crate) fn len(&self) -> usize {
+        self.sequence_nrs.len()
+    }
+
+    /// Position of a sequence number, or where it would have to be inserted
+    pub(crate) fn position(&self, sequence_nr: u64) -> Result<usize, usize> {
+        self.sequence_nrs.binary_search(&sequence_nr)
+    }
+
+    /// Position of the first event with a sequence number of at least `sequence_nr`
+    pub(crate) fn lower_bound(&self, sequence_nr: u64) -> usize {
+        self.sequence_nrs.partition_point(|&s| s < sequence_nr)
+    }
+
+    pub(crate) fn get(&self, position: usize) -> EventRef<'_> {
+        EventRef {
+            arena: self,
+            position,
+        }
+    }
+
+    pub(crate) fn iter_from(&self, position: usize) -> impl Iterator<Item = EventRef<'_>> {
+        (position..self.len()).map(|position| self.get(position))
+    }
+
+    pub(crate) fn push(&mut self, envelope: EventEnvelope) {
+        let position = self.len();
+        self.insert(position, envelope);
+    }
+
+    pub(crate) fn insert(&mut self, position: usize, envelope: EventEnvelope) {
+        let (hash, previous_hash) = match (
+            parse_digest(&envelope.hash),
+            parse_digest(&envelope.previous_hash),
+        ) {
+            (Some(hash), Some(previous_hash)) => (hash, previous_hash),
+            _ => {
+                self.irregular_hashes.insert(
+                    envelope.sequence_nr,
+                    (envelope.previous_hash, envelope.hash),
+                );
+                ([0; 32], [0; 32])
+            }
+        };
+        let deduplication_key = self.prefixes.store(envelope.deduplication_key);
+
+        self.sequence_nrs.insert(position, envelope.sequence_nr);
+        self.events.insert(position, envelope.event);
+        self.timestamps.insert(position, envelope.timestamp);
+        self.client_ids.insert(position, envelope.client_id);
+        self.tx_ids.insert(position, envelope.tx_id);
+        self.deduplication_keys.insert(position, deduplication_key);
+        self.hashes.insert(position, hash);
+        self.previous_hashes.insert(position, previous_hash);
+    }
+
+    /// Drop every event before `position`
+    pub(crate) fn drain_front(&mut self, position: usize) {
+        if let Some(&first_kept) = self.sequence_nrs.get(position) {
+            self.irregular_hashes
+                .retain(|&sequence_nr, _| sequence_nr >= first_kept);
+        } else {
+            self.irregular_hashes.clear();
+        }
+
+        self.sequence_nrs.drain(..position);
+        self.events.drain(..position);
+        self.timestamps.drain(..position);
+        self.client_ids.drain(..position);
+        self.tx_ids.drain(..position);
+        self.deduplication_keys.drain(..position);
+        self.hashes.drain(..position);
+        self.previous_hashes.drain(..position);
+    }
+
+    /// Replace the domain event at a position, keeping metadata and hashes
+    pub(crate) fn replace_event(&mut self, position: usize, event: TransactionTypeEvent) {
+        self.events[position] = event;
+    }
+}
+
+/// Borrowed view of one envelope in an arena
+#[derive(Clone, Copy)]
+pub struct EventRef<'a> {
+    arena: &'a EventArena,
+    position: usize,
+}
+
+impl<'a> EventRef<'a> {
+    pub fn sequence_nr(&self) -> u64 {
+        self.arena.sequence_nrs[self.position]
+    }
+
+    pub fn event(&self) -> &'a TransactionTypeEvent {
+        &self.arena.events[self.position]
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.arena.timestamps[self.position]
+    }
+
+    pub fn client_id(&self) -> u16 {
+        self.arena.client_ids[self.position]
+    }
+
+    pub fn tx_id(&self) -> u32 {
+        self.arena.tx_ids[self.position]
+    }
+
+    /// Chain hash of the envelope
+    pub fn hash(&self) -> String {
+        self.hashes().1
+    }
+
+    /// Materialize an owned envelope
+    pub fn to_envelope(&self) -> EventEnvelope {
+        let arena = self.arena;
+        let sequence_nr = self.sequence_nr();
+        let (previous_hash, hash) = self.hashes();
+
+        EventEnvelope {
+            sequence_nr,
+            event: self.event().clone(),
+            timestamp: self.timestamp(),
+            client_id: self.client_id(),
+            tx_id: self.tx_id(),
+            deduplication_key: arena
+                .prefixes
+                .load(&arena.deduplication_keys[self.position]),
+            previous_hash,
+            hash,
+        }
+    }
+
+    /// Previous and own chain hash
+    fn hashes(&self) -> (String, String) {
+        let arena = self.arena;
+        match arena.irregular_hashes.get(&self.sequence_nr()) {
+            Some((previous_hash, hash)) => (previous_hash.clone(), hash.clone()),
+            None => (
+                encode_digest(&arena.previous_hashes[self.position]),
+                encode_digest(&arena.hashes[self.position]),
+            ),
+        }
+    }
+}
+
+/// Deduplication key split into an interned prefix and a numeric suffix when possible
+enum StoredKey {
+    Numbered { prefix: u32, number: u64 },
+    Raw(Box<str>),
+}
+
+/// Interned deduplication key prefixes
+#[derive(Default)]
+struct KeyPrefixes {
+    ids: HashMap<Box<str>, u32>,
+    prefixes: Vec<Box<str>>,
+}
+
+impl KeyPrefixes {
+    fn store(&mut self, key: DeduplicationKey) -> StoredKey {
+        let key = key.as_str();
+        let Some((prefix, suffix)) = key.rsplit_once(':') else {
+            return StoredKey::Raw(key.into());
+        };
+        // Only keep the numeric form if it renders back to the exact same text
+        let number = match suffix.parse::<u64>() {
+            Ok(number) if number.to_string() == suffix => number,
+            _ => return StoredKey::Raw(key.into()),
+        };
+
+        let prefix = match self.ids.get(prefix) {
+            Some(&id) => id,
+            None => {
+                let id = self.prefixes.len() as u32;
+                self.prefixes.push(prefix.into());
+                self.ids.insert(prefix.into(), id);
+                id
+            }
+        };
+        StoredKey::Numbered { prefix, number }
+    }
+
+    fn load(&self, key: &StoredKey) -> DeduplicationKey {
+        match key {
+            StoredKey::Numbered { prefix, number } => {
+                DeduplicationKey::new(format!("{}:{}", self.prefixes[*prefix as usize], number))
+            }
+            StoredKey::Raw(key) => DeduplicationKey::new(key.to_string()),
+        }
+    }
+}
+
+/// Parse a lowercase hex SHA-256 digest, rejecting anything that would not render back identically
+fn parse_digest(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+fn encode_digest(digest: &[u8; 32]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
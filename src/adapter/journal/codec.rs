@@ -0,0 +1,238 @@
+use crate::{
+    adapter::{ClientKeyStore, Keyring, SealedPayload, journal::archive::json_error},
+    domain::{
+        DeduplicationKey, EngineError, EventEnvelope, PaymentError, Redacted, TransactionTypeEvent,
+    },
+    port::EnvelopeCodec,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Plain JSON records: one serialized EventEnvelope per line
+pub struct JsonCodec;
+
+impl EnvelopeCodec for JsonCodec {
+    fn encode(&self, envelope: &EventEnvelope) -> Result<String, PaymentError> {
+        serde_json::to_string(envelope).map_err(json_error)
+    }
+
+    fn decode(&self, record: &str) -> Result<EventEnvelope, PaymentError> {
+        let record: StoredRecord = serde_json::from_str(record).map_err(json_error)?;
+
+        match &record.payload {
+            StoredPayload::Event(event) => {
+                let event = stored_event(event.clone());
+                Ok(record.into_envelope(event))
+            }
+            StoredPayload::Sealed(sealed) => Err(PaymentError::Engine(
+                EngineError::PersistenceError(format!(
+                    "Record {} is encrypted with key {}, a keyfile is required",
+                    record.sequence_nr, sealed.key_id
+                )),
+            )),
+        }
+    }
+}
+
+/// Encrypts event payloads at rest while leaving the envelope metadata in plaintext
+///
+/// Sequence numbers, timestamps, client and transaction ids, deduplication keys and
+/// chain hashes stay readable so records can be indexed and audited without keys.
+/// Only the event itself is sealed, bound to its record through the associated data.
+/// Plaintext records (written before encryption was enabled) are still decoded.
+pub struct EncryptedCodec {
+    keyring: Arc<Keyring>,
+}
+
+impl EncryptedCodec {
+    pub fn new(keyring: Arc<Keyring>) -> Self {
+        Self { keyring }
+    }
+}
+
+impl EnvelopeCodec for EncryptedCodec {
+    fn encode(&self, envelope: &EventEnvelope) -> Result<String, PaymentError> {
+        let plaintext = serde_json::to_vec(&envelope.event).map_err(json_error)?;
+        let sealed = self.keyring.seal(
+            &plaintext,
+            associated_data(envelope.sequence_nr, envelope.client_id, envelope.tx_id).as_bytes(),
+        )?;
+
+        let record = StoredRecord::from_envelope(envelope, StoredPayload::Sealed(sealed));
+        serde_json::to_string(&record).map_err(json_error)
+    }
+
+    fn decode(&self, record: &str) -> Result<EventEnvelope, PaymentError> {
+        let record: StoredRecord = serde_json::from_str(record).map_err(json_error)?;
+
+        let event = match &record.payload {
+            StoredPayload::Event(event) => stored_event(event.clone()),
+            StoredPayload::Sealed(sealed) => {
+                let aad = associated_data(record.sequence_nr, record.client_id, record.tx_id);
+                let plaintext = self.keyring.open(sealed, aad.as_bytes())?;
+                stored_event(serde_json::from_slice(&plaintext).map_err(json_error)?)
+            }
+        };
+
+        Ok(record.into_envelope(event))
+    }
+
+    fn seal_payload(
+        &self,
+        _client_id: u16,
+        context: &str,
+        payload: &[u8],
+    ) -> Result<Option<String>, PaymentError> {
+        let sealed = self.keyring.seal(payload, context.as_bytes())?;
+        serde_json::to_string(&sealed).map(Some).map_err(json_error)
+    }
+
+    fn open_payload(
+        &self,
+        _client_id: u16,
+        context: &str,
+        sealed: &str,
+    ) -> Result<Option<Vec<u8>>, PaymentError> {
+        let sealed: SealedPayload = serde_json::from_str(sealed).map_err(json_error)?;
+        self.keyring.open(&sealed, context.as_bytes()).map(Some)
+    }
+}
+
+/// Encrypts every event payload with its client's own data key (crypto-shredding)
+///
+/// Same record layout as EncryptedCodec, but the key is picked by the plaintext
+/// `client_id`. Once a client's key is erased its records decode as Redacted tombstones
+/// instead of failing, so the journal still replays from start to end.
+pub struct ClientKeyCodec {
+    keys: Arc<ClientKeyStore>,
+}
+
+impl ClientKeyCodec {
+    pub fn new(keys: Arc<ClientKeyStore>) -> Self {
+        Self { keys }
+    }
+}
+
+impl EnvelopeCodec for ClientKeyCodec {
+    fn encode(&self, envelope: &EventEnvelope) -> Result<String, PaymentError> {
+        let plaintext = serde_json::to_vec(&envelope.event).map_err(json_error)?;
+        let aad = associated_data(envelope.sequence_nr, envelope.client_id, envelope.tx_id);
+        let sealed = self
+            .keys
+            .seal(envelope.client_id, &plaintext, aad.as_bytes())?;
+
+        let record = StoredRecord::from_envelope(envelope, StoredPayload::Sealed(sealed));
+        serde_json::to_string(&record).map_err(json_error)
+    }
+
+    fn decode(&self, record: &str) -> Result<EventEnvelope, PaymentError> {
+        let record: StoredRecord = serde_json::from_str(record).map_err(json_error)?;
+
+        let event = match &record.payload {
+            StoredPayload::Event(event) => stored_event(event.clone()),
+            StoredPayload::Sealed(sealed) => {
+                let aad = associated_data(record.sequence_nr, record.client_id, record.tx_id);
+                match self.keys.open(record.client_id, sealed, aad.as_bytes())? {
+                    Some(plaintext) => {
+                        stored_event(serde_json::from_slice(&plaintext).map_err(json_error)?)
+                    }
+                    None => TransactionTypeEvent::Redacted(Redacted {
+                        client_id: record.client_id,
+                        tx_id: record.tx_id,
+                        shredded: true,
+                    }),
+                }
+            }
+        };
+
+        Ok(record.into_envelope(event))
+    }
+
+    fn seal_payload(
+        &self,
+        client_id: u16,
+        context: &str,
+        payload: &[u8],
+    ) -> Result<Option<String>, PaymentError> {
+        let sealed = self.keys.seal(client_id, payload, context.as_bytes())?;
+        serde_json::to_string(&sealed).map(Some).map_err(json_error)
+    }
+
+    fn open_payload(
+        &self,
+        client_id: u16,
+        context: &str,
+        sealed: &str,
+    ) -> Result<Option<Vec<u8>>, PaymentError> {
+        let sealed: SealedPayload = serde_json::from_str(sealed).map_err(json_error)?;
+        self.keys.open(client_id, &sealed, context.as_bytes())
+    }
+}
+
+/// Event as read from a record; only the codec itself may vouch for a shredded tombstone
+fn stored_event(event: TransactionTypeEvent) -> TransactionTypeEvent {
+    match event {
+        TransactionTypeEvent::Redacted(redacted) => TransactionTypeEvent::Redacted(Redacted {
+            shredded: false,
+            ..redacted
+        }),
+        event => event,
+    }
+}
+
+/// Binds a ciphertext to the record it belongs to, so payloads cannot be swapped between records
+fn associated_data(sequence_nr: u64, client_id: u16, tx_id: u32) -> String {
+    format!("{}:{}:{}", sequence_nr, client_id, tx_id)
+}
+
+/// On-disk record layout; with a plaintext payload it is identical to a serialized EventEnvelope
+#[derive(Serialize, Deserialize)]
+struct StoredRecord {
+    sequence_nr: u64,
+    #[serde(flatten)]
+    payload: StoredPayload,
+    timestamp: DateTime<Utc>,
+    client_id: u16,
+    tx_id: u32,
+    deduplication_key: DeduplicationKey,
+    #[serde(default)]
+    previous_hash: String,
+    #[serde(default)]
+    hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum StoredPayload {
+    Event(TransactionTypeEvent),
+    Sealed(SealedPayload),
+}
+
+impl StoredRecord {
+    fn from_envelope(envelope: &EventEnvelope, payload: StoredPayload) -> Self {
+        Self {
+            sequence_nr: envelope.sequence_nr,
+            payload,
+            timestamp: envelope.timestamp,
+            client_id: envelope.client_id,
+            tx_id: envelope.tx_id,
+            deduplication_key: envelope.deduplication_key.clone(),
+            previous_hash: envelope.previous_hash.clone(),
+            hash: envelope.hash.clone(),
+        }
+    }
+
+    fn into_envelope(self, event: TransactionTypeEvent) -> EventEnvelope {
+        EventEnvelope {
+            sequence_nr: self.sequence_nr,
+            event,
+            timestamp: self.timestamp,
+            client_id: self.client_id,
+            tx_id: self.tx_id,
+            deduplication_key: self.deduplication_key,
+            previous_hash: self.previous_hash,
+            hash: self.hash,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::domain::DeduplicationWindow;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+
+/// Size of the Bloom filter remembering expired keys (8 MiB, one per journal)
+///
+/// With 7 hash functions this keeps false positives around 0.05% for 4M expired keys,
+/// which is the order of magnitude of the README benchmark.
+const EXPIRED_FILTER_BITS: usize = 64 * 1024 * 1024;
+const EXPIRED_FILTER_HASHES: u64 = 7;
+
+/// Payload fingerprint of a key whose payload is unknown (imported as a redacted
+/// tombstone); it matches any payload
+pub(crate) const ANY_PAYLOAD: u64 = 0;
+
+/// Result of looking up a deduplication key
+pub(crate) enum DeduplicationLookup {
+    /// Key is inside the window: the command was already persisted under this sequence
+    /// number, with this payload fingerprint
+    Hit { sequence_nr: u64, payload: u64 },
+    /// Key was seen before but has left the window
+    Expired,
+    /// Key was never seen
+    Miss,
+}
+
+/// Deduplication keys of an in-memory journal, bounded by a DeduplicationWindow
+///
+/// Keys are stored as 128-bit fingerprints, each with the 64-bit fingerprint of the
+/// payload persisted under it. When the window is bounded, keys leaving it
+/// are added to a fixed-size Bloom filter so a late duplicate is reported instead of being
+/// applied a second time. Being probabilistic, the filter may very rarely flag a new key
+/// as expired; it never lets an expired key through.
+pub(crate) struct DeduplicationIndex {
+    window: DeduplicationWindow,
+    /// Key fingerprint -> (sequence number, payload fingerprint)
+    entries: HashMap<u128, (u64, u64)>,
+    /// Insertion order, used for eviction (empty when unbounded)
+    order: VecDeque<(u128, DateTime<Utc>)>,
+    expired: Option<ExpiredKeys>,
+}
+
+impl DeduplicationIndex {
+    pub(crate) fn new(window: DeduplicationWindow) -> Self {
+        let (entries, expired) = match window {
+            DeduplicationWindow::Unbounded => (HashMap::new(), None),
+            DeduplicationWindow::Count(max_keys) => (
+                HashMap::with_capacity(max_keys.min(1 << 16)),
+                Some(ExpiredKeys::new(EXPIRED_FILTER_BITS)),
+            ),
+            DeduplicationWindow::Time(_) => {
+                (HashMap::new(), Some(ExpiredKeys::new(EXPIRED_FILTER_BITS)))
+            }
+        };
+
+        Self {
+            window,
+            entries,
+            order: VecDeque::new(),
+            expired,
+        }
+    }
+
+    pub(crate) fn lookup(&mut self, fingerprint: u128) -> DeduplicationLookup {
+        self.evict(Utc::now());
+
+        if let Some(&(sequence_nr, payload)) = self.entries.get(&fingerprint) {
+            return DeduplicationLookup::Hit {
+                sequence_nr,
+                payload,
+            };
+        }
+
+        match &self.expired {
+            Some(expired) if expired.contains(fingerprint) => DeduplicationLookup::Expired,
+            _ => DeduplicationLookup::Miss,
+        }
+    }
+
+    /// Remember a key; an already known key keeps its original sequence number and payload
+    pub(crate) fn insert(
+        &mut self,
+        fingerprint: u128,
+        sequence_nr: u64,
+        payload: u64,
+        timestamp: DateTime<Utc>,
+    ) {
+        if self.entries.contains_key(&fingerprint) {
+            return;
+        }
+
+        if !matches!(self.window, DeduplicationWindow::Unbounded) {
+            self.order.push_back((fingerprint, timestamp));
+        }
+        self.entries.insert(fingerprint, (sequence_nr, payload));
+        self.evict(Utc::now());
+    }
+
+    fn evict(&mut self, now: DateTime<Utc>) {
+        loop {
+            let expired = match (self.window, self.order.front()) {
+                (DeduplicationWindow::Count(max_keys), Some(_)) => self.entries.len() > max_keys,
+                (DeduplicationWindow::Time(ttl), Some((_, timestamp))) => *timestamp < now - ttl,
+                _ => false,
+            };
+            if !expired {
+                return;
+            }
+
+            if let Some((fingerprint, _)) = self.order.pop_front() {
+                self.entries.remove(&fingerprint);
+                if let Some(filter) = self.expired.as_mut() {
+                    filter.insert(fingerprint);
+                }
+            }
+        }
+    }
+}
+
+/// Bloom filter over 128-bit fingerprints (double hashing on the two 64-bit halves)
+struct ExpiredKeys {
+    bits: Vec<u64>,
+}
+
+impl ExpiredKeys {
+    fn new(bits: usize) -> Self {
+        Self {
+            bits: vec![0; bits.div_ceil(64)],
+        }
+    }
+
+    fn positions(&self, fingerprint: u128) -> impl Iterator<Item = usize> + use<> {
+        let size = (self.bits.len() * 64) as u64;
+        let h1 = fingerprint as u64;
+        let h2 = ((fingerprint >> 64) as u64) | 1;
+        (0..EXPIRED_FILTER_HASHES)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % size) as usize)
+    }
+
+    fn insert(&mut self, fingerprint: u128) {
+        for position in self.positions(fingerprint) {
+            self.bits[position / 64] |= 1 << (position % 64);
+        }
+    }
+
+    fn contains(&self, fingerprint: u128) -> bool {
+        self.positions(fingerprint)
+            .all(|position| self.bits[position / 64] & (1 << (position % 64)) != 0)
+    }
+}
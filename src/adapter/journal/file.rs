@@ -0,0 +1,697 @@
+use crate::{
+    adapter::{
+        InMemoryJournal, InMemoryJournalConfig, JsonCodec,
+        journal::archive::{SegmentArchive, io_error, json_error, sync_dir},
+        journal::memory::check_same_history,
+    },
+    domain::{
+        AppendOutcome, ChainAnchor, CompactionReport, DeduplicationKey, DeduplicationWindow,
+        EngineError, EventEnvelope, EventMetadata, JournalPage, JournalQuery, PaymentError,
+        RetentionPolicy, TransactionTypeEvent,
+    },
+    port::{EnvelopeCodec, Journal},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+const ANCHOR_FILE: &str = "anchor.json";
+
+/// Configuration for the file-backed journal
+#[derive(Debug, Clone, Copy)]
+pub struct FileJournalConfig {
+    /// Number of events after which the active segment is sealed and a new one is started
+    pub segment_size: u64,
+    /// How long deduplication keys are remembered by the in-memory index
+    pub deduplication_window: DeduplicationWindow,
+    /// Fsync every record before its append is acknowledged; without it a power loss
+    /// can drop the most recent appends
+    pub sync_writes: bool,
+}
+
+impl Default for FileJournalConfig {
+    fn default() -> Self {
+        Self {
+            segment_size: 100_000,
+            deduplication_window: DeduplicationWindow::default(),
+            sync_writes: true,
+        }
+    }
+}
+
+/// A contiguous run of events stored in one hot segment file
+struct Segment {
+    path: PathBuf,
+    first_sequence: u64,
+    last_sequence: u64,
+    count: u64,
+    newest_timestamp: DateTime<Utc>,
+}
+
+impl Segment {
+    fn record(&mut self, envelope: &EventEnvelope) {
+        self.last_sequence = envelope.sequence_nr;
+        self.count += 1;
+        self.newest_timestamp = self.newest_timestamp.max(envelope.timestamp);
+    }
+}
+
+struct SegmentLog {
+    dir: PathBuf,
+    config: FileJournalConfig,
+    codec: Arc<dyn EnvelopeCodec>,
+    /// Hot segments in sequence order; the last one is the active segment unless sealed
+    segments: Vec<Segment>,
+    writer: Option<File>,
+}
+
+impl SegmentLog {
+    fn is_sealed(&self, segment: &Segment) -> bool {
+        segment.count >= self.config.segment_size
+    }
+
+    /// Write an envelope to the active segment, rolling over to a new segment when sealed
+    ///
+    /// The record is on disk (and synced, unless disabled) when this returns. A failed
+    /// write leaves the segments as they were.
+    fn write(&mut self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        let mut record = self.codec.encode(envelope)?;
+        record.push('\n');
+
+        if self.segments.last().is_none_or(|s| self.is_sealed(s)) {
+            self.segments.push(Segment {
+                path: segment_path(&self.dir, envelope.sequence_nr),
+                first_sequence: envelope.sequence_nr,
+                last_sequence: envelope.sequence_nr,
+                count: 0,
+                newest_timestamp: envelope.timestamp,
+            });
+            self.writer = None;
+        }
+
+        if let Err(e) = self.write_record(record.as_bytes()) {
+            self.writer = None;
+            if self.segments.last().is_some_and(|s| s.count == 0)
+                && let Some(segment) = self.segments.pop()
+            {
+                let _ = fs::remove_file(&segment.path);
+            }
+            return Err(e);
+        }
+
+        self.segments
+            .last_mut()
+            .expect("active segment exists")
+            .record(envelope);
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &[u8]) -> Result<(), PaymentError> {
+        let sync = self.config.sync_writes;
+        let active = self.segments.last().expect("active segment exists");
+        if self.writer.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&active.path)
+                .map_err(io_error)?;
+            self.writer = Some(file);
+        }
+
+        let file = self.writer.as_mut().expect("writer is open");
+        let length = file.metadata().map_err(io_error)?.len();
+        let written = file
+            .write_all(record)
+            .and_then(|_| if sync { file.sync_data() } else { Ok(()) });
+
+        if let Err(e) = written {
+            // Cut off a torn record so the next append does not continue a broken line
+            let _ = file.set_len(length);
+            return Err(io_error(e));
+        }
+        Ok(())
+    }
+}
+
+/// Durable journal storing events in segmented JSON Lines files
+///
+/// Layout of the journal directory:
+/// - `segment-<first_sequence>.jsonl` - hot segments, the last one receives appends
+/// - `archive/segment-<first>-<last>.jsonl.gz` - sealed segments moved out by compaction
+/// - `anchor.json` - sequence number and hash of the last archived event
+///
+/// Hot events are also kept in an InMemoryJournal which serves reads and deduplication,
+/// so compaction is what bounds both disk usage and memory. On open, archived segments
+/// only contribute their deduplication keys, and the anchor lets the chain continue even
+/// when compaction left the hot log empty.
+///
+/// Records are written through an EnvelopeCodec (plain JSON by default, EncryptedCodec
+/// for encryption at rest). An append is written to its segment before it enters the
+/// index, so nothing is served or deduplicated against that is not on disk; both steps run
+/// on one task, so dropping an append future cannot separate them. A torn record
+/// that a crash left at the end of the newest segment is cut off on open. File I/O runs
+/// on tokio's blocking pool.
+pub struct FileJournal {
+    dir: PathBuf,
+    index: Arc<InMemoryJournal>,
+    log: Arc<Mutex<SegmentLog>>,
+    archive: SegmentArchive,
+}
+
+impl FileJournal {
+    /// Open (or create) a journal directory and load its hot segments
+    pub async fn open(
+        dir: impl Into<PathBuf>,
+        config: FileJournalConfig,
+    ) -> Result<Self, PaymentError> {
+        Self::open_with_codec(dir, config, Arc::new(JsonCodec)).await
+    }
+
+    /// Open a journal directory whose records are encoded with the given codec
+    pub async fn open_with_codec(
+        dir: impl Into<PathBuf>,
+        config: FileJournalConfig,
+        codec: Arc<dyn EnvelopeCodec>,
+    ) -> Result<Self, PaymentError> {
+        let dir = dir.into();
+        let archive = SegmentArchive::new(dir.join("archive"), codec.clone());
+        let index = InMemoryJournal::with_config(InMemoryJournalConfig {
+            deduplication_window: config.deduplication_window,
+            ..Default::default()
+        });
+
+        let (archives, anchor, segment_paths) = {
+            let (dir, archive) = (dir.clone(), archive.clone());
+            blocking(move || {
+                fs::create_dir_all(&dir).map_err(io_error)?;
+                Ok((archive.list()?, read_anchor(&dir)?, list_segments(&dir)?))
+            })
+            .await?
+        };
+
+        // Archived events stay out of the hot log but keep their keys and sequence numbers
+        for path in archives {
+            let archive = archive.clone();
+            for envelope in blocking(move || archive.read(&path)).await? {
+                index.remember(&envelope).await;
+            }
+        }
+        if let Some(anchor) = anchor {
+            index.seed_anchor(anchor);
+        }
+
+        // A crash in the middle of an append can leave a torn record at the end of the
+        // newest segment; that append was never acknowledged, so it is cut off
+        if let Some(newest) = segment_paths.last().cloned() {
+            let dropped = blocking(move || drop_torn_tail(&newest)).await?;
+            if dropped > 0 {
+                tracing::warn!(
+                    "Dropped {} bytes of a torn record at the end of the newest journal segment",
+                    dropped
+                );
+            }
+        }
+
+        let mut segments = Vec::new();
+        for path in segment_paths {
+            let envelopes = {
+                let (path, codec) = (path.clone(), codec.clone());
+                blocking(move || read_segment(&path, codec.as_ref())).await?
+            };
+            let (Some(first), Some(last)) = (envelopes.first(), envelopes.last()) else {
+                continue;
+            };
+
+            segments.push(Segment {
+                path,
+                first_sequence: first.sequence_nr,
+                last_sequence: last.sequence_nr,
+                count: envelopes.len() as u64,
+                newest_timestamp: envelopes
+                    .iter()
+                    .map(|e| e.timestamp)
+                    .max()
+                    .unwrap_or(first.timestamp),
+            });
+
+            for envelope in envelopes {
+                index.import(envelope).await?;
+            }
+        }
+
+        Ok(Self {
+            archive,
+            log: Arc::new(Mutex::new(SegmentLog {
+                dir: dir.clone(),
+                config,
+                codec,
+                segments,
+                writer: None,
+            })),
+            dir,
+            index: Arc::new(index),
+        })
+    }
+
+    pub fn archive(&self) -> &SegmentArchive {
+        &self.archive
+    }
+
+    /// Archive sealed segments that are no longer needed in the hot log
+    ///
+    /// A sealed segment is moved to the archive only if:
+    /// - every event in it is covered by a snapshot (`sequence_nr <= snapshot_floor`), and
+    /// - its newest event is older than the dispute horizon, so `find_by_tx_id`
+    ///   keeps returning every transaction that can still be disputed.
+    ///
+    /// Segments are archived oldest first and compaction stops at the first segment that
+    /// must stay, keeping the hot log contiguous.
+    pub async fn compact(
+        &self,
+        policy: &RetentionPolicy,
+        snapshot_floor: u64,
+    ) -> Result<CompactionReport, PaymentError> {
+        let mut log = self.log.lock().await;
+        let horizon = Utc::now() - policy.dispute_horizon;
+        let mut report = CompactionReport::default();
+
+        while log.segments.len() > 1 || log.segments.first().is_some_and(|s| log.is_sealed(s)) {
+            let segment = &log.segments[0];
+            if segment.last_sequence > snapshot_floor || segment.newest_timestamp >= horizon {
+                break;
+            }
+
+            let (first_sequence, last_sequence) = (segment.first_sequence, segment.last_sequence);
+            let archived = {
+                let (path, codec) = (segment.path.clone(), log.codec.clone());
+                let (dir, archive) = (self.dir.clone(), self.archive.clone());
+                // The archive and the anchor are durable before the hot segment goes away
+                blocking(move || {
+                    let envelopes = read_segment(&path, codec.as_ref())?;
+                    let archived = archive.write(&envelopes)?;
+                    if let Some(last) = envelopes.last() {
+                        write_anchor(
+                            &dir,
+                            &ChainAnchor {
+                                sequence_nr: last.sequence_nr,
+                                hash: last.hash.clone(),
+                            },
+                        )?;
+                    }
+                    fs::remove_file(&path).map_err(io_error)?;
+                    sync_dir(&dir)?;
+                    Ok(archived)
+                })
+                .await?
+            };
+            report.truncated_events += self.index.truncate(last_sequence + 1).await?;
+            report.archived_segments += 1;
+
+            tracing::info!(
+                "Archived journal segment {}..={} to {}",
+                first_sequence,
+                last_sequence,
+                archived.display()
+            );
+
+            log.segments.remove(0);
+            if log.segments.is_empty() {
+                log.writer = None;
+            }
+        }
+
+        report.hot_from_sequence = log.segments.first().map(|s| s.first_sequence);
+        Ok(report)
+    }
+
+    /// Bring an archived segment back into the hot log
+    pub async fn restore_archive(&self, path: &Path) -> Result<usize, PaymentError> {
+        let envelopes = {
+            let (archive, path) = (self.archive.clone(), path.to_path_buf());
+            blocking(move || archive.read(&path)).await?
+        };
+        let (Some(first), Some(last)) = (envelopes.first(), envelopes.last()) else {
+            return Ok(0);
+        };
+
+        let mut log = self.log.lock().await;
+        if log
+            .segments
+            .iter()
+            .any(|s| s.first_sequence <= last.sequence_nr && first.sequence_nr <= s.last_sequence)
+        {
+            return Err(PaymentError::Engine(EngineError::PersistenceError(
+                format!("Archive {} overlaps the hot log", path.display()),
+            )));
+        }
+
+        let segment_path = segment_path(&self.dir, first.sequence_nr);
+        let envelopes = {
+            let (path, codec) = (segment_path.clone(), log.codec.clone());
+            blocking(move || {
+                let mut writer = BufWriter::new(File::create(&path).map_err(io_error)?);
+                for envelope in &envelopes {
+                    write_line(&mut writer, envelope, codec.as_ref())?;
+                }
+                writer
+                    .into_inner()
+                    .map_err(|e| io_error(e.into_error()))?
+                    .sync_all()
+                    .map_err(io_error)?;
+                Ok(envelopes)
+            })
+            .await?
+        };
+        let (first, last) = (&envelopes[0], &envelopes[envelopes.len() - 1]);
+
+        let segment = Segment {
+            path: segment_path,
+            first_sequence: first.sequence_nr,
+            last_sequence: last.sequence_nr,
+            count: envelopes.len() as u64,
+            newest_timestamp: envelopes
+                .iter()
+                .map(|e| e.timestamp)
+                .max()
+                .unwrap_or(first.timestamp),
+        };
+        let position = log
+            .segments
+            .partition_point(|s| s.first_sequence < segment.first_sequence);
+        log.segments.insert(position, segment);
+
+        let restored = envelopes.len();
+        for envelope in envelopes {
+            self.index.import(envelope).await?;
+        }
+        Ok(restored)
+    }
+
+    /// Drop the plaintext copies of a client's events from the in-memory index
+    ///
+    /// Meant to follow the erasure of the client's data key: segments on disk are already
+    /// unreadable for that client and now the cache matches what a reopen would produce.
+    pub async fn redact_client(&self, client_id: u16) -> usize {
+        self.index.redact_client(client_id).await
+    }
+}
+
+#[async_trait]
+impl Journal for FileJournal {
+    async fn append(
+        &self,
+        event: TransactionTypeEvent,
+        metadata: EventMetadata,
+    ) -> Result<AppendOutcome, PaymentError> {
+        // The segment lock serializes appends so the file order matches sequence order,
+        // and keeps the prepared envelope valid until it is committed to the index
+        let log = self.log.clone().lock_owned().await;
+
+        let envelope = match self.index.prepare_append(event, metadata).await? {
+            AppendOutcome::Appended(envelope) => envelope,
+            // Duplicates are answered from the index without touching the disk
+            duplicate => return Ok(duplicate),
+        };
+
+        commit(self.index.clone(), log, envelope)
+            .await
+            .map(AppendOutcome::Appended)
+    }
+
+    async fn replay(&self, from_sequence: Option<u64>) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.index.replay(from_sequence).await
+    }
+
+    async fn highest_sequence(&self) -> Result<Option<u64>, PaymentError> {
+        self.index.highest_sequence().await
+    }
+
+    async fn find_by_tx_id(&self, tx_id: u32) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.index.find_by_tx_id(tx_id).await
+    }
+
+    async fn visit_transaction(
+        &self,
+        tx_id: u32,
+        visitor: &mut (dyn for<'e> FnMut(&'e TransactionTypeEvent) + Send),
+    ) -> Result<(), PaymentError> {
+        self.index.visit_transaction(tx_id, visitor).await
+    }
+
+    async fn find_by_deduplication_key(
+        &self,
+        client_id: u16,
+        deduplication_key: &DeduplicationKey,
+    ) -> Result<Option<EventEnvelope>, PaymentError> {
+        self.index
+            .find_by_deduplication_key(client_id, deduplication_key)
+            .await
+    }
+
+    async fn query(&self, query: &JournalQuery) -> Result<JournalPage, PaymentError> {
+        self.index.query(query).await
+    }
+
+    async fn import(&self, envelope: EventEnvelope) -> Result<(), PaymentError> {
+        let log = self.log.clone().lock_owned().await;
+        let head = self.index.highest_sequence().await?.unwrap_or(0);
+
+        if envelope.sequence_nr <= head {
+            // Segments are append-only; older history comes back through restore_archive
+            if let Some(stored_hash) = self.index.stored_hash(envelope.sequence_nr).await {
+                return check_same_history(&stored_hash, &envelope);
+            }
+            return Err(PaymentError::Engine(EngineError::PersistenceError(
+                format!(
+                    "Cannot import sequence {} below journal head {}",
+                    envelope.sequence_nr, head
+                ),
+            )));
+        }
+
+        commit(self.index.clone(), log, envelope).await.map(|_| ())
+    }
+
+    /// Truncated events are moved to the archive, like compacted ones, so their
+    /// deduplication keys survive a reopen; segments are rewritten without them
+    async fn truncate(&self, before_sequence: u64) -> Result<usize, PaymentError> {
+        let mut log = self.log.lock().await;
+
+        while let Some(segment) = log.segments.first() {
+            if segment.first_sequence >= before_sequence {
+                break;
+            }
+
+            let (path, codec) = (segment.path.clone(), log.codec.clone());
+            let (dir, archive) = (self.dir.clone(), self.archive.clone());
+            let kept = blocking(move || {
+                let mut envelopes = read_segment(&path, codec.as_ref())?;
+                let cut = envelopes.partition_point(|e| e.sequence_nr < before_sequence);
+                let kept = envelopes.split_off(cut);
+
+                // Archive and anchor are durable before the segment loses the events
+                if let Some(last) = envelopes.last() {
+                    archive.write(&envelopes)?;
+                    write_anchor(
+                        &dir,
+                        &ChainAnchor {
+                            sequence_nr: last.sequence_nr,
+                            hash: last.hash.clone(),
+                        },
+                    )?;
+                }
+                if kept.is_empty() {
+                    fs::remove_file(&path).map_err(io_error)?;
+                } else {
+                    rewrite_segment(&path, &kept, codec.as_ref())?;
+                }
+                sync_dir(&dir)?;
+                Ok(kept)
+            })
+            .await?;
+
+            let active = log.segments.len() == 1;
+            match (kept.first(), kept.last()) {
+                (Some(first), Some(last)) => {
+                    let segment = &mut log.segments[0];
+                    segment.first_sequence = first.sequence_nr;
+                    segment.last_sequence = last.sequence_nr;
+                    segment.count = kept.len() as u64;
+                    segment.newest_timestamp = kept
+                        .iter()
+                        .map(|e| e.timestamp)
+                        .max()
+                        .unwrap_or(first.timestamp);
+                }
+                _ => {
+                    log.segments.remove(0);
+                }
+            }
+            // The active segment file was replaced or removed
+            if active {
+                log.writer = None;
+            }
+        }
+
+        self.index.truncate(before_sequence).await
+    }
+
+    async fn anchor(&self) -> Result<Option<ChainAnchor>, PaymentError> {
+        self.index.anchor().await
+    }
+}
+
+/// Write a record to its segment and enter it into the index as one unit
+///
+/// Both steps run on a task of their own: a caller dropping its future halfway cannot
+/// leave a record on disk that the index, and so the sequencer, never saw.
+async fn commit(
+    index: Arc<InMemoryJournal>,
+    mut log: OwnedMutexGuard<SegmentLog>,
+    envelope: EventEnvelope,
+) -> Result<EventEnvelope, PaymentError> {
+    tokio::spawn(async move {
+        let (_log, envelope) =
+            blocking(move || log.write(&envelope).map(|_| (log, envelope))).await?;
+        index.import(envelope.clone()).await?;
+        Ok(envelope)
+    })
+    .await
+    .map_err(|e| PaymentError::Engine(EngineError::PersistenceError(e.to_string())))?
+}
+
+/// Run blocking file I/O on tokio's blocking pool instead of an async worker thread
+pub(crate) async fn blocking<T: Send + 'static>(
+    io: impl FnOnce() -> Result<T, PaymentError> + Send + 'static,
+) -> Result<T, PaymentError> {
+    tokio::task::spawn_blocking(io)
+        .await
+        .map_err(|e| PaymentError::Engine(EngineError::PersistenceError(e.to_string())))?
+}
+
+fn read_anchor(dir: &Path) -> Result<Option<ChainAnchor>, PaymentError> {
+    match fs::read_to_string(dir.join(ANCHOR_FILE)) {
+        Ok(content) => serde_json::from_str(&content).map(Some).map_err(json_error),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(io_error(e)),
+    }
+}
+
+/// Replace the anchor atomically: a crash leaves either the old or the new one, and the
+/// new one is durable once this returns
+fn write_anchor(dir: &Path, anchor: &ChainAnchor) -> Result<(), PaymentError> {
+    let path = dir.join(ANCHOR_FILE);
+    let tmp_path = path.with_extension("json.tmp");
+
+    let mut file = File::create(&tmp_path).map_err(io_error)?;
+    file.write_all(&serde_json::to_vec(anchor).map_err(json_error)?)
+        .and_then(|_| file.sync_all())
+        .map_err(io_error)?;
+    fs::rename(&tmp_path, &path).map_err(io_error)?;
+    sync_dir(dir)
+}
+
+fn segment_path(dir: &Path, first_sequence: u64) -> PathBuf {
+    dir.join(format!("segment-{:020}.jsonl", first_sequence))
+}
+
+fn list_segments(dir: &Path) -> Result<Vec<PathBuf>, PaymentError> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(io_error)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("segment-") && name.ends_with(".jsonl"))
+        })
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn read_segment(
+    path: &Path,
+    codec: &dyn EnvelopeCodec,
+) -> Result<Vec<EventEnvelope>, PaymentError> {
+    let reader = BufReader::new(File::open(path).map_err(io_error)?);
+
+    let mut envelopes = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(io_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        envelopes.push(codec.decode(&line)?);
+    }
+    Ok(envelopes)
+}
+
+/// Cut a segment back to its last complete (newline-terminated) record
+///
+/// Returns the number of bytes removed.
+fn drop_torn_tail(path: &Path) -> Result<u64, PaymentError> {
+    const CHUNK: u64 = 8 * 1024;
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(io_error)?;
+    let length = file.metadata().map_err(io_error)?.len();
+
+    // Scan backwards for the last newline
+    let mut end = length;
+    let mut kept = 0;
+    let mut buffer = vec![0u8; CHUNK as usize];
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let chunk = &mut buffer[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start)).map_err(io_error)?;
+        file.read_exact(chunk).map_err(io_error)?;
+        if let Some(newline) = chunk.iter().rposition(|&byte| byte == b'\n') {
+            kept = start + newline as u64 + 1;
+            break;
+        }
+        end = start;
+    }
+
+    if kept < length {
+        file.set_len(kept)
+            .and_then(|_| file.sync_all())
+            .map_err(io_error)?;
+    }
+    Ok(length - kept)
+}
+
+/// Atomically replace a segment's records
+fn rewrite_segment(
+    path: &Path,
+    envelopes: &[EventEnvelope],
+    codec: &dyn EnvelopeCodec,
+) -> Result<(), PaymentError> {
+    let tmp_path = path.with_extension("jsonl.tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path).map_err(io_error)?);
+    for envelope in envelopes {
+        write_line(&mut writer, envelope, codec)?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| io_error(e.into_error()))?
+        .sync_all()
+        .map_err(io_error)?;
+    fs::rename(&tmp_path, path).map_err(io_error)
+}
+
+fn write_line(
+    writer: &mut impl Write,
+    envelope: &EventEnvelope,
+    codec: &dyn EnvelopeCodec,
+) -> Result<(), PaymentError> {
+    let record = codec.encode(envelope)?;
+    writer.write_all(record.as_bytes()).map_err(io_error)?;
+    writer.write_all(b"\n").map_err(io_error)
+}
@@ -27,18 +27,22 @@ impl TransactionLookup for JournalTransactionLookup {
         &self,
         tx_id: u32,
     ) -> Result<Option<TransactionTypeEvent>, PaymentError> {
-        let events = self.journal.find_by_tx_id(tx_id).await?;
-
-        for envelope in events {
-            match &envelope.event {
-                TransactionTypeEvent::Deposited(_) | TransactionTypeEvent::Withdrawn(_) => {
-                    return Ok(Some(envelope.event.clone()));
+        // Only the original deposit or withdrawal is cloned out of the journal
+        let mut original = None;
+        self.journal
+            .visit_transaction(tx_id, &mut |event| {
+                if original.is_none()
+                    && matches!(
+                        event,
+                        TransactionTypeEvent::Deposited(_) | TransactionTypeEvent::Withdrawn(_)
+                    )
+                {
+                    original = Some(event.clone());
                 }
-                _ => continue,
-            }
-        }
+            })
+            .await?;
 
-        Ok(None)
+        Ok(original)
     }
 
     async fn is_disputed(&self, tx_id: u32) -> Result<bool, PaymentError> {
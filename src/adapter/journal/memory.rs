@@ -1,35 +1,417 @@
 use crate::{
-    domain::{DeduplicationKey, EventEnvelope, EventMetadata, PaymentError, TransactionTypeEvent},
+    adapter::journal::{
+        arena::{EventArena, EventRef},
+        dedup::{ANY_PAYLOAD, DeduplicationIndex, DeduplicationLookup},
+        query_index::{Candidates, QueryIndex},
+    },
+    domain::{
+        AppendOutcome, ChainAnchor, DeduplicationKey, DeduplicationWindow, EngineError,
+        EventEnvelope, EventKind, EventMetadata, GENESIS_HASH, JournalPage, JournalQuery,
+        PaymentError, Redacted, TransactionTypeEvent,
+    },
     port::Journal,
 };
 use async_trait::async_trait;
+use smallvec::SmallVec;
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{RwLock, RwLockReadGuard};
 
-struct JournalData {
-    events: Vec<Arc<EventEnvelope>>,
-    deduplication_index: HashMap<DeduplicationKey, Arc<EventEnvelope>>,
-    tx_id_index: HashMap<u32, Vec<Arc<EventEnvelope>>>,
+/// Events of the clients mapped to one shard
+struct JournalShard {
+    events: EventArena,
+    /// Sequence numbers of the events of each transaction, in log order
+    tx_id_index: HashMap<u32, SmallVec<[u64; 2]>>,
+    /// Client, kind and time indexes backing `query`
+    query_index: QueryIndex,
+}
+
+impl JournalShard {
+    /// Arena positions of a transaction's events that are still in the hot log
+    fn tx_positions(&self, tx_id: u32) -> impl Iterator<Item = usize> + '_ {
+        self.tx_id_index
+            .get(&tx_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|&sequence_nr| self.events.position(sequence_nr).ok())
+    }
+
+    /// Original event of a redelivered command, or an error if its key cannot be reused
+    ///
+    /// Keys are scoped to a client, so the original event always lives in this shard.
+    fn find_duplicate(
+        &self,
+        deduplication_key: &DeduplicationKey,
+        lookup: DeduplicationLookup,
+        payload: u64,
+    ) -> Result<Option<EventEnvelope>, PaymentError> {
+        if let DeduplicationLookup::Hit {
+            payload: original, ..
+        } = lookup
+        {
+            // Same key, different content: not a redelivery (like an HTTP idempotency key)
+            if original != ANY_PAYLOAD && original != payload {
+                return Err(PaymentError::Engine(EngineError::IdempotencyConflict(
+                    deduplication_key.as_str().to_string(),
+                )));
+            }
+        }
+        self.find_original(deduplication_key, lookup)
+    }
+
+    /// Event journaled under a deduplication key, or an error if it is no longer available
+    fn find_original(
+        &self,
+        deduplication_key: &DeduplicationKey,
+        lookup: DeduplicationLookup,
+    ) -> Result<Option<EventEnvelope>, PaymentError> {
+        match lookup {
+            DeduplicationLookup::Hit { sequence_nr, .. } => {
+                // The original event may have been compacted out of the hot log since
+                match self.events.position(sequence_nr) {
+                    Ok(position) => Ok(Some(self.events.get(position).to_envelope())),
+                    Err(_) => Err(PaymentError::Engine(EngineError::DuplicateOutsideWindow(
+                        deduplication_key.as_str().to_string(),
+                    ))),
+                }
+            }
+            DeduplicationLookup::Expired => Err(PaymentError::Engine(
+                EngineError::DuplicateOutsideWindow(deduplication_key.as_str().to_string()),
+            )),
+            DeduplicationLookup::Miss => Ok(None),
+        }
+    }
+
+    /// First `limit` events of the shard matching a query, in log order
+    fn query(
+        &self,
+        query: &JournalQuery,
+        bounds: std::ops::RangeInclusive<u64>,
+        limit: usize,
+    ) -> Vec<EventRef<'_>> {
+        let matches = |event: &EventRef<'_>| {
+            query.matches_fields(
+                event.sequence_nr(),
+                event.client_id(),
+                event.event().kind(),
+                event.timestamp(),
+            )
+        };
+
+        match self.query_index.candidates(query, bounds) {
+            None => Vec::new(),
+            Some(Candidates::Range(range)) => {
+                let start = self.events.lower_bound(*range.start());
+                self.events
+                    .iter_from(start)
+                    .take_while(|event| event.sequence_nr() <= *range.end())
+                    .filter(matches)
+                    .take(limit)
+                    .collect()
+            }
+            Some(Candidates::Postings(postings)) => {
+                // The first `limit` matches overall are among the first `limit` of each list
+                let mut refs: Vec<EventRef<'_>> = postings
+                    .into_iter()
+                    .flat_map(|postings| {
+                        postings
+                            .iter()
+                            .filter_map(|&sequence_nr| self.events.position(sequence_nr).ok())
+                            .map(|position| self.events.get(position))
+                            .filter(matches)
+                            .take(limit)
+                    })
+                    .collect();
+                refs.sort_by_key(|event| event.sequence_nr());
+                refs.truncate(limit);
+                refs
+            }
+        }
+    }
+}
+
+/// Global ordering service shared by all shards
+///
+/// Hands out sequence numbers and extends the hash chain. Envelopes are placed into their
+/// shard while the sequencer is held, so every sequence number up to `sequence_counter`
+/// is visible in some shard once the lock is released.
+struct Sequencer {
     sequence_counter: u64,
+    /// Hash of the envelope at the head of the log, linked into the next append
+    chain_head: String,
+    /// Last envelope truncated from the front of the log
+    anchor: Option<ChainAnchor>,
+}
+
+impl Sequencer {
+    /// Build the envelope that extends the chain with an event, without claiming its number
+    fn seal(&self, event: TransactionTypeEvent, metadata: EventMetadata) -> EventEnvelope {
+        let mut envelope = EventEnvelope {
+            sequence_nr: self.sequence_counter + 1,
+            event,
+            timestamp: metadata.timestamp,
+            client_id: metadata.client_id,
+            tx_id: metadata.tx_id,
+            deduplication_key: metadata.deduplication_key,
+            previous_hash: self.chain_head.clone(),
+            hash: String::new(),
+        };
+        envelope.hash = envelope.content_hash();
+        envelope
+    }
+}
+
+/// Configuration for the in-memory journal
+#[derive(Debug, Clone, Copy)]
+pub struct InMemoryJournalConfig {
+    /// How long deduplication keys are remembered (applies across all shards)
+    pub deduplication_window: DeduplicationWindow,
+    /// Number of shards clients are partitioned into
+    pub shards: usize,
+}
+
+impl Default for InMemoryJournalConfig {
+    fn default() -> Self {
+        Self {
+            deduplication_window: DeduplicationWindow::default(),
+            shards: 16,
+        }
+    }
 }
 
 /// In-memory journal implementation
+///
+/// Events are partitioned by `client_id` into shards, each behind its own lock, so client
+/// actors only contend with actors of the same shard. A small sequencer still assigns the
+/// global sequence numbers and hash chain that replay relies on for total order.
+///
+/// Within a shard, events are kept in a columnar arena; the deduplication and `tx_id`
+/// indexes only hold sequence numbers, so each event is stored exactly once. Deduplication
+/// keys are scoped to the client that issued the command and kept in a single index shared
+/// by the shards, so the window and its Bloom filter are those of the whole journal.
 pub struct InMemoryJournal {
-    data: Arc<RwLock<JournalData>>,
+    shards: Arc<[RwLock<JournalShard>]>,
+    sequencer: Arc<Mutex<Sequencer>>,
+    deduplication_index: Arc<Mutex<DeduplicationIndex>>,
 }
 
 impl InMemoryJournal {
     pub fn new() -> Self {
+        Self::with_config(InMemoryJournalConfig::default())
+    }
+
+    pub fn with_config(config: InMemoryJournalConfig) -> Self {
+        let shards = (0..config.shards.max(1))
+            .map(|_| {
+                RwLock::new(JournalShard {
+                    events: EventArena::default(),
+                    tx_id_index: HashMap::new(),
+                    query_index: QueryIndex::default(),
+                })
+            })
+            .collect();
+
         Self {
-            data: Arc::new(RwLock::new(JournalData {
-                events: Vec::with_capacity(1000000),
-                deduplication_index: HashMap::with_capacity(1000000),
-                tx_id_index: HashMap::with_capacity(1000000),
+            shards,
+            sequencer: Arc::new(Mutex::new(Sequencer {
                 sequence_counter: 0,
+                chain_head: GENESIS_HASH.to_string(),
+                anchor: None,
             })),
+            deduplication_index: Arc::new(Mutex::new(DeduplicationIndex::new(
+                config.deduplication_window,
+            ))),
         }
     }
+
+    fn shard(&self, client_id: u16) -> &RwLock<JournalShard> {
+        &self.shards[client_id as usize % self.shards.len()]
+    }
+
+    fn sequencer(&self) -> std::sync::MutexGuard<'_, Sequencer> {
+        // The sequencer is only held for plain field updates, so a poisoned lock still holds consistent data
+        self.sequencer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Shared deduplication index
+    ///
+    /// Callers hold the shard of the key's client, which keeps lookups and inserts of one
+    /// key in order; the index itself is only locked for the map update.
+    fn deduplication_index(&self) -> std::sync::MutexGuard<'_, DeduplicationIndex> {
+        self.deduplication_index
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Highest sequence number whose event is visible in its shard
+    fn watermark(&self) -> u64 {
+        self.sequencer().sequence_counter
+    }
+
+    /// Read every shard, in shard order
+    async fn read_shards(&self) -> Vec<RwLockReadGuard<'_, JournalShard>> {
+        let mut guards = Vec::with_capacity(self.shards.len());
+        for shard in self.shards.iter() {
+            guards.push(shard.read().await);
+        }
+        guards
+    }
+
+    /// Replace every cached event of a client with a Redacted tombstone
+    ///
+    /// Used after a client's data key was erased, so plaintext copies do not outlive the key.
+    /// Envelope metadata and chain hashes are kept, and the tombstones are `shredded` like
+    /// the ones the codec returns for the erased records. Returns the number of redacted events.
+    pub async fn redact_client(&self, client_id: u16) -> usize {
+        let mut shard = self.shard(client_id).write().await;
+
+        let positions: Vec<(usize, u32)> = shard
+            .events
+            .iter_from(0)
+            .enumerate()
+            .filter(|(_, event)| {
+                event.client_id() == client_id
+                    && !matches!(event.event(), TransactionTypeEvent::Redacted(_))
+            })
+            .map(|(position, event)| (position, event.tx_id()))
+            .collect();
+
+        for &(position, tx_id) in &positions {
+            let event = shard.events.get(position);
+            let (sequence_nr, kind) = (event.sequence_nr(), event.event().kind());
+            shard
+                .query_index
+                .change_kind(sequence_nr, kind, EventKind::Redacted);
+            shard.events.replace_event(
+                position,
+                TransactionTypeEvent::Redacted(Redacted {
+                    client_id,
+                    tx_id,
+                    shredded: true,
+                }),
+            );
+        }
+
+        positions.len()
+    }
+
+    /// Resolve an append without applying it
+    ///
+    /// Returns the original event of a duplicate, or the envelope the append would add.
+    /// Lets a durable journal persist the envelope before committing it with `import`;
+    /// the caller must keep other appends out until then.
+    pub(crate) async fn prepare_append(
+        &self,
+        event: TransactionTypeEvent,
+        metadata: EventMetadata,
+    ) -> Result<AppendOutcome, PaymentError> {
+        let fingerprint = metadata.deduplication_key.fingerprint(metadata.client_id);
+        let payload = event.payload_fingerprint();
+
+        let shard = self.shard(metadata.client_id).write().await;
+        let lookup = self.deduplication_index().lookup(fingerprint);
+        if let Some(original) =
+            shard.find_duplicate(&metadata.deduplication_key, lookup, payload)?
+        {
+            return Ok(AppendOutcome::Duplicate(original));
+        }
+
+        Ok(AppendOutcome::Appended(
+            self.sequencer().seal(event, metadata),
+        ))
+    }
+
+    /// Register an event that lives outside the hot log (e.g. archived) without storing it
+    ///
+    /// Its deduplication key is remembered and the sequencer moves past it, so a reopened
+    /// journal neither reuses its sequence number nor accepts its command a second time.
+    pub(crate) async fn remember(&self, envelope: &EventEnvelope) {
+        let _shard = self.shard(envelope.client_id).write().await;
+        self.deduplication_index().insert(
+            envelope.deduplication_key.fingerprint(envelope.client_id),
+            envelope.sequence_nr,
+            stored_payload(&envelope.event),
+            envelope.timestamp,
+        );
+        self.advance_head(envelope.sequence_nr, &envelope.hash);
+    }
+
+    /// Continue the chain after an anchor, unless the log is already past it
+    pub(crate) fn seed_anchor(&self, anchor: ChainAnchor) {
+        self.advance_head(anchor.sequence_nr, &anchor.hash);
+        self.advance_anchor(anchor);
+    }
+
+    fn advance_anchor(&self, anchor: ChainAnchor) {
+        let mut sequencer = self.sequencer();
+        if sequencer
+            .anchor
+            .as_ref()
+            .is_none_or(|current| anchor.sequence_nr > current.sequence_nr)
+        {
+            sequencer.anchor = Some(anchor);
+        }
+    }
+
+    fn advance_head(&self, sequence_nr: u64, hash: &str) {
+        let mut sequencer = self.sequencer();
+        if sequence_nr > sequencer.sequence_counter {
+            sequencer.sequence_counter = sequence_nr;
+            sequencer.chain_head = hash.to_string();
+        }
+    }
+
+    /// Check whether an event with this sequence number is in the hot log
+    pub async fn contains_sequence(&self, sequence_nr: u64) -> bool {
+        self.stored_hash(sequence_nr).await.is_some()
+    }
+
+    /// Chain hash of the event with this sequence number, if it is in the hot log
+    pub async fn stored_hash(&self, sequence_nr: u64) -> Option<String> {
+        for shard in self.shards.iter() {
+            let shard = shard.read().await;
+            if let Ok(position) = shard.events.position(sequence_nr) {
+                return Some(shard.events.get(position).hash());
+            }
+        }
+        None
+    }
+
+    /// Visit events from a sequence number on, in log order, without materializing envelopes
+    pub async fn visit(&self, from_sequence: Option<u64>, mut visitor: impl FnMut(EventRef<'_>)) {
+        let watermark = self.watermark();
+        let from = from_sequence.unwrap_or(0);
+        let shards = self.read_shards().await;
+
+        let mut refs: Vec<EventRef<'_>> = shards
+            .iter()
+            .flat_map(|shard| shard.events.iter_from(shard.events.lower_bound(from)))
+            .filter(|event| event.sequence_nr() <= watermark)
+            .collect();
+        // Each shard is already sorted; the stable sort merges the runs in linear time
+        refs.sort_by_key(|event| event.sequence_nr());
+        refs.into_iter().for_each(&mut visitor);
+    }
+
+    /// Visit the events of a transaction, in log order, without materializing envelopes
+    pub async fn visit_tx_id(&self, tx_id: u32, mut visitor: impl FnMut(EventRef<'_>)) {
+        let watermark = self.watermark();
+        let shards = self.read_shards().await;
+
+        let mut refs: Vec<EventRef<'_>> = shards
+            .iter()
+            .flat_map(|shard| {
+                shard
+                    .tx_positions(tx_id)
+                    .map(|position| shard.events.get(position))
+            })
+            .filter(|event| event.sequence_nr() <= watermark)
+            .collect();
+        refs.sort_by_key(|event| event.sequence_nr());
+        refs.into_iter().for_each(&mut visitor);
+    }
 }
 
 #[async_trait]
@@ -38,66 +420,203 @@ impl Journal for InMemoryJournal {
         &self,
         event: TransactionTypeEvent,
         metadata: EventMetadata,
-    ) -> Result<EventEnvelope, PaymentError> {
-        let deduplication_key = metadata.deduplication_key;
-
-        let mut data = self.data.write().await;
+    ) -> Result<AppendOutcome, PaymentError> {
+        let fingerprint = metadata.deduplication_key.fingerprint(metadata.client_id);
+        let payload = event.payload_fingerprint();
 
-        if let Some(existing) = data.deduplication_index.get(&deduplication_key) {
-            return Ok((**existing).clone());
+        let mut shard = self.shard(metadata.client_id).write().await;
+        let lookup = self.deduplication_index().lookup(fingerprint);
+        if let Some(original) =
+            shard.find_duplicate(&metadata.deduplication_key, lookup, payload)?
+        {
+            return Ok(AppendOutcome::Duplicate(original));
         }
 
-        data.sequence_counter += 1;
-        let sequence_nr = data.sequence_counter;
+        let envelope = {
+            let mut sequencer = self.sequencer();
+            let envelope = sequencer.seal(event, metadata);
+            sequencer.sequence_counter = envelope.sequence_nr;
+            sequencer.chain_head = envelope.hash.clone();
 
-        let envelope = Arc::new(EventEnvelope {
-            sequence_nr,
-            event,
-            timestamp: metadata.timestamp,
-            client_id: metadata.client_id,
-            tx_id: metadata.tx_id,
-            deduplication_key: deduplication_key.clone(),
-        });
+            shard.events.push(envelope.clone());
+            envelope
+        };
 
-        data.events.push(envelope.clone());
-        data.deduplication_index
-            .insert(deduplication_key, envelope.clone());
-        data.tx_id_index
-            .entry(metadata.tx_id)
-            .or_insert_with(|| Vec::with_capacity(1000))
-            .push(envelope.clone());
+        self.deduplication_index().insert(
+            fingerprint,
+            envelope.sequence_nr,
+            payload,
+            envelope.timestamp,
+        );
+        shard
+            .tx_id_index
+            .entry(envelope.tx_id)
+            .or_default()
+            .push(envelope.sequence_nr);
+        shard.query_index.insert(
+            envelope.sequence_nr,
+            envelope.client_id,
+            envelope.event.kind(),
+            envelope.timestamp,
+        );
 
-        Ok((*envelope).clone())
+        Ok(AppendOutcome::Appended(envelope))
     }
 
     async fn replay(&self, from_sequence: Option<u64>) -> Result<Vec<EventEnvelope>, PaymentError> {
-        let data = self.data.read().await;
-        let from = from_sequence.unwrap_or(0);
-
-        Ok(data
-            .events
-            .iter()
-            .filter(|e| e.sequence_nr >= from)
-            .map(|arc| (**arc).clone())
-            .collect())
+        let mut envelopes = Vec::new();
+        self.visit(from_sequence, |event| envelopes.push(event.to_envelope()))
+            .await;
+        Ok(envelopes)
     }
 
     async fn highest_sequence(&self) -> Result<Option<u64>, PaymentError> {
-        let data = self.data.read().await;
-        if data.sequence_counter == 0 {
-            Ok(None)
-        } else {
-            Ok(Some(data.sequence_counter))
+        match self.watermark() {
+            0 => Ok(None),
+            sequence_nr => Ok(Some(sequence_nr)),
         }
     }
 
     async fn find_by_tx_id(&self, tx_id: u32) -> Result<Vec<EventEnvelope>, PaymentError> {
-        let data = self.data.read().await;
-        Ok(data
-            .tx_id_index
-            .get(&tx_id)
-            .map(|arcs| arcs.iter().map(|arc| (**arc).clone()).collect())
-            .unwrap_or_default())
+        let mut envelopes = Vec::new();
+        self.visit_tx_id(tx_id, |event| envelopes.push(event.to_envelope()))
+            .await;
+        Ok(envelopes)
+    }
+
+    async fn visit_transaction(
+        &self,
+        tx_id: u32,
+        visitor: &mut (dyn for<'e> FnMut(&'e TransactionTypeEvent) + Send),
+    ) -> Result<(), PaymentError> {
+        self.visit_tx_id(tx_id, |event| visitor(event.event())).await;
+        Ok(())
+    }
+
+    async fn find_by_deduplication_key(
+        &self,
+        client_id: u16,
+        deduplication_key: &DeduplicationKey,
+    ) -> Result<Option<EventEnvelope>, PaymentError> {
+        let shard = self.shard(client_id).read().await;
+        let lookup = self
+            .deduplication_index()
+            .lookup(deduplication_key.fingerprint(client_id));
+        shard.find_original(deduplication_key, lookup)
+    }
+
+    async fn query(&self, query: &JournalQuery) -> Result<JournalPage, PaymentError> {
+        let bounds =
+            query.lowest_sequence()..=query.to_sequence.unwrap_or(u64::MAX).min(self.watermark());
+        // One extra match tells whether there is a next page
+        let limit = query.limit.saturating_add(1);
+
+        let shards: Vec<_> = match query.client_id {
+            Some(client_id) => vec![self.shard(client_id).read().await],
+            None => self.read_shards().await,
+        };
+
+        let mut refs: Vec<EventRef<'_>> = shards
+            .iter()
+            .flat_map(|shard| shard.query(query, bounds.clone(), limit))
+            .collect();
+        refs.sort_by_key(|event| event.sequence_nr());
+        refs.truncate(limit);
+
+        Ok(JournalPage::from_matches(
+            refs.iter().map(|event| event.to_envelope()).collect(),
+            query.limit,
+        ))
+    }
+
+    async fn import(&self, envelope: EventEnvelope) -> Result<(), PaymentError> {
+        let mut shard = self.shard(envelope.client_id).write().await;
+
+        // Imports may arrive below the current head (e.g. restoring an archived segment),
+        // so keep the log sorted by sequence number instead of blindly pushing.
+        let position = match shard.events.position(envelope.sequence_nr) {
+            Ok(existing) => {
+                return check_same_history(&shard.events.get(existing).hash(), &envelope);
+            }
+            Err(position) => position,
+        };
+
+        self.deduplication_index().insert(
+            envelope.deduplication_key.fingerprint(envelope.client_id),
+            envelope.sequence_nr,
+            stored_payload(&envelope.event),
+            envelope.timestamp,
+        );
+
+        let postings = shard.tx_id_index.entry(envelope.tx_id).or_default();
+        let posting = postings.partition_point(|&s| s < envelope.sequence_nr);
+        postings.insert(posting, envelope.sequence_nr);
+        shard.query_index.insert(
+            envelope.sequence_nr,
+            envelope.client_id,
+            envelope.event.kind(),
+            envelope.timestamp,
+        );
+
+        self.advance_head(envelope.sequence_nr, &envelope.hash);
+        shard.events.insert(position, envelope);
+
+        Ok(())
+    }
+
+    async fn truncate(&self, before_sequence: u64) -> Result<usize, PaymentError> {
+        let mut truncated = 0;
+
+        for shard in self.shards.iter() {
+            let mut shard = shard.write().await;
+
+            let cut = shard.events.lower_bound(before_sequence);
+            if cut == 0 {
+                continue;
+            }
+            let last = shard.events.get(cut - 1).to_envelope();
+            self.advance_anchor(ChainAnchor {
+                sequence_nr: last.sequence_nr,
+                hash: last.hash,
+            });
+            shard.events.drain_front(cut);
+            truncated += cut;
+
+            shard.query_index.truncate(before_sequence);
+            shard.tx_id_index.retain(|_, postings| {
+                postings.retain(|s| *s >= before_sequence);
+                !postings.is_empty()
+            });
+        }
+
+        Ok(truncated)
+    }
+
+    async fn anchor(&self) -> Result<Option<ChainAnchor>, PaymentError> {
+        Ok(self.sequencer().anchor.clone())
+    }
+}
+
+/// An import of a sequence number that is already stored must carry the same history
+pub(crate) fn check_same_history(
+    stored_hash: &str,
+    envelope: &EventEnvelope,
+) -> Result<(), PaymentError> {
+    if stored_hash == envelope.hash {
+        return Ok(());
+    }
+    Err(PaymentError::Engine(EngineError::HistoryConflict(format!(
+        "sequence {} is stored with hash {}, import has {}",
+        envelope.sequence_nr, stored_hash, envelope.hash
+    ))))
+}
+
+/// Payload fingerprint to remember for an event's deduplication key
+fn stored_payload(event: &TransactionTypeEvent) -> u64 {
+    match event {
+        // A redacted tombstone no longer carries the payload its key was used for
+        TransactionTypeEvent::Redacted(_) => ANY_PAYLOAD,
+        event => event.payload_fingerprint(),
     }
 }
 
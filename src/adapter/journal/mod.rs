@@ -1,5 +1,15 @@
+mod archive;
+mod arena;
+mod codec;
+mod dedup;
+mod file;
 mod lookup;
 mod memory;
+mod query_index;
 
+pub use archive::*;
+pub use arena::EventRef;
+pub use codec::*;
+pub use file::*;
 pub use lookup::*;
 pub use memory::*;
@@ -0,0 +1,142 @@
+use crate::domain::{EventKind, JournalQuery};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
+
+/// Width of the buckets of the time index
+const TIME_BUCKET_SECONDS: i64 = 3600;
+
+/// Secondary indexes of a journal shard, used to answer `Journal::query`
+///
+/// Postings hold sequence numbers in log order. The time index is coarse: for each hour it
+/// keeps the range of sequence numbers of the events stamped within it, which narrows a
+/// time-range query to a sequence range without requiring timestamps to be monotonic.
+#[derive(Default)]
+pub(crate) struct QueryIndex {
+    clients: HashMap<u16, Vec<u64>>,
+    kinds: HashMap<EventKind, Vec<u64>>,
+    hours: BTreeMap<i64, RangeInclusive<u64>>,
+}
+
+/// Candidate sequence numbers for a query, before exact filtering
+pub(crate) enum Candidates<'a> {
+    /// Every event of the shard within a sequence range
+    Range(RangeInclusive<u64>),
+    /// Postings lists (each in log order) restricted to a sequence range
+    Postings(Vec<&'a [u64]>),
+}
+
+impl QueryIndex {
+    pub(crate) fn insert(
+        &mut self,
+        sequence_nr: u64,
+        client_id: u16,
+        kind: EventKind,
+        timestamp: DateTime<Utc>,
+    ) {
+        insert_sorted(self.clients.entry(client_id).or_default(), sequence_nr);
+        insert_sorted(self.kinds.entry(kind).or_default(), sequence_nr);
+
+        let range = self
+            .hours
+            .entry(hour(timestamp))
+            .or_insert(sequence_nr..=sequence_nr);
+        *range = (*range.start()).min(sequence_nr)..=(*range.end()).max(sequence_nr);
+    }
+
+    /// Move an event to another kind (e.g. when it is redacted)
+    pub(crate) fn change_kind(&mut self, sequence_nr: u64, from: EventKind, to: EventKind) {
+        if let Some(postings) = self.kinds.get_mut(&from)
+            && let Ok(position) = postings.binary_search(&sequence_nr)
+        {
+            postings.remove(position);
+        }
+        insert_sorted(self.kinds.entry(to).or_default(), sequence_nr);
+    }
+
+    /// Forget every event before `before_sequence`
+    pub(crate) fn truncate(&mut self, before_sequence: u64) {
+        for postings in self.clients.values_mut().chain(self.kinds.values_mut()) {
+            let cut = postings.partition_point(|&s| s < before_sequence);
+            postings.drain(..cut);
+        }
+        self.clients.retain(|_, postings| !postings.is_empty());
+        self.hours
+            .retain(|_, range| *range.end() >= before_sequence);
+    }
+
+    /// Narrow a query to the smallest candidate set the indexes allow
+    ///
+    /// `bounds` is the sequence range allowed by the query's sequence bounds and cursor.
+    pub(crate) fn candidates(
+        &self,
+        query: &JournalQuery,
+        bounds: RangeInclusive<u64>,
+    ) -> Option<Candidates<'_>> {
+        let bounds = self.time_bounds(query, bounds)?;
+
+        if let Some(client_id) = query.client_id {
+            let postings = self.clients.get(&client_id).map(Vec::as_slice)?;
+            return Some(Candidates::Postings(vec![within(postings, &bounds)]));
+        }
+
+        if !query.kinds.is_empty() {
+            return Some(Candidates::Postings(
+                query
+                    .kinds
+                    .iter()
+                    .filter_map(|kind| self.kinds.get(kind))
+                    .map(|postings| within(postings, &bounds))
+                    .collect(),
+            ));
+        }
+
+        Some(Candidates::Range(bounds))
+    }
+
+    fn time_bounds(
+        &self,
+        query: &JournalQuery,
+        bounds: RangeInclusive<u64>,
+    ) -> Option<RangeInclusive<u64>> {
+        if query.from_timestamp.is_none() && query.to_timestamp.is_none() {
+            return (!bounds.is_empty()).then_some(bounds);
+        }
+
+        let first_hour = query.from_timestamp.map(hour).unwrap_or(i64::MIN);
+        let last_hour = query.to_timestamp.map(hour).unwrap_or(i64::MAX);
+        if first_hour > last_hour {
+            return None;
+        }
+
+        let (low, high) = self
+            .hours
+            .range(first_hour..=last_hour)
+            .map(|(_, range)| (*range.start(), *range.end()))
+            .reduce(|(low, high), (start, end)| (low.min(start), high.max(end)))?;
+
+        let bounds = (*bounds.start()).max(low)..=(*bounds.end()).min(high);
+        (!bounds.is_empty()).then_some(bounds)
+    }
+}
+
+fn hour(timestamp: DateTime<Utc>) -> i64 {
+    timestamp.timestamp().div_euclid(TIME_BUCKET_SECONDS)
+}
+
+fn within<'a>(postings: &'a [u64], bounds: &RangeInclusive<u64>) -> &'a [u64] {
+    let start = postings.partition_point(|s| s < bounds.start());
+    let end = postings.partition_point(|s| s <= bounds.end());
+    &postings[start..end]
+}
+
+fn insert_sorted(postings: &mut Vec<u64>, sequence_nr: u64) {
+    match postings.last() {
+        Some(&last) if last >= sequence_nr => {
+            if let Err(position) = postings.binary_search(&sequence_nr) {
+                postings.insert(position, sequence_nr);
+            }
+        }
+        _ => postings.push(sequence_nr),
+    }
+}
@@ -1,15 +1,25 @@
 mod callback;
+mod cdc;
 mod command;
+mod crypto;
 mod distributed;
 mod engine;
 mod event;
 mod indexes;
 mod journal;
 mod processor;
+mod replication;
+mod snapshot;
+mod subscription;
 
 pub use callback::*;
+pub use cdc::*;
+pub use crypto::*;
 pub use distributed::*;
 pub use engine::*;
 pub use indexes::*;
 pub use journal::*;
 pub use processor::*;
+pub use replication::*;
+pub use snapshot::*;
+pub use subscription::*;
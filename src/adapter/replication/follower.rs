@@ -0,0 +1,235 @@
+use super::journal::{ReplicaJournal, ReplicationRole};
+use super::protocol::{ReplicationMessage, receive, replication_error, send};
+use crate::domain::{ChainAnchor, EngineError, PaymentError, verify_chain_from};
+use crate::port::Journal;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::BufReader;
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Follower side of journal replication
+///
+/// Switches the journal to the follower role, connects to the leader and applies the
+/// streamed envelopes in sequence order, acknowledging each one. A lost connection is
+/// retried every `reconnect_interval`, resuming after the journal's highest sequence.
+///
+/// Every envelope must extend the replica's hash chain. A leader whose history diverged
+/// (`HistoryConflict`) or that skips sequences it no longer has, e.g. archived by
+/// compaction (`HistoryGap`), stops replication for good: see `failure`.
+///
+/// Stopping is cooperative: the replication task only gives up while waiting for the
+/// leader, never in the middle of applying an envelope, so the replica's journal is never
+/// left with a half-applied record.
+pub struct ReplicationFollower {
+    journal: Arc<ReplicaJournal>,
+    position: watch::Receiver<u64>,
+    failure: watch::Receiver<Option<PaymentError>>,
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl ReplicationFollower {
+    pub fn start(
+        leader: impl Into<String>,
+        journal: Arc<ReplicaJournal>,
+        reconnect_interval: Duration,
+    ) -> Self {
+        journal.set_role(ReplicationRole::Follower);
+
+        let leader = leader.into();
+        let (position_tx, position) = watch::channel(0);
+        let (failure_tx, failure) = watch::channel(None);
+        let (stop, mut stopped) = watch::channel(false);
+        let replica = journal.clone();
+
+        let task = tokio::spawn(async move {
+            loop {
+                match follow(&leader, &replica, &position_tx, &mut stopped).await {
+                    Err(e) if diverged(&e) => {
+                        tracing::error!("Replication from {} stopped: {}", leader, e);
+                        failure_tx.send_replace(Some(e));
+                        return;
+                    }
+                    Err(e) => tracing::warn!("Replication from {} interrupted: {}", leader, e),
+                    // Stop requested
+                    Ok(()) => return,
+                }
+                let reconnect = tokio::time::sleep(reconnect_interval);
+                if unless_stopped(&mut stopped, reconnect).await.is_none() {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            journal,
+            position,
+            failure,
+            stop,
+            task,
+        }
+    }
+
+    /// Last sequence number applied from the leader
+    pub fn position(&self) -> u64 {
+        *self.position.borrow()
+    }
+
+    /// Why replication stopped on its own, if it did
+    pub fn failure(&self) -> Option<PaymentError> {
+        self.failure.borrow().clone()
+    }
+
+    /// Wait until replication stops on its own and return why
+    pub async fn failed(&mut self) -> PaymentError {
+        match self.failure.wait_for(|failure| failure.is_some()).await {
+            Ok(failure) => failure.clone().expect("checked by wait_for"),
+            Err(_) => PaymentError::Engine(EngineError::Stopped("replication".to_string())),
+        }
+    }
+
+    /// Wait until the follower has applied `sequence_nr`
+    ///
+    /// Fails with the replication failure if replication stops before getting there.
+    pub async fn wait_for(&mut self, sequence_nr: u64) -> Result<(), PaymentError> {
+        let reached = self
+            .position
+            .wait_for(|position| *position >= sequence_nr)
+            .await
+            .is_ok();
+        match self.failure() {
+            _ if reached => Ok(()),
+            Some(failure) => Err(failure),
+            None => Err(PaymentError::Engine(EngineError::Stopped(
+                "replication".to_string(),
+            ))),
+        }
+    }
+
+    /// Stop replicating and make the journal writable, e.g. after the leader failed
+    ///
+    /// Returns the highest sequence number of the promoted journal.
+    pub async fn promote(mut self) -> Result<u64, PaymentError> {
+        self.stop.send_replace(true);
+        // Wait for the task so no replicated envelope lands after promotion
+        let _ = (&mut self.task).await;
+
+        self.journal.set_role(ReplicationRole::Leader);
+        let highest = self.journal.highest_sequence().await?.unwrap_or(0);
+        tracing::info!("Promoted to leader at sequence {}", highest);
+        Ok(highest)
+    }
+}
+
+impl Drop for ReplicationFollower {
+    /// The task finishes the envelope it is applying, if any, then stops
+    fn drop(&mut self) {
+        self.stop.send_replace(true);
+    }
+}
+
+/// Wait for `future` unless a stop is requested first (None)
+async fn unless_stopped<T>(
+    stopped: &mut watch::Receiver<bool>,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    tokio::select! {
+        biased;
+        // Also done when the follower is gone
+        _ = stopped.wait_for(|stop| *stop) => None,
+        output = future => Some(output),
+    }
+}
+
+/// One replication session, until the connection is lost (Err) or a stop is requested (Ok)
+async fn follow(
+    leader: &str,
+    journal: &ReplicaJournal,
+    position: &watch::Sender<u64>,
+    stopped: &mut watch::Receiver<bool>,
+) -> Result<(), PaymentError> {
+    let Some(stream) = unless_stopped(stopped, TcpStream::connect(leader)).await else {
+        return Ok(());
+    };
+    let stream = stream.map_err(replication_error)?;
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let (mut last_sequence, mut last_hash) = journal.chain_head().await?;
+    position.send_replace(last_sequence);
+    send(
+        &mut writer,
+        &ReplicationMessage::Hello {
+            after: last_sequence,
+        },
+    )
+    .await?;
+    tracing::info!(
+        "Replicating from {} after sequence {}",
+        leader,
+        last_sequence
+    );
+
+    loop {
+        // Stops are only taken here, between envelopes: an apply always runs to the end
+        let Some(message) = unless_stopped(stopped, receive(&mut reader)).await else {
+            return Ok(());
+        };
+        let Some(message) = message? else {
+            return Err(replication_error("leader closed the connection"));
+        };
+        let envelope = match message {
+            ReplicationMessage::Event { envelope } => envelope,
+            ReplicationMessage::Gap {
+                from_sequence,
+                to_sequence,
+            } => {
+                return Err(PaymentError::Engine(EngineError::HistoryGap {
+                    from_sequence,
+                    to_sequence,
+                }));
+            }
+            message => return Err(replication_error(format!("unexpected {:?}", message))),
+        };
+        if envelope.sequence_nr <= last_sequence {
+            continue;
+        }
+        // The leader streams every envelope after the announced position in order: a jump
+        // means it no longer has the skipped range
+        if envelope.sequence_nr != last_sequence + 1 {
+            return Err(PaymentError::Engine(EngineError::HistoryGap {
+                from_sequence: last_sequence + 1,
+                to_sequence: envelope.sequence_nr - 1,
+            }));
+        }
+        // Same checks as the journal verifier: the link to the replica's head and the
+        // envelope's own hash, so a payload altered in transit or on the leader is not applied
+        let head = ChainAnchor {
+            sequence_nr: last_sequence,
+            hash: last_hash.clone(),
+        };
+        if let Err(chain_break) = verify_chain_from(Some(&head), [&envelope]) {
+            return Err(PaymentError::Engine(EngineError::HistoryConflict(format!(
+                "leader's sequence {} does not extend the replica's head {} ({}): {:?}",
+                envelope.sequence_nr, last_sequence, last_hash, chain_break.reason
+            ))));
+        }
+
+        let (sequence_nr, hash) = (envelope.sequence_nr, envelope.hash.clone());
+        journal.apply_replicated(envelope).await?;
+        last_sequence = sequence_nr;
+        last_hash = hash;
+        position.send_replace(last_sequence);
+        send(&mut writer, &ReplicationMessage::Ack { sequence_nr }).await?;
+    }
+}
+
+/// The leader's history cannot extend the replica's: reconnecting would not help
+fn diverged(error: &PaymentError) -> bool {
+    matches!(
+        error,
+        PaymentError::Engine(EngineError::HistoryConflict(_) | EngineError::HistoryGap { .. })
+    )
+}
@@ -0,0 +1,126 @@
+use crate::domain::{
+    AppendOutcome, ChainAnchor, DeduplicationKey, EngineError, EventEnvelope, EventMetadata,
+    JournalPage, JournalQuery, PaymentError, TransactionTypeEvent,
+};
+use crate::port::Journal;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Role of a node in leader/follower replication
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplicationRole {
+    Leader,
+    Follower,
+}
+
+/// Journal wrapper enforcing the node's replication role
+///
+/// A leader accepts writes as usual. A follower rejects `append`, `import` and `truncate`
+/// with `ReadOnlyReplica`: its only writer is the replication stream. Reads are always served.
+pub struct ReplicaJournal {
+    inner: Arc<dyn Journal + Send + Sync>,
+    follower: AtomicBool,
+}
+
+impl ReplicaJournal {
+    /// Wrap a journal, starting as leader
+    pub fn new(inner: Arc<dyn Journal + Send + Sync>) -> Self {
+        Self {
+            inner,
+            follower: AtomicBool::new(false),
+        }
+    }
+
+    pub fn role(&self) -> ReplicationRole {
+        if self.follower.load(Ordering::Acquire) {
+            ReplicationRole::Follower
+        } else {
+            ReplicationRole::Leader
+        }
+    }
+
+    pub(crate) fn set_role(&self, role: ReplicationRole) {
+        self.follower
+            .store(role == ReplicationRole::Follower, Ordering::Release);
+    }
+
+    /// Apply an envelope received from the leader, keeping its sequence number
+    pub(crate) async fn apply_replicated(
+        &self,
+        envelope: EventEnvelope,
+    ) -> Result<(), PaymentError> {
+        self.inner.import(envelope).await
+    }
+
+    fn ensure_leader(&self, operation: &str) -> Result<(), PaymentError> {
+        match self.role() {
+            ReplicationRole::Leader => Ok(()),
+            ReplicationRole::Follower => Err(PaymentError::Engine(EngineError::ReadOnlyReplica(
+                format!("{} rejected, promote this node first", operation),
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl Journal for ReplicaJournal {
+    async fn append(
+        &self,
+        event: TransactionTypeEvent,
+        metadata: EventMetadata,
+    ) -> Result<AppendOutcome, PaymentError> {
+        self.ensure_leader("append")?;
+        self.inner.append(event, metadata).await
+    }
+
+    async fn replay(&self, from_sequence: Option<u64>) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.inner.replay(from_sequence).await
+    }
+
+    async fn highest_sequence(&self) -> Result<Option<u64>, PaymentError> {
+        self.inner.highest_sequence().await
+    }
+
+    async fn find_by_tx_id(&self, tx_id: u32) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.inner.find_by_tx_id(tx_id).await
+    }
+
+    async fn visit_transaction(
+        &self,
+        tx_id: u32,
+        visitor: &mut (dyn for<'e> FnMut(&'e TransactionTypeEvent) + Send),
+    ) -> Result<(), PaymentError> {
+        self.inner.visit_transaction(tx_id, visitor).await
+    }
+
+    async fn find_by_deduplication_key(
+        &self,
+        client_id: u16,
+        deduplication_key: &DeduplicationKey,
+    ) -> Result<Option<EventEnvelope>, PaymentError> {
+        self.inner
+            .find_by_deduplication_key(client_id, deduplication_key)
+            .await
+    }
+
+    async fn query(&self, query: &JournalQuery) -> Result<JournalPage, PaymentError> {
+        self.inner.query(query).await
+    }
+
+    async fn import(&self, envelope: EventEnvelope) -> Result<(), PaymentError> {
+        self.ensure_leader("import")?;
+        self.inner.import(envelope).await
+    }
+
+    async fn truncate(&self, before_sequence: u64) -> Result<usize, PaymentError> {
+        self.ensure_leader("truncate")?;
+        self.inner.truncate(before_sequence).await
+    }
+
+    async fn anchor(&self) -> Result<Option<ChainAnchor>, PaymentError> {
+        self.inner.anchor().await
+    }
+}
@@ -0,0 +1,178 @@
+use super::protocol::{ReplicationMessage, receive, replication_error, send};
+use crate::adapter::{InMemoryCheckpointStore, SubscriptionConfig, SubscriptionHub};
+use crate::domain::{EngineError, EventEnvelope, PaymentError};
+use crate::port::{CheckpointStore, Subscriber};
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::BufReader;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, watch};
+use tokio::task::JoinHandle;
+
+/// Leader side of journal replication
+///
+/// Listens on a TCP socket and streams the journal to every follower that connects, from
+/// the position announced in its `Hello`: catch-up from the journal, then live events
+/// through the subscription hub (whose publisher must be registered on the engines).
+/// Envelopes travel decoded; bind to a local or otherwise trusted interface.
+pub struct ReplicationLeader {
+    local_addr: SocketAddr,
+    acknowledged: watch::Receiver<u64>,
+    task: JoinHandle<()>,
+}
+
+impl ReplicationLeader {
+    /// Start accepting followers on `addr` (port 0 picks a free port)
+    pub async fn bind(addr: &str, hub: Arc<SubscriptionHub>) -> Result<Self, PaymentError> {
+        let listener = TcpListener::bind(addr).await.map_err(replication_error)?;
+        let local_addr = listener.local_addr().map_err(replication_error)?;
+        let (acknowledged_tx, acknowledged) = watch::channel(0);
+        let acknowledged_tx = Arc::new(acknowledged_tx);
+
+        tracing::info!("Replication leader listening on {}", local_addr);
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("Replication accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let hub = hub.clone();
+                let acknowledged_tx = acknowledged_tx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve_follower(stream, peer, hub, acknowledged_tx).await {
+                        tracing::warn!("Replication to {} ended: {}", peer, e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            acknowledged,
+            task,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Highest sequence number acknowledged by any follower
+    pub fn acknowledged(&self) -> u64 {
+        *self.acknowledged.borrow()
+    }
+
+    /// Wait until a follower has acknowledged `sequence_nr`, for at most `timeout`
+    ///
+    /// Returns whether it did.
+    pub async fn wait_for_ack(&self, sequence_nr: u64, timeout: Duration) -> bool {
+        let mut acknowledged = self.acknowledged.clone();
+        tokio::time::timeout(
+            timeout,
+            acknowledged.wait_for(|acked| *acked >= sequence_nr),
+        )
+        .await
+        .is_ok_and(|result| result.is_ok())
+    }
+}
+
+impl Drop for ReplicationLeader {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve_follower(
+    stream: TcpStream,
+    peer: SocketAddr,
+    hub: Arc<SubscriptionHub>,
+    acknowledged: Arc<watch::Sender<u64>>,
+) -> Result<(), PaymentError> {
+    let (reader, writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let after = match receive(&mut reader).await? {
+        Some(ReplicationMessage::Hello { after }) => after,
+        other => {
+            return Err(replication_error(format!(
+                "expected hello, received {:?}",
+                other
+            )));
+        }
+    };
+    tracing::info!("Follower {} connected after sequence {}", peer, after);
+
+    // The follower's own position is the starting offset of its stream
+    let name = format!("replica-{}", peer);
+    let checkpoints = Arc::new(InMemoryCheckpointStore::new());
+    checkpoints.commit(&name, after).await?;
+    let subscription = hub.subscribe(
+        Arc::new(FollowerStream {
+            name,
+            writer: Mutex::new(writer),
+        }),
+        checkpoints,
+        SubscriptionConfig::default(),
+    );
+
+    let result = loop {
+        match receive(&mut reader).await {
+            Ok(Some(ReplicationMessage::Ack { sequence_nr })) => {
+                acknowledged.send_if_modified(|acked| {
+                    let advanced = sequence_nr > *acked;
+                    *acked = (*acked).max(sequence_nr);
+                    advanced
+                });
+            }
+            Ok(Some(other)) => {
+                break Err(replication_error(format!("unexpected {:?}", other)));
+            }
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+
+    // A write failure also ends the subscription; its error is the connection's
+    subscription.stop().await?;
+    tracing::info!("Follower {} disconnected", peer);
+    result
+}
+
+/// Subscriber writing the stream to one follower's socket
+struct FollowerStream {
+    name: String,
+    writer: Mutex<OwnedWriteHalf>,
+}
+
+#[async_trait]
+impl Subscriber for FollowerStream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn handle(&self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        let message = ReplicationMessage::Event {
+            envelope: envelope.clone(),
+        };
+        send(&mut *self.writer.lock().await, &message).await
+    }
+
+    /// Tell the follower it cannot replicate past the gap, then stop streaming
+    async fn on_gap(&self, from_sequence: u64, to_sequence: u64) -> Result<(), PaymentError> {
+        let message = ReplicationMessage::Gap {
+            from_sequence,
+            to_sequence,
+        };
+        send(&mut *self.writer.lock().await, &message).await?;
+        Err(PaymentError::Engine(EngineError::HistoryGap {
+            from_sequence,
+            to_sequence,
+        }))
+    }
+}
@@ -0,0 +1,8 @@
+mod follower;
+mod journal;
+mod leader;
+mod protocol;
+
+pub use follower::*;
+pub use journal::*;
+pub use leader::*;
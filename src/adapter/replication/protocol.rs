@@ -0,0 +1,57 @@
+use crate::adapter::journal::json_error;
+use crate::domain::{EngineError, EventEnvelope, PaymentError};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Replication messages, one JSON object per line
+///
+/// The follower opens with `Hello`, the leader streams every envelope after that position
+/// in sequence order, and the follower acknowledges each one once applied. `Gap` tells the
+/// follower that the leader no longer has a range it needs (e.g. archived by compaction).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ReplicationMessage {
+    Hello {
+        after: u64,
+    },
+    Event {
+        envelope: EventEnvelope,
+    },
+    Ack {
+        sequence_nr: u64,
+    },
+    Gap {
+        from_sequence: u64,
+        to_sequence: u64,
+    },
+}
+
+pub(crate) async fn send<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &ReplicationMessage,
+) -> Result<(), PaymentError> {
+    let mut line = serde_json::to_vec(message).map_err(json_error)?;
+    line.push(b'\n');
+    writer.write_all(&line).await.map_err(replication_error)?;
+    writer.flush().await.map_err(replication_error)
+}
+
+/// Next message, or None when the peer closed the connection
+pub(crate) async fn receive<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<Option<ReplicationMessage>, PaymentError> {
+    let mut line = String::new();
+    if reader
+        .read_line(&mut line)
+        .await
+        .map_err(replication_error)?
+        == 0
+    {
+        return Ok(None);
+    }
+    serde_json::from_str(&line).map(Some).map_err(json_error)
+}
+
+pub(crate) fn replication_error(e: impl std::fmt::Display) -> PaymentError {
+    PaymentError::Engine(EngineError::PersistenceError(format!("Replication: {}", e)))
+}
@@ -0,0 +1,360 @@
+use crate::adapter::JsonCodec;
+use crate::adapter::journal::{blocking, io_error, json_error, sync_dir};
+use crate::domain::{
+    AccountState, ActiveAccountState, EngineError, FrozenAccountState, PaymentError,
+};
+use crate::port::{EnvelopeCodec, Snapshotter};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Version of the snapshot file format written by this build
+///
+/// Version 2 added sealed balances; version 1 files are plaintext and still readable.
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = 2;
+
+/// Snapshots kept per client unless configured otherwise
+const DEFAULT_RETAINED_SNAPSHOTS: usize = 1;
+
+/// Metadata of a stored snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotMetadata {
+    pub client_id: u16,
+    /// Journal sequence of the last event included in the state
+    pub sequence_nr: u64,
+    pub schema_version: u32,
+    pub taken_at: DateTime<Utc>,
+}
+
+/// Snapshot store keeping each client's snapshots in `client-<id>-<sequence>.snapshot`
+///
+/// Files are written atomically (synced tmp file + rename) on tokio's blocking pool. After
+/// each save only the newest `retain` snapshots of the client are kept; loading picks the
+/// newest one that can still be read.
+///
+/// Balances are sealed with the journal's codec, so an encrypted journal does not leak
+/// them through its snapshots and erasing a client's key makes its snapshots unreadable.
+#[derive(Clone)]
+pub struct FileSnapshotStore {
+    dir: PathBuf,
+    retain: usize,
+    codec: Arc<dyn EnvelopeCodec>,
+}
+
+impl FileSnapshotStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, PaymentError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(io_error)?;
+        Ok(Self {
+            dir,
+            retain: DEFAULT_RETAINED_SNAPSHOTS,
+            codec: Arc::new(JsonCodec),
+        })
+    }
+
+    /// Seal balances with the journal's codec (plaintext by default)
+    pub fn with_codec(mut self, codec: Arc<dyn EnvelopeCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Keep the newest `retain` snapshots per client (at least one)
+    pub fn with_retention(mut self, retain: usize) -> Self {
+        self.retain = retain.max(1);
+        self
+    }
+
+    /// Metadata of the client's stored snapshots, oldest first
+    pub fn list(&self, client_id: u16) -> Result<Vec<SnapshotMetadata>, PaymentError> {
+        self.files(client_id)?
+            .iter()
+            .map(|(_, path)| Ok(read_record(path)?.metadata()))
+            .collect()
+    }
+
+    /// Shred every snapshot of a client: overwrite each file, then delete it
+    ///
+    /// Returns the number of snapshots removed.
+    pub fn remove_client(&self, client_id: u16) -> Result<usize, PaymentError> {
+        let files = self.files(client_id)?;
+        for (_, path) in &files {
+            // Overwrite durably before unlinking so the state does not linger in the old blocks
+            let mut file = OpenOptions::new()
+                .write(true)
+                .open(path)
+                .map_err(io_error)?;
+            let len = file.metadata().map_err(io_error)?.len();
+            file.write_all(&vec![0u8; len as usize])
+                .and_then(|()| file.sync_all())
+                .map_err(io_error)?;
+            fs::remove_file(path).map_err(io_error)?;
+        }
+        sync_dir(&self.dir)?;
+        Ok(files.len())
+    }
+
+    /// Write a snapshot durably: synced tmp file, rename, synced directory
+    fn write(
+        &self,
+        client_id: u16,
+        sequence_nr: u64,
+        state: AccountState,
+    ) -> Result<(), PaymentError> {
+        let record = SnapshotRecord::new(client_id, sequence_nr, state, self.codec.as_ref())?;
+        let path = self.path(client_id, sequence_nr);
+        let tmp_path = path.with_extension("snapshot.tmp");
+
+        let content = serde_json::to_vec(&record).map_err(json_error)?;
+        let mut file = File::create(&tmp_path).map_err(io_error)?;
+        file.write_all(&content)
+            .and_then(|()| file.sync_all())
+            .map_err(io_error)?;
+        fs::rename(&tmp_path, &path).map_err(io_error)?;
+        sync_dir(&self.dir)?;
+        self.prune(client_id)
+    }
+
+    /// The newest readable snapshot not past `sequence`
+    ///
+    /// A snapshot that cannot be read or decoded (torn write, corruption) is skipped with a
+    /// warning in favour of the previous one; with none left the caller replays the journal.
+    fn read(
+        &self,
+        client_id: u16,
+        sequence: u64,
+    ) -> Result<Option<(u64, AccountState)>, PaymentError> {
+        let files = self.files(client_id)?;
+        for (_, path) in files
+            .iter()
+            .rev()
+            .filter(|(sequence_nr, _)| *sequence_nr <= sequence)
+        {
+            let record = match decode_record(path) {
+                Ok(record) => record,
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable snapshot {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            // A snapshot from a newer build is readable, just not by this one
+            check_schema(&record, path)?;
+            let sequence_nr = record.sequence_nr;
+            match record.into_state(self.codec.as_ref()) {
+                Ok(state) => return Ok(state.map(|state| (sequence_nr, state))),
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable snapshot {}: {}", path.display(), e)
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn path(&self, client_id: u16, sequence_nr: u64) -> PathBuf {
+        self.dir
+            .join(format!("client-{}-{:020}.snapshot", client_id, sequence_nr))
+    }
+
+    /// The client's snapshot files with their sequence, oldest first
+    fn files(&self, client_id: u16) -> Result<Vec<(u64, PathBuf)>, PaymentError> {
+        let prefix = format!("client-{}-", client_id);
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            let sequence_nr = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|rest| rest.strip_suffix(".snapshot"))
+                .and_then(|sequence| sequence.parse::<u64>().ok());
+            if let Some(sequence_nr) = sequence_nr {
+                files.push((sequence_nr, path));
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn prune(&self, client_id: u16) -> Result<(), PaymentError> {
+        let files = self.files(client_id)?;
+        let excess = files.len().saturating_sub(self.retain);
+        for (_, path) in &files[..excess] {
+            fs::remove_file(path).map_err(io_error)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Snapshotter for FileSnapshotStore {
+    async fn save(
+        &self,
+        client_id: u16,
+        sequence_nr: u64,
+        state: AccountState,
+    ) -> Result<(), PaymentError> {
+        let store = self.clone();
+        blocking(move || store.write(client_id, sequence_nr, state)).await
+    }
+
+    /// The newest snapshot, or None if there is none or its client was erased
+    async fn load(&self, client_id: u16) -> Result<Option<(u64, AccountState)>, PaymentError> {
+        self.load_at_or_before(client_id, u64::MAX).await
+    }
+
+    /// The newest retained snapshot not past `sequence`
+    async fn load_at_or_before(
+        &self,
+        client_id: u16,
+        sequence: u64,
+    ) -> Result<Option<(u64, AccountState)>, PaymentError> {
+        let store = self.clone();
+        blocking(move || store.read(client_id, sequence)).await
+    }
+}
+
+fn read_record(path: &Path) -> Result<SnapshotRecord, PaymentError> {
+    let record = decode_record(path)?;
+    check_schema(&record, path)?;
+    Ok(record)
+}
+
+fn decode_record(path: &Path) -> Result<SnapshotRecord, PaymentError> {
+    let content = fs::read(path).map_err(io_error)?;
+    serde_json::from_slice(&content).map_err(json_error)
+}
+
+fn check_schema(record: &SnapshotRecord, path: &Path) -> Result<(), PaymentError> {
+    if record.schema_version > SNAPSHOT_SCHEMA_VERSION {
+        return Err(PaymentError::Engine(EngineError::PersistenceError(
+            format!(
+                "Snapshot {} has unsupported schema version {}",
+                path.display(),
+                record.schema_version
+            ),
+        )));
+    }
+    Ok(())
+}
+
+/// On-disk form of a snapshot
+///
+/// The metadata stays readable; the balances are either stored as-is or sealed by the
+/// codec, bound to the client and sequence they belong to.
+#[derive(Serialize, Deserialize)]
+struct SnapshotRecord {
+    schema_version: u32,
+    client_id: u16,
+    /// Journal sequence of the last event included in the state
+    sequence_nr: u64,
+    #[serde(flatten)]
+    balances: StoredBalances,
+    taken_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredBalances {
+    Sealed { sealed: String },
+    Plain(Balances),
+}
+
+/// Account state as stored in a snapshot
+///
+/// AccountState serializes untagged, which cannot tell a frozen account from an active
+/// one, so the lock is stored explicitly.
+#[derive(Serialize, Deserialize)]
+struct Balances {
+    frozen: bool,
+    available: f64,
+    held: f64,
+    total: f64,
+    last_activity: DateTime<Utc>,
+}
+
+impl SnapshotRecord {
+    fn new(
+        client_id: u16,
+        sequence_nr: u64,
+        state: AccountState,
+        codec: &dyn EnvelopeCodec,
+    ) -> Result<Self, PaymentError> {
+        let (frozen, available, held, total, last_activity) = match state {
+            AccountState::Active(s) => (false, s.available, s.held, s.total, s.last_activity),
+            AccountState::Frozen(s) => (true, s.available, s.held, s.total, s.last_activity),
+        };
+        let balances = Balances {
+            frozen,
+            available,
+            held,
+            total,
+            last_activity,
+        };
+        let plaintext = serde_json::to_vec(&balances).map_err(json_error)?;
+        let balances =
+            match codec.seal_payload(client_id, &context(client_id, sequence_nr), &plaintext)? {
+                Some(sealed) => StoredBalances::Sealed { sealed },
+                None => StoredBalances::Plain(balances),
+            };
+        Ok(Self {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
+            client_id,
+            sequence_nr,
+            balances,
+            taken_at: Utc::now(),
+        })
+    }
+
+    fn metadata(&self) -> SnapshotMetadata {
+        SnapshotMetadata {
+            client_id: self.client_id,
+            sequence_nr: self.sequence_nr,
+            schema_version: self.schema_version,
+            taken_at: self.taken_at,
+        }
+    }
+
+    /// The stored state; None when it was sealed with a key that has since been erased
+    fn into_state(self, codec: &dyn EnvelopeCodec) -> Result<Option<AccountState>, PaymentError> {
+        let balances = match self.balances {
+            StoredBalances::Plain(balances) => balances,
+            StoredBalances::Sealed { sealed } => {
+                let context = context(self.client_id, self.sequence_nr);
+                match codec.open_payload(self.client_id, &context, &sealed)? {
+                    Some(plaintext) => serde_json::from_slice(&plaintext).map_err(json_error)?,
+                    None => return Ok(None),
+                }
+            }
+        };
+        let Balances {
+            frozen,
+            available,
+            held,
+            total,
+            last_activity,
+        } = balances;
+        Ok(Some(if frozen {
+            AccountState::Frozen(FrozenAccountState {
+                available,
+                held,
+                total,
+                last_activity,
+            })
+        } else {
+            AccountState::Active(ActiveAccountState {
+                available,
+                held,
+                total,
+                last_activity,
+            })
+        }))
+    }
+}
+
+/// Associated data binding sealed balances to their snapshot
+fn context(client_id: u16, sequence_nr: u64) -> String {
+    format!("snapshot:{}:{}", client_id, sequence_nr)
+}
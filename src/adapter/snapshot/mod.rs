@@ -0,0 +1,5 @@
+mod file;
+mod policy;
+
+pub use file::*;
+pub use policy::*;
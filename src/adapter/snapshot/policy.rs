@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+/// When a ClientActor snapshots its account
+///
+/// Triggers are checked after each applied event; the snapshot itself is written in the
+/// background so command processing never waits for it.
+#[derive(Debug, Clone)]
+pub struct SnapshotPolicy {
+    /// Snapshot once this many events were applied since the last snapshot
+    pub every_events: Option<u64>,
+    /// Snapshot on the first event applied this long after the last snapshot
+    pub every_interval: Option<Duration>,
+    /// Snapshot when the actor stops (passivation or shutdown)
+    pub on_stop: bool,
+}
+
+impl SnapshotPolicy {
+    /// Never snapshot automatically (snapshots are only saved explicitly)
+    pub fn never() -> Self {
+        Self {
+            every_events: None,
+            every_interval: None,
+            on_stop: false,
+        }
+    }
+}
+
+impl Default for SnapshotPolicy {
+    fn default() -> Self {
+        Self {
+            every_events: Some(1000),
+            every_interval: None,
+            on_stop: true,
+        }
+    }
+}
+
+/// Progress of one actor towards its next snapshot
+pub(crate) struct SnapshotTracker {
+    policy: SnapshotPolicy,
+    /// Events applied since the last snapshot (including events replayed on recovery)
+    events_since: u64,
+    last_snapshot: Instant,
+}
+
+impl SnapshotTracker {
+    pub(crate) fn new(policy: SnapshotPolicy, events_since: u64) -> Self {
+        Self {
+            policy,
+            events_since,
+            last_snapshot: Instant::now(),
+        }
+    }
+
+    /// Count an applied event; true when the policy asks for a snapshot
+    pub(crate) fn record_event(&mut self) -> bool {
+        self.events_since += 1;
+        let by_count = self
+            .policy
+            .every_events
+            .is_some_and(|every| self.events_since >= every);
+        let by_time = self
+            .policy
+            .every_interval
+            .is_some_and(|every| self.last_snapshot.elapsed() >= every);
+        by_count || by_time
+    }
+
+    /// True when the actor stops with events not covered by a snapshot
+    pub(crate) fn due_on_stop(&self) -> bool {
+        self.policy.on_stop && self.events_since > 0
+    }
+
+    pub(crate) fn taken(&mut self) {
+        self.events_since = 0;
+        self.last_snapshot = Instant::now();
+    }
+}
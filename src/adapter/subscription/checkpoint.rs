@@ -0,0 +1,108 @@
+use crate::adapter::journal::{blocking, io_error, sync_dir};
+use crate::domain::{EngineError, PaymentError};
+use crate::port::CheckpointStore;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+
+/// In-memory implementation of CheckpointStore (offsets are lost on restart)
+#[derive(Default)]
+pub struct InMemoryCheckpointStore {
+    offsets: RwLock<HashMap<String, u64>>,
+}
+
+impl InMemoryCheckpointStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for InMemoryCheckpointStore {
+    async fn load(&self, subscriber: &str) -> Result<Option<u64>, PaymentError> {
+        Ok(self.offsets.read().await.get(subscriber).copied())
+    }
+
+    async fn commit(&self, subscriber: &str, sequence_nr: u64) -> Result<(), PaymentError> {
+        self.offsets
+            .write()
+            .await
+            .insert(subscriber.to_string(), sequence_nr);
+        Ok(())
+    }
+}
+
+/// CheckpointStore keeping one `<subscriber>.checkpoint` file per subscriber in a directory
+///
+/// Offsets are written under a temporary name, synced and renamed on tokio's blocking pool,
+/// so a crash leaves either the previous or the new offset, never a torn one.
+pub struct FileCheckpointStore {
+    dir: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, PaymentError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(io_error)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, subscriber: &str) -> Result<PathBuf, PaymentError> {
+        let valid = !subscriber.is_empty()
+            && subscriber
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && !subscriber.starts_with('.');
+        if !valid {
+            return Err(PaymentError::Engine(EngineError::ConfigurationError(format!(
+                "Invalid subscriber name: {:?}",
+                subscriber
+            ))));
+        }
+        Ok(self.dir.join(format!("{}.checkpoint", subscriber)))
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for FileCheckpointStore {
+    async fn load(&self, subscriber: &str) -> Result<Option<u64>, PaymentError> {
+        let path = self.path(subscriber)?;
+        let content = {
+            let path = path.clone();
+            blocking(move || match fs::read_to_string(&path) {
+                Ok(content) => Ok(Some(content)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(io_error(e)),
+            })
+            .await?
+        };
+        let Some(content) = content else {
+            return Ok(None);
+        };
+        content.trim().parse().map(Some).map_err(|e| {
+            PaymentError::Engine(EngineError::PersistenceError(format!(
+                "Corrupt checkpoint {}: {}",
+                path.display(),
+                e
+            )))
+        })
+    }
+
+    async fn commit(&self, subscriber: &str, sequence_nr: u64) -> Result<(), PaymentError> {
+        let path = self.path(subscriber)?;
+        let dir = self.dir.clone();
+        blocking(move || {
+            let tmp_path = path.with_extension("checkpoint.tmp");
+            let mut file = File::create(&tmp_path).map_err(io_error)?;
+            file.write_all(sequence_nr.to_string().as_bytes())
+                .and_then(|()| file.sync_all())
+                .map_err(io_error)?;
+            fs::rename(&tmp_path, &path).map_err(io_error)?;
+            sync_dir(&dir)
+        })
+        .await
+    }
+}
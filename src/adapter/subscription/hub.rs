@@ -0,0 +1,353 @@
+use crate::domain::{
+    Chargebacked, Deposited, Disputed, EngineError, EventEnvelope, JournalCursor, JournalQuery,
+    PaymentError, Resolved, Withdrawn,
+};
+use crate::port::{CallbackContext, CheckpointStore, EventCallback, Journal, Subscriber};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Notify, broadcast, oneshot, watch};
+use tokio::task::JoinHandle;
+
+/// Tuning of a catch-up subscription
+#[derive(Debug, Clone, Copy)]
+pub struct SubscriptionConfig {
+    /// Events read from the journal per query while catching up
+    pub page_size: usize,
+    /// Handled events after which the offset is committed (it is also committed on stop)
+    pub checkpoint_interval: u64,
+    /// How long the events before a live event may stay unreadable in the journal before
+    /// they are reported to the subscriber as a gap
+    pub gap_timeout: Duration,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            page_size: 1000,
+            checkpoint_interval: 100,
+            gap_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Fan-out point for live events and factory of catch-up subscriptions
+///
+/// The hub's publisher is an EventCallback: registered with the engines (see
+/// `ClientRegistry::with_callback`), it broadcasts every newly persisted envelope.
+/// Subscriptions read the journal from their committed offset, then switch to the live
+/// stream. Live events may arrive out of order across clients or be dropped when a
+/// subscriber lags; both are detected as a sequence gap and repaired from the journal.
+/// Events the journal no longer has are reported through `Subscriber::on_gap`.
+pub struct SubscriptionHub {
+    journal: Arc<dyn Journal + Send + Sync>,
+    sender: broadcast::Sender<EventEnvelope>,
+    /// Signalled on every publication, for subscriptions waiting out a gap
+    published: Arc<Notify>,
+}
+
+impl SubscriptionHub {
+    /// Create a hub buffering up to `capacity` live events per subscription
+    pub fn new(journal: Arc<dyn Journal + Send + Sync>, capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            journal,
+            sender,
+            published: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Callback publishing persisted events to the hub's subscriptions
+    pub fn publisher(&self) -> Arc<dyn EventCallback> {
+        Arc::new(LiveEventPublisher {
+            sender: self.sender.clone(),
+            published: self.published.clone(),
+        })
+    }
+
+    /// Start a subscriber from its last committed offset
+    pub fn subscribe(
+        &self,
+        subscriber: Arc<dyn Subscriber>,
+        checkpoints: Arc<dyn CheckpointStore>,
+        config: SubscriptionConfig,
+    ) -> SubscriptionHandle {
+        // Subscribe to live events before catching up so nothing falls in between
+        let live = self.sender.subscribe();
+        let (stop, stopped) = oneshot::channel();
+        let (position_tx, position) = watch::channel(0);
+
+        let runner = SubscriptionRunner {
+            journal: self.journal.clone(),
+            published: self.published.clone(),
+            subscriber,
+            checkpoints,
+            config,
+            position: 0,
+            uncommitted: 0,
+            position_tx,
+        };
+        let task = tokio::spawn(runner.run(live, stopped));
+
+        SubscriptionHandle {
+            stop: Some(stop),
+            task,
+            position,
+        }
+    }
+}
+
+/// Running subscription
+pub struct SubscriptionHandle {
+    stop: Option<oneshot::Sender<()>>,
+    task: JoinHandle<Result<u64, PaymentError>>,
+    position: watch::Receiver<u64>,
+}
+
+impl SubscriptionHandle {
+    /// Last sequence number handled by the subscriber
+    pub fn position(&self) -> u64 {
+        *self.position.borrow()
+    }
+
+    /// Wait until the subscriber has handled every event up to `sequence_nr`
+    ///
+    /// Fails if the subscription stops before getting there.
+    pub async fn wait_for(&mut self, sequence_nr: u64) -> Result<(), PaymentError> {
+        self.position
+            .wait_for(|position| *position >= sequence_nr)
+            .await
+            .map(|_| ())
+            .map_err(|_| PaymentError::Engine(EngineError::Stopped("subscription".to_string())))
+    }
+
+    /// Stop the subscription, commit its offset and return it
+    pub async fn stop(mut self) -> Result<u64, PaymentError> {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        self.task.await.map_err(|e| {
+            PaymentError::Engine(EngineError::Stopped(format!(
+                "subscription task failed: {}",
+                e
+            )))
+        })?
+    }
+}
+
+struct SubscriptionRunner {
+    journal: Arc<dyn Journal + Send + Sync>,
+    published: Arc<Notify>,
+    subscriber: Arc<dyn Subscriber>,
+    checkpoints: Arc<dyn CheckpointStore>,
+    config: SubscriptionConfig,
+    /// Last handled sequence number
+    position: u64,
+    /// Events handled since the last commit
+    uncommitted: u64,
+    position_tx: watch::Sender<u64>,
+}
+
+impl SubscriptionRunner {
+    async fn run(
+        mut self,
+        mut live: broadcast::Receiver<EventEnvelope>,
+        mut stopped: oneshot::Receiver<()>,
+    ) -> Result<u64, PaymentError> {
+        let name = self.subscriber.name().to_string();
+        self.position = self.checkpoints.load(&name).await?.unwrap_or(0);
+        self.position_tx.send_replace(self.position);
+        tracing::info!(
+            "Subscription {} starting after sequence {}",
+            name,
+            self.position
+        );
+
+        let result = self.follow(&mut live, &mut stopped).await;
+        // Whatever happened, keep the progress made so far
+        self.commit().await?;
+        result.map(|_| self.position)
+    }
+
+    async fn follow(
+        &mut self,
+        live: &mut broadcast::Receiver<EventEnvelope>,
+        stopped: &mut oneshot::Receiver<()>,
+    ) -> Result<(), PaymentError> {
+        self.catch_up().await?;
+
+        loop {
+            tokio::select! {
+                _ = &mut *stopped => return Ok(()),
+                received = live.recv() => match received {
+                    Ok(envelope) if envelope.sequence_nr <= self.position => {}
+                    Ok(envelope) if envelope.sequence_nr == self.position + 1 => {
+                        self.deliver(&envelope).await?;
+                    }
+                    // Gap (out-of-order publication): read up to this event from the journal
+                    Ok(envelope) => self.catch_up_to(&envelope).await?,
+                    // Dropped events: read everything persisted so far from the journal
+                    Err(broadcast::error::RecvError::Lagged(_)) => self.catch_up().await?,
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+            }
+
+            self.commit_if_due().await?;
+        }
+    }
+
+    /// Deliver every journal event after the current position
+    async fn catch_up(&mut self) -> Result<(), PaymentError> {
+        loop {
+            let page = self
+                .journal
+                .query(&JournalQuery {
+                    after: Some(JournalCursor::after(self.position)),
+                    limit: self.config.page_size.max(1),
+                    ..Default::default()
+                })
+                .await?;
+
+            // Reads stop at the journal's watermark, so a sequence missing below a readable
+            // one is gone for good
+            for envelope in &page.envelopes {
+                if envelope.sequence_nr > self.position + 1 {
+                    self.skip_gap(envelope.sequence_nr - 1).await?;
+                }
+                self.deliver(envelope).await?;
+            }
+            self.commit_if_due().await?;
+            if page.next_cursor.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Catch up until the live `envelope` is delivered
+    ///
+    /// Events published ahead of lower sequence numbers still being appended on other
+    /// shards only become readable once those appends complete, so the journal is read
+    /// again after each publication. Whatever is still missing after `gap_timeout` is
+    /// reported as a gap and the live envelope delivered after it.
+    async fn catch_up_to(&mut self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        let deadline = tokio::time::Instant::now() + self.config.gap_timeout;
+        let published = self.published.clone();
+        loop {
+            // Registered before reading, so a publication during the read is not missed
+            let next_publication = published.notified();
+            self.catch_up().await?;
+            if self.position >= envelope.sequence_nr {
+                return Ok(());
+            }
+            if tokio::time::timeout_at(deadline, next_publication)
+                .await
+                .is_err()
+            {
+                if self.position + 1 < envelope.sequence_nr {
+                    self.skip_gap(envelope.sequence_nr - 1).await?;
+                }
+                return self.deliver(envelope).await;
+            }
+        }
+    }
+
+    /// Report the events after the position up to `to_sequence` as missing and move past them
+    async fn skip_gap(&mut self, to_sequence: u64) -> Result<(), PaymentError> {
+        let from_sequence = self.position + 1;
+        tracing::warn!(
+            "Subscription {} is missing sequences {} to {}",
+            self.subscriber.name(),
+            from_sequence,
+            to_sequence
+        );
+        self.subscriber.on_gap(from_sequence, to_sequence).await?;
+        self.position = to_sequence;
+        self.uncommitted += 1;
+        self.position_tx.send_replace(self.position);
+        Ok(())
+    }
+
+    async fn deliver(&mut self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        self.subscriber.handle(envelope).await?;
+        self.position = envelope.sequence_nr;
+        self.uncommitted += 1;
+        self.position_tx.send_replace(self.position);
+        Ok(())
+    }
+
+    async fn commit_if_due(&mut self) -> Result<(), PaymentError> {
+        if self.uncommitted >= self.config.checkpoint_interval {
+            self.commit().await?;
+        }
+        Ok(())
+    }
+
+    async fn commit(&mut self) -> Result<(), PaymentError> {
+        if self.uncommitted == 0 {
+            return Ok(());
+        }
+        self.checkpoints
+            .commit(self.subscriber.name(), self.position)
+            .await?;
+        self.uncommitted = 0;
+        Ok(())
+    }
+}
+
+/// EventCallback broadcasting persisted envelopes to a SubscriptionHub
+struct LiveEventPublisher {
+    sender: broadcast::Sender<EventEnvelope>,
+    published: Arc<Notify>,
+}
+
+impl LiveEventPublisher {
+    fn publish(&self, ctx: &CallbackContext) -> Result<(), PaymentError> {
+        // No receiver just means no subscription is running
+        let _ = self.sender.send(ctx.envelope.clone());
+        self.published.notify_waiters();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventCallback for LiveEventPublisher {
+    async fn on_deposited(
+        &self,
+        _event: &Deposited,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx)
+    }
+
+    async fn on_withdrawn(
+        &self,
+        _event: &Withdrawn,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx)
+    }
+
+    async fn on_disputed(
+        &self,
+        _event: &Disputed,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx)
+    }
+
+    async fn on_resolved(
+        &self,
+        _event: &Resolved,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx)
+    }
+
+    async fn on_chargebacked(
+        &self,
+        _event: &Chargebacked,
+        ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.publish(ctx)
+    }
+}
@@ -0,0 +1,5 @@
+mod checkpoint;
+mod hub;
+
+pub use checkpoint::*;
+pub use hub::*;
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::domain::{DeduplicationKey, EventEnvelope, TransactionTypeEvent};
+
+/// `previous_hash` of the very first envelope in a journal
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Last envelope moved out of a journal's hot log
+///
+/// Persisted by durable journals when they archive or drop their oldest events, so the
+/// chain can still be continued and verified once its beginning is no longer at hand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainAnchor {
+    pub sequence_nr: u64,
+    pub hash: String,
+}
+
+/// Fields covered by the envelope hash, in a fixed order
+#[derive(Serialize)]
+struct HashedContent<'a> {
+    sequence_nr: u64,
+    event: &'a TransactionTypeEvent,
+    timestamp: &'a chrono::DateTime<chrono::Utc>,
+    client_id: u16,
+    tx_id: u32,
+    deduplication_key: &'a DeduplicationKey,
+    previous_hash: &'a str,
+}
+
+impl EventEnvelope {
+    /// Compute the SHA-256 of this envelope's content chained with its `previous_hash`
+    ///
+    /// The stored `hash` field is not part of the input, so this can be used both to
+    /// seal a new envelope and to check an existing one.
+    pub fn content_hash(&self) -> String {
+        let content = HashedContent {
+            sequence_nr: self.sequence_nr,
+            event: &self.event,
+            timestamp: &self.timestamp,
+            client_id: self.client_id,
+            tx_id: self.tx_id,
+            deduplication_key: &self.deduplication_key,
+            previous_hash: &self.previous_hash,
+        };
+
+        // Serializing a plain struct of owned/borrowed values cannot fail
+        let bytes = serde_json::to_vec(&content).expect("envelope content is serializable");
+        Sha256::digest(&bytes)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Why a link of the hash chain is broken
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum ChainBreakReason {
+    /// The envelope's content no longer matches its stored hash (edited in place)
+    ContentMismatch { stored: String, computed: String },
+    /// The envelope does not point at the hash of the envelope before it (inserted/removed)
+    BrokenLink { expected: String, actual: String },
+    /// The chain does not start where the journal's history says it should (events removed)
+    MissingHistory { expected_sequence: u64 },
+}
+
+/// First broken link found while walking a journal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainBreak {
+    pub sequence_nr: u64,
+    #[serde(flatten)]
+    pub reason: ChainBreakReason,
+}
+
+/// Walk envelopes in sequence order and report the first broken link
+///
+/// The chain must start at envelope #1, linked to GENESIS_HASH. Use `verify_chain_from`
+/// for a journal whose head was compacted away.
+/// Returns the number of verified envelopes.
+pub fn verify_chain<'a>(
+    envelopes: impl IntoIterator<Item = &'a EventEnvelope>,
+) -> Result<u64, ChainBreak> {
+    verify_chain_from(None, envelopes)
+}
+
+/// Walk envelopes that continue the chain after `anchor`
+///
+/// The first envelope must either be #1 or directly follow the anchor and link to its hash,
+/// so leading events cannot be dropped unnoticed.
+/// Redacted tombstones are only exempt from the content check when they are `shredded`,
+/// i.e. a codec produced them because the record's key was erased; any other tombstone
+/// must still match the hash of the event it replaced, which it never does.
+/// Returns the number of verified envelopes.
+pub fn verify_chain_from<'a>(
+    anchor: Option<&ChainAnchor>,
+    envelopes: impl IntoIterator<Item = &'a EventEnvelope>,
+) -> Result<u64, ChainBreak> {
+    let mut previous: Option<&EventEnvelope> = None;
+    let mut verified = 0;
+
+    for envelope in envelopes {
+        let expected_link = match (previous, anchor) {
+            (Some(previous), _) => previous.hash.as_str(),
+            (None, _) if envelope.sequence_nr == 1 => GENESIS_HASH,
+            (None, Some(anchor)) if envelope.sequence_nr == anchor.sequence_nr + 1 => {
+                anchor.hash.as_str()
+            }
+            (None, _) => {
+                return Err(ChainBreak {
+                    sequence_nr: envelope.sequence_nr,
+                    reason: ChainBreakReason::MissingHistory {
+                        expected_sequence: anchor.map_or(1, |a| a.sequence_nr + 1),
+                    },
+                });
+            }
+        };
+
+        if envelope.previous_hash != expected_link {
+            return Err(ChainBreak {
+                sequence_nr: envelope.sequence_nr,
+                reason: ChainBreakReason::BrokenLink {
+                    expected: expected_link.to_string(),
+                    actual: envelope.previous_hash.clone(),
+                },
+            });
+        }
+
+        // A shredded envelope's content is gone for good; its stored hash still anchors the link
+        let shredded = matches!(&envelope.event, TransactionTypeEvent::Redacted(r) if r.shredded);
+        let computed = envelope.content_hash();
+        if !shredded && envelope.hash != computed {
+            return Err(ChainBreak {
+                sequence_nr: envelope.sequence_nr,
+                reason: ChainBreakReason::ContentMismatch {
+                    stored: envelope.hash.clone(),
+                    computed,
+                },
+            });
+        }
+
+        previous = Some(envelope);
+        verified += 1;
+    }
+
+    Ok(verified)
+}
@@ -1,6 +1,7 @@
 use crate::{domain::TransactionTypeEvent, port::EffectFn};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DeduplicationKey(String);
@@ -13,6 +14,21 @@ impl DeduplicationKey {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Compact 128-bit fingerprint of the key within a client's scope (truncated SHA-256)
+    ///
+    /// Keys often embed long identifiers (file paths, request ids); indexes store this
+    /// fixed-size fingerprint instead of the full string. The same key issued by two
+    /// clients yields two fingerprints, as the clients' commands are distinct.
+    pub fn fingerprint(&self, client_id: u16) -> u128 {
+        let digest = Sha256::new()
+            .chain_update(client_id.to_be_bytes())
+            .chain_update(self.0.as_bytes())
+            .finalize();
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        u128::from_be_bytes(bytes)
+    }
 }
 
 /// Envelope wrapping an event with ordering metadata
@@ -30,6 +46,12 @@ pub struct EventEnvelope {
     pub tx_id: u32,
     /// Deduplication key from the command source (Kafka offset, API request ID, etc.)
     pub deduplication_key: DeduplicationKey,
+    /// Hash of the previous envelope in the journal (GENESIS_HASH for the first one)
+    #[serde(default)]
+    pub previous_hash: String,
+    /// Hash of this envelope's content chained with `previous_hash`
+    #[serde(default)]
+    pub hash: String,
 }
 
 /// Metadata about the command for deduplication
@@ -36,8 +36,155 @@ pub enum EngineError {
     SideEffectError(String),
     #[error("No events produced by command handler")]
     NoEvents,
-    #[error("State transition failed - event could not be applied")]
-    StateTransitionFailed,
+    #[error("State transition failed - event {sequence_nr} could not be applied")]
+    StateTransitionFailed { sequence_nr: u64 },
+    #[error("Persistence error: {0}")]
+    PersistenceError(String),
+    #[error("Configuration error: {0}")]
+    ConfigurationError(String),
+    #[error("Duplicate command outside the deduplication window: {0}")]
+    DuplicateOutsideWindow(String),
+    #[error("Deduplication key reused with a different payload: {0}")]
+    IdempotencyConflict(String),
+    #[error("Journal is a read-only follower: {0}")]
+    ReadOnlyReplica(String),
+    #[error("Journal history conflict: {0}")]
+    HistoryConflict(String),
+    #[error("Journal history is missing sequences {from_sequence} to {to_sequence}")]
+    HistoryGap {
+        from_sequence: u64,
+        to_sequence: u64,
+    },
+    #[error(
+        "Event ordering violation for client {client_id}: last sequence {last_sequence}, got {sequence_nr}"
+    )]
+    SequenceViolation {
+        client_id: u16,
+        last_sequence: u64,
+        sequence_nr: u64,
+    },
+    #[error("Client {client_id} actor failed: {reason}")]
+    ActorFailed { client_id: u16, reason: String },
+    #[error("Client {0} is quarantined after repeated actor failures")]
+    ClientQuarantined(u16),
+    #[error("Call to client {client_id} timed out after {timeout_ms} ms")]
+    CallTimeout { client_id: u16, timeout_ms: u64 },
+    #[error("Client {client_id} actor unavailable: {reason}")]
+    ActorUnavailable { client_id: u16, reason: String },
+    #[error("Failed to spawn actor: {0}")]
+    SpawnFailed(String),
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+    #[error("Stopped: {0}")]
+    Stopped(String),
+}
+
+/// Broad class of an error, deciding how callers react to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// The command was refused by business rules or as a duplicate; retrying cannot help
+    BusinessRejection,
+    /// The infrastructure failed in a way that may succeed if retried
+    InfrastructureTransient,
+    /// The infrastructure failed and retrying cannot help
+    InfrastructureFatal,
+}
+
+impl ErrorCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::BusinessRejection => "business_rejection",
+            ErrorCategory::InfrastructureTransient => "infrastructure_transient",
+            ErrorCategory::InfrastructureFatal => "infrastructure_fatal",
+        }
+    }
+}
+
+impl Display for ErrorCategory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TransactionError {
+    /// Stable identifier of the error, safe to match on in logs and reports
+    pub fn code(&self) -> &'static str {
+        match self {
+            TransactionError::InsufficientFunds => "insufficient_funds",
+            TransactionError::AccountLocked => "account_locked",
+            TransactionError::TransactionNotFound => "transaction_not_found",
+            TransactionError::DuplicateTransaction => "duplicate_transaction",
+            TransactionError::InvalidTransactionType => "invalid_transaction_type",
+            TransactionError::InvalidAmount => "invalid_amount",
+            TransactionError::GeneralError(_) => "transaction_error",
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        ErrorCategory::BusinessRejection
+    }
+}
+
+impl EngineError {
+    /// Stable identifier of the error, safe to match on in logs and reports
+    pub fn code(&self) -> &'static str {
+        match self {
+            EngineError::LoadingResourcesError(_) => "resource_not_loaded",
+            EngineError::ValidationError(_) => "validation_failed",
+            EngineError::EmittingEventError(_) => "event_not_emitted",
+            EngineError::SideEffectError(_) => "side_effect_failed",
+            EngineError::NoEvents => "no_events",
+            EngineError::StateTransitionFailed { .. } => "state_transition_failed",
+            EngineError::PersistenceError(_) => "persistence_failed",
+            EngineError::ConfigurationError(_) => "invalid_configuration",
+            EngineError::DuplicateOutsideWindow(_) => "duplicate_outside_window",
+            EngineError::IdempotencyConflict(_) => "idempotency_conflict",
+            EngineError::ReadOnlyReplica(_) => "read_only_replica",
+            EngineError::HistoryConflict(_) => "history_conflict",
+            EngineError::HistoryGap { .. } => "history_gap",
+            EngineError::SequenceViolation { .. } => "sequence_violation",
+            EngineError::ActorFailed { .. } => "actor_failed",
+            EngineError::ClientQuarantined(_) => "client_quarantined",
+            EngineError::CallTimeout { .. } => "call_timeout",
+            EngineError::ActorUnavailable { .. } => "actor_unavailable",
+            EngineError::SpawnFailed(_) => "spawn_failed",
+            EngineError::Unavailable(_) => "unavailable",
+            EngineError::Stopped(_) => "stopped",
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            // The command itself was refused
+            EngineError::LoadingResourcesError(_)
+            | EngineError::ValidationError(_)
+            | EngineError::DuplicateOutsideWindow(_)
+            | EngineError::IdempotencyConflict(_) => ErrorCategory::BusinessRejection,
+            // The actor machinery did not answer, or drops its state to rebuild it. A sequence
+            // violation was persisted before it was detected, so its retry is answered as a
+            // duplicate from the rebuilt state
+            EngineError::CallTimeout { .. }
+            | EngineError::ActorUnavailable { .. }
+            | EngineError::SequenceViolation { .. }
+            | EngineError::Unavailable(_) => ErrorCategory::InfrastructureTransient,
+            // An actor that cannot recover its client from the journal fails the same way
+            // on every attempt, as does a misconfigured component
+            EngineError::EmittingEventError(_)
+            | EngineError::SideEffectError(_)
+            | EngineError::NoEvents
+            | EngineError::StateTransitionFailed { .. }
+            | EngineError::PersistenceError(_)
+            | EngineError::ConfigurationError(_)
+            | EngineError::ReadOnlyReplica(_)
+            | EngineError::HistoryConflict(_)
+            | EngineError::HistoryGap { .. }
+            | EngineError::ActorFailed { .. }
+            | EngineError::ClientQuarantined(_)
+            | EngineError::SpawnFailed(_)
+            | EngineError::Stopped(_) => ErrorCategory::InfrastructureFatal,
+        }
+    }
 }
 
 #[derive(Error, Debug, Clone, Serialize, Deserialize)]
@@ -54,3 +201,25 @@ impl Display for PaymentError {
         }
     }
 }
+
+impl PaymentError {
+    /// Stable identifier of the error, safe to match on in logs and reports
+    pub fn code(&self) -> &'static str {
+        match self {
+            PaymentError::Engine(e) => e.code(),
+            PaymentError::Transaction(e) => e.code(),
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            PaymentError::Engine(e) => e.category(),
+            PaymentError::Transaction(e) => e.category(),
+        }
+    }
+
+    /// Whether the same command may succeed if sent again
+    pub fn is_retryable(&self) -> bool {
+        self.category() == ErrorCategory::InfrastructureTransient
+    }
+}
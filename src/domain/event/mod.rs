@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -8,6 +9,90 @@ pub enum TransactionTypeEvent {
     Disputed(Disputed),
     Resolved(Resolved),
     Chargebacked(Chargebacked),
+    Redacted(Redacted),
+}
+
+/// Discriminant of a TransactionTypeEvent, used to filter journal queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Deposited,
+    Withdrawn,
+    Disputed,
+    Resolved,
+    Chargebacked,
+    Redacted,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 6] = [
+        EventKind::Deposited,
+        EventKind::Withdrawn,
+        EventKind::Disputed,
+        EventKind::Resolved,
+        EventKind::Chargebacked,
+        EventKind::Redacted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Deposited => "deposited",
+            EventKind::Withdrawn => "withdrawn",
+            EventKind::Disputed => "disputed",
+            EventKind::Resolved => "resolved",
+            EventKind::Chargebacked => "chargebacked",
+            EventKind::Redacted => "redacted",
+        }
+    }
+}
+
+impl std::str::FromStr for EventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EventKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown event kind: {}", s))
+    }
+}
+
+impl TransactionTypeEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            TransactionTypeEvent::Deposited(_) => EventKind::Deposited,
+            TransactionTypeEvent::Withdrawn(_) => EventKind::Withdrawn,
+            TransactionTypeEvent::Disputed(_) => EventKind::Disputed,
+            TransactionTypeEvent::Resolved(_) => EventKind::Resolved,
+            TransactionTypeEvent::Chargebacked(_) => EventKind::Chargebacked,
+            TransactionTypeEvent::Redacted(_) => EventKind::Redacted,
+        }
+    }
+
+    /// Amount carried by the event (None for redacted events)
+    pub fn amount(&self) -> Option<f64> {
+        match self {
+            TransactionTypeEvent::Deposited(e) => Some(e.amount),
+            TransactionTypeEvent::Withdrawn(e) => Some(e.amount),
+            TransactionTypeEvent::Disputed(e) => Some(e.amount),
+            TransactionTypeEvent::Resolved(e) => Some(e.amount),
+            TransactionTypeEvent::Chargebacked(e) => Some(e.amount),
+            TransactionTypeEvent::Redacted(_) => None,
+        }
+    }
+
+    /// 64-bit fingerprint of the event's payload (truncated SHA-256)
+    ///
+    /// Stored next to each deduplication key, so a key reused for different content can
+    /// be told apart from a genuine redelivery.
+    pub fn payload_fingerprint(&self) -> u64 {
+        // Serializing a plain enum of owned values cannot fail
+        let bytes = serde_json::to_vec(self).expect("event is serializable");
+        let digest = Sha256::digest(&bytes);
+        let mut fingerprint = [0u8; 8];
+        fingerprint.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(fingerprint)
+    }
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chargebacked {
@@ -43,3 +128,17 @@ pub struct Withdrawn {
     pub tx_id: u32,
     pub amount: f64,
 }
+
+/// Tombstone replayed in place of an event whose client data key was destroyed
+///
+/// Only the plaintext envelope metadata survives, so it carries no amount and
+/// applies to the account state as a no-op.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Redacted {
+    pub client_id: u16,
+    pub tx_id: u32,
+    /// Set when the tombstone stands for a record sealed with an erased key; codecs never
+    /// take it from a stored record, so chain verification only trusts tombstones with it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shredded: bool,
+}
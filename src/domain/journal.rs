@@ -1,7 +1,7 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::domain::DeduplicationKey;
+use crate::domain::{DeduplicationKey, EventEnvelope, EventKind};
 
 /// Metadata needed to construct an event envelope
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,3 +11,194 @@ pub struct EventMetadata {
     pub deduplication_key: DeduplicationKey,
     pub timestamp: DateTime<Utc>,
 }
+
+/// Retention rules applied when compacting the hot journal
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    /// How long original transactions must stay queryable through `find_by_tx_id`
+    /// so that disputes, resolves and chargebacks can still reference them.
+    pub dispute_horizon: TimeDelta,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            dispute_horizon: TimeDelta::days(180),
+        }
+    }
+}
+
+/// Outcome of a compaction run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactionReport {
+    /// Sealed segments moved to the archive
+    pub archived_segments: usize,
+    /// Events dropped from the hot log
+    pub truncated_events: usize,
+    /// Lowest sequence number still held in the hot log (None if empty)
+    pub hot_from_sequence: Option<u64>,
+}
+
+/// How long deduplication keys are remembered by an in-memory journal
+#[derive(Debug, Clone, Copy, Default)]
+pub enum DeduplicationWindow {
+    /// Remember every key forever
+    #[default]
+    Unbounded,
+    /// Remember the most recent `n` keys
+    Count(usize),
+    /// Remember keys whose event timestamp is younger than the given age
+    Time(TimeDelta),
+}
+
+/// Position in a journal query's results; pass it back to fetch the next page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JournalCursor(u64);
+
+impl JournalCursor {
+    /// Cursor resuming after the given sequence number
+    pub fn after(sequence_nr: u64) -> Self {
+        Self(sequence_nr)
+    }
+
+    pub fn sequence_nr(&self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for JournalCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::str::FromStr for JournalCursor {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+/// Filter for `Journal::query`; every criterion left empty matches all events
+///
+/// Bounds are inclusive. Results are returned in sequence order, at most `limit` at a time.
+#[derive(Debug, Clone)]
+pub struct JournalQuery {
+    pub client_id: Option<u16>,
+    /// Event kinds to include (all kinds when empty)
+    pub kinds: Vec<EventKind>,
+    pub from_timestamp: Option<DateTime<Utc>>,
+    pub to_timestamp: Option<DateTime<Utc>>,
+    pub from_sequence: Option<u64>,
+    pub to_sequence: Option<u64>,
+    /// Resume after the last event of a previous page
+    pub after: Option<JournalCursor>,
+    pub limit: usize,
+}
+
+impl Default for JournalQuery {
+    fn default() -> Self {
+        Self {
+            client_id: None,
+            kinds: Vec::new(),
+            from_timestamp: None,
+            to_timestamp: None,
+            from_sequence: None,
+            to_sequence: None,
+            after: None,
+            limit: 1000,
+        }
+    }
+}
+
+impl JournalQuery {
+    /// Lowest sequence number the query can match, taking the cursor into account
+    pub fn lowest_sequence(&self) -> u64 {
+        let from = self.from_sequence.unwrap_or(0);
+        match self.after {
+            Some(cursor) => from.max(cursor.sequence_nr() + 1),
+            None => from,
+        }
+    }
+
+    pub fn matches(&self, envelope: &EventEnvelope) -> bool {
+        self.matches_fields(
+            envelope.sequence_nr,
+            envelope.client_id,
+            envelope.event.kind(),
+            envelope.timestamp,
+        )
+    }
+
+    /// Same as `matches`, for journals that do not store whole envelopes
+    pub fn matches_fields(
+        &self,
+        sequence_nr: u64,
+        client_id: u16,
+        kind: EventKind,
+        timestamp: DateTime<Utc>,
+    ) -> bool {
+        sequence_nr >= self.lowest_sequence()
+            && self.to_sequence.is_none_or(|to| sequence_nr <= to)
+            && self.client_id.is_none_or(|id| client_id == id)
+            && (self.kinds.is_empty() || self.kinds.contains(&kind))
+            && self.from_timestamp.is_none_or(|from| timestamp >= from)
+            && self.to_timestamp.is_none_or(|to| timestamp <= to)
+    }
+}
+
+/// Result of appending an event to the journal
+#[derive(Debug, Clone)]
+pub enum AppendOutcome {
+    /// The event was written under a new sequence number
+    Appended(EventEnvelope),
+    /// The deduplication key was already journaled: the original envelope, nothing written
+    Duplicate(EventEnvelope),
+}
+
+impl AppendOutcome {
+    pub fn envelope(&self) -> &EventEnvelope {
+        match self {
+            AppendOutcome::Appended(envelope) | AppendOutcome::Duplicate(envelope) => envelope,
+        }
+    }
+
+    pub fn into_envelope(self) -> EventEnvelope {
+        match self {
+            AppendOutcome::Appended(envelope) | AppendOutcome::Duplicate(envelope) => envelope,
+        }
+    }
+
+    pub fn is_duplicate(&self) -> bool {
+        matches!(self, AppendOutcome::Duplicate(_))
+    }
+}
+
+/// One page of query results
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JournalPage {
+    pub envelopes: Vec<EventEnvelope>,
+    /// Cursor for the next page; None when the results are exhausted
+    pub next_cursor: Option<JournalCursor>,
+}
+
+impl JournalPage {
+    /// Build a page from matches in sequence order, of which at most `limit` are kept
+    pub fn from_matches(mut envelopes: Vec<EventEnvelope>, limit: usize) -> Self {
+        let next_cursor = if envelopes.len() > limit {
+            envelopes.truncate(limit);
+            envelopes
+                .last()
+                .map(|e| JournalCursor::after(e.sequence_nr))
+        } else {
+            None
+        };
+
+        Self {
+            envelopes,
+            next_cursor,
+        }
+    }
+}
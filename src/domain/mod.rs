@@ -1,3 +1,4 @@
+mod chain;
 mod command;
 mod engine;
 mod error;
@@ -6,6 +7,7 @@ mod journal;
 mod orchestrator;
 mod state;
 
+pub use chain::*;
 pub use command::*;
 pub use engine::*;
 pub use error::*;
@@ -2,3 +2,13 @@
 pub enum OrchestratorMode {
     Csv { file_path: String },
 }
+
+/// How client commands are executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionBackend {
+    /// One `ClientActor` per client, spawned on demand (`ClientRegistry`)
+    #[default]
+    ActorPerClient,
+    /// A fixed pool of workers, each owning the clients hashed to it (`WorkerPool`)
+    WorkerPool { workers: usize },
+}
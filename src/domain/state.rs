@@ -8,6 +8,18 @@ pub enum AccountState {
     Frozen(FrozenAccountState),
 }
 
+impl AccountState {
+    /// A new client's account: active, zero balances, no activity yet
+    pub fn empty() -> Self {
+        AccountState::Active(ActiveAccountState {
+            available: 0.0,
+            held: 0.0,
+            total: 0.0,
+            last_activity: DateTime::<Utc>::MIN_UTC,
+        })
+    }
+}
+
 /// Active account state - only balances (O(1) memory)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActiveAccountState {
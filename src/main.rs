@@ -1,8 +1,26 @@
 use clap::{Parser, Subcommand};
 use payment::{
-    domain::OrchestratorMode,
-    service::{mock::generator, orchestrator::Orchestrator},
+    adapter::{
+        CdcSink, CdcSinkConfig, CdcTail, ClientKeyCodec, ClientKeyStore, EncryptedCodec,
+        FileJournal, FileJournalConfig, FileSnapshotStore, InMemoryJournal, JsonCodec, Keyring,
+        ReplicaJournal, ReplicationFollower, ReplicationLeader, SnapshotPolicy, SubscriptionHub,
+    },
+    domain::{
+        EngineError, EventKind, ExecutionBackend, JournalCursor, JournalQuery, OrchestratorMode,
+        PaymentError, RetentionPolicy,
+    },
+    port::{EnvelopeCodec, Journal, Snapshotter},
+    service::{
+        AsOf, AuditReport, ExportFilter, account_state_as_of, audit_journal, erase_client,
+        export_journal, import_journal, mock::generator, orchestrator::Orchestrator,
+        rebuild_dispute_index, snapshot_floor, verify_journal_chain,
+    },
 };
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(name = "payment", version, about = "A payment processing CLI", long_about = None)]
@@ -13,6 +31,71 @@ struct Cli {
     /// Path to the transactions CSV file to process
     #[arg(value_name = "FILE")]
     file: Option<String>,
+
+    /// Persist events to a file journal in this directory instead of keeping them in memory
+    #[arg(long, value_name = "DIR")]
+    journal_dir: Option<PathBuf>,
+
+    /// Recover client accounts from snapshots in this directory plus the journal
+    #[arg(long, value_name = "DIR", requires = "journal_dir")]
+    snapshot_dir: Option<PathBuf>,
+
+    /// Snapshot a client after this many events (0 disables count-based snapshots)
+    #[arg(
+        long,
+        default_value = "1000",
+        value_name = "EVENTS",
+        requires = "snapshot_dir"
+    )]
+    snapshot_every: u64,
+
+    /// Snapshot a client on its first event this many seconds after its last snapshot
+    #[arg(long, value_name = "SECONDS", requires = "snapshot_dir")]
+    snapshot_interval: Option<u64>,
+
+    /// Snapshots kept per client
+    #[arg(
+        long,
+        default_value = "1",
+        value_name = "COUNT",
+        requires = "snapshot_dir"
+    )]
+    snapshot_retain: usize,
+
+    /// Passivate client actors after this many seconds without messages
+    #[arg(long, value_name = "SECONDS")]
+    idle_timeout: Option<u64>,
+
+    /// Execute commands with a pool of this many workers owning clients by hash, instead of
+    /// one actor per client
+    #[arg(long, value_name = "COUNT", conflicts_with = "idle_timeout")]
+    workers: Option<usize>,
+
+    /// Publish every persisted event to a rotating JSON Lines sink in this directory
+    #[arg(long, value_name = "DIR")]
+    cdc_dir: Option<PathBuf>,
+
+    /// Stream persisted events to replication followers connecting to this address
+    #[arg(long, value_name = "ADDR", requires = "journal_dir")]
+    replicate_listen: Option<String>,
+
+    /// How long to wait, once the file is processed, for a follower to acknowledge every event
+    #[arg(long, default_value = "10", value_name = "SECONDS")]
+    replication_timeout: u64,
+
+    /// Once the file is processed, audit the journal against the dispute index the run
+    /// maintained (report on stderr, exit code 1 if inconsistent)
+    #[arg(long)]
+    audit: bool,
+
+    /// Encrypt journal payloads at rest with the keys in this keyfile
+    #[arg(long, global = true, value_name = "FILE")]
+    keyfile: Option<PathBuf>,
+
+    /// Encrypt journal payloads with per-client data keys stored in this directory
+    /// (enables crypto-shredding with `journal erase-client`)
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "keyfile")]
+    client_keys: Option<PathBuf>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -27,6 +110,188 @@ enum Commands {
         #[arg(short, long, default_value = "10", value_name = "COUNT")]
         count: usize,
     },
+    /// Maintain a file journal
+    Journal {
+        /// Journal directory
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+
+        #[command(subcommand)]
+        command: JournalCommands,
+    },
+    /// Audit a file journal's consistency
+    ///
+    /// The dispute index only lives in a processing run's memory: it is rebuilt from the
+    /// journal, as a restarting node would, and audited too.
+    ///
+    /// Exit codes: 0 consistent, 1 inconsistencies found, 2 journal could not be audited
+    Verify {
+        /// Journal directory
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+    },
+    /// Manage encryption keys
+    Keys {
+        #[command(subcommand)]
+        command: KeyCommands,
+    },
+    /// Read a change data capture sink
+    Cdc {
+        /// Sink directory
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+
+        #[command(subcommand)]
+        command: CdcCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CdcCommands {
+    /// Print the sink's events as JSON Lines
+    Tail {
+        /// Start after this sequence number
+        #[arg(long, default_value = "0", value_name = "SEQUENCE")]
+        after: u64,
+
+        /// Keep waiting for new events, across segment rotations
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum KeyCommands {
+    /// Append a new active key to the keyfile (created if missing); older keys stay readable
+    Rotate,
+}
+
+#[derive(Subcommand, Debug)]
+enum JournalCommands {
+    /// Archive sealed segments that are covered by snapshots and past the dispute horizon
+    ///
+    /// Events are covered up to the oldest retained snapshot of every client with events
+    /// in the hot log; a client without a snapshot keeps all of its events.
+    Compact {
+        /// Snapshot directory of the journal's clients
+        #[arg(long, value_name = "DIR")]
+        snapshot_dir: PathBuf,
+
+        /// Archive no further than this sequence, even if the snapshots would allow it
+        #[arg(long, value_name = "SEQUENCE")]
+        snapshot_floor: Option<u64>,
+
+        /// Days during which transactions must remain disputable
+        #[arg(long, default_value = "180", value_name = "DAYS")]
+        dispute_horizon_days: i64,
+    },
+    /// Re-import an archived segment into the hot log
+    Restore {
+        /// Archived segment (segment-<first>-<last>.jsonl.gz)
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+    },
+    /// Walk the hash chain and report the first broken link (exits with 1 if tampered)
+    VerifyChain,
+    /// Dump the journal, or a range of it, as JSON Lines (decoded, even for encrypted journals)
+    Export {
+        /// Output file (stdout if omitted)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// First sequence number to export
+        #[arg(long, value_name = "SEQUENCE")]
+        from_sequence: Option<u64>,
+
+        /// Last sequence number to export
+        #[arg(long, value_name = "SEQUENCE")]
+        to_sequence: Option<u64>,
+
+        /// Only export events of this client
+        #[arg(long, value_name = "CLIENT")]
+        client: Option<u16>,
+    },
+    /// Load a JSON Lines export, keeping sequence numbers, deduplication keys and timestamps
+    Import {
+        /// Export file to load
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+    },
+    /// Find events by client, kind, time range and sequence range, one page at a time
+    Query {
+        /// Only events of this client
+        #[arg(long, value_name = "CLIENT")]
+        client: Option<u16>,
+
+        /// Only events of these kinds (deposited, withdrawn, disputed, resolved, chargebacked, redacted)
+        #[arg(long = "kind", value_name = "KIND")]
+        kinds: Vec<EventKind>,
+
+        /// Only events recorded at or after this RFC 3339 time
+        #[arg(long, value_name = "TIME")]
+        since: Option<chrono::DateTime<chrono::Utc>>,
+
+        /// Only events recorded at or before this RFC 3339 time
+        #[arg(long, value_name = "TIME")]
+        until: Option<chrono::DateTime<chrono::Utc>>,
+
+        /// First sequence number
+        #[arg(long, value_name = "SEQUENCE")]
+        from_sequence: Option<u64>,
+
+        /// Last sequence number
+        #[arg(long, value_name = "SEQUENCE")]
+        to_sequence: Option<u64>,
+
+        /// Cursor returned by the previous page
+        #[arg(long, value_name = "CURSOR")]
+        after: Option<JournalCursor>,
+
+        /// Maximum number of events per page
+        #[arg(long, default_value = "100", value_name = "COUNT")]
+        limit: usize,
+    },
+    /// Rebuild a client's account as it was at a sequence number or time, with the balance after each event
+    StateAt {
+        /// Client to inspect
+        #[arg(value_name = "CLIENT")]
+        client: u16,
+
+        /// Include every event up to this sequence number
+        #[arg(
+            long,
+            value_name = "SEQUENCE",
+            required_unless_present = "at",
+            conflicts_with = "at"
+        )]
+        sequence: Option<u64>,
+
+        /// Include every event recorded at or before this RFC 3339 time
+        #[arg(long, value_name = "TIME")]
+        at: Option<chrono::DateTime<chrono::Utc>>,
+
+        /// Start from the nearest earlier snapshot in this directory
+        #[arg(long, value_name = "DIR")]
+        snapshot_dir: Option<PathBuf>,
+    },
+    /// Crypto-shred a client: destroy its data key so its events replay as tombstones
+    EraseClient {
+        /// Client to erase
+        #[arg(value_name = "CLIENT")]
+        client: u16,
+
+        /// Snapshot directory whose snapshots of the client are shredded too
+        #[arg(long, value_name = "DIR")]
+        snapshot_dir: Option<PathBuf>,
+    },
+    /// Replicate a leader's journal into this one until interrupted (Ctrl-C), then promote it
+    ///
+    /// The journal rejects writes while following.
+    Follow {
+        /// Leader address (its --replicate-listen)
+        #[arg(value_name = "ADDR")]
+        leader: String,
+    },
 }
 
 #[tokio::main]
@@ -37,16 +302,304 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Some(Commands::Generate { output, count }) => {
             generator(&output, count)?;
         }
+        Some(Commands::Journal { dir, command }) => {
+            let client_keys = args
+                .client_keys
+                .map(ClientKeyStore::load)
+                .transpose()?
+                .map(Arc::new);
+            let codec = codec(args.keyfile.as_deref(), client_keys.clone())?;
+            let journal =
+                FileJournal::open_with_codec(dir, FileJournalConfig::default(), codec.clone())
+                    .await?;
+
+            match command {
+                JournalCommands::Compact {
+                    snapshot_dir,
+                    snapshot_floor: floor_limit,
+                    dispute_horizon_days,
+                } => {
+                    let snapshots = FileSnapshotStore::new(snapshot_dir)?.with_codec(codec);
+                    let floor = snapshot_floor(&journal, &snapshots).await?;
+                    let floor = floor_limit.map_or(floor, |limit| floor.min(limit));
+                    let policy = RetentionPolicy {
+                        dispute_horizon: chrono::TimeDelta::days(dispute_horizon_days),
+                    };
+                    let report = journal.compact(&policy, floor).await?;
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                }
+                JournalCommands::Restore { archive } => {
+                    let restored = journal.restore_archive(&archive).await?;
+                    println!("Restored {} events from {}", restored, archive.display());
+                }
+                JournalCommands::VerifyChain => {
+                    let report = verify_journal_chain(&journal).await?;
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                    if !report.is_intact() {
+                        std::process::exit(1);
+                    }
+                }
+                JournalCommands::Export {
+                    output,
+                    from_sequence,
+                    to_sequence,
+                    client,
+                } => {
+                    let filter = ExportFilter {
+                        from_sequence,
+                        to_sequence,
+                        client_id: client,
+                    };
+                    let report = match output {
+                        Some(output) => {
+                            let file = BufWriter::new(File::create(output)?);
+                            export_journal(&journal, &filter, file).await?
+                        }
+                        None => export_journal(&journal, &filter, std::io::stdout().lock()).await?,
+                    };
+                    eprintln!("{}", serde_json::to_string_pretty(&report)?);
+                }
+                JournalCommands::Import { input } => {
+                    let reader = BufReader::new(File::open(input)?);
+                    let report = import_journal(&journal, reader).await?;
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                }
+                JournalCommands::Query {
+                    client,
+                    kinds,
+                    since,
+                    until,
+                    from_sequence,
+                    to_sequence,
+                    after,
+                    limit,
+                } => {
+                    let query = JournalQuery {
+                        client_id: client,
+                        kinds,
+                        from_timestamp: since,
+                        to_timestamp: until,
+                        from_sequence,
+                        to_sequence,
+                        after,
+                        limit,
+                    };
+                    let page = journal.query(&query).await?;
+                    println!("{}", serde_json::to_string_pretty(&page)?);
+                }
+                JournalCommands::StateAt {
+                    client,
+                    sequence,
+                    at,
+                    snapshot_dir,
+                } => {
+                    let as_of = match (sequence, at) {
+                        (Some(sequence), _) => AsOf::Sequence(sequence),
+                        (None, Some(at)) => AsOf::Timestamp(at),
+                        (None, None) => return Err("Please provide --sequence or --at".into()),
+                    };
+                    let snapshots = snapshot_dir
+                        .map(|dir| FileSnapshotStore::new(dir).map(|store| store.with_codec(codec)))
+                        .transpose()?;
+                    let report = account_state_as_of(
+                        &journal,
+                        client,
+                        as_of,
+                        snapshots.as_ref().map(|store| store as &dyn Snapshotter),
+                    )
+                    .await?;
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                }
+                JournalCommands::EraseClient {
+                    client,
+                    snapshot_dir,
+                } => {
+                    let keys = client_keys.ok_or("Please provide --client-keys")?;
+                    let snapshots = snapshot_dir
+                        .map(|dir| FileSnapshotStore::new(dir).map(|store| store.with_codec(codec)))
+                        .transpose()?;
+                    let report = erase_client(&journal, &keys, snapshots.as_ref(), client).await?;
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                }
+                JournalCommands::Follow { leader } => {
+                    let replica = Arc::new(ReplicaJournal::new(Arc::new(journal)));
+                    let mut follower = ReplicationFollower::start(
+                        leader,
+                        replica.clone(),
+                        Duration::from_millis(500),
+                    );
+                    tokio::select! {
+                        result = tokio::signal::ctrl_c() => result?,
+                        failure = follower.failed() => {
+                            eprintln!("Replication stopped: {}", failure);
+                            std::process::exit(1);
+                        }
+                    }
+                    let highest = follower.promote().await?;
+                    println!("Promoted to leader at sequence {}", highest);
+                }
+            }
+        }
+        Some(Commands::Verify { dir }) => {
+            let client_keys = args
+                .client_keys
+                .map(ClientKeyStore::load)
+                .transpose()?
+                .map(Arc::new);
+            let codec = codec(args.keyfile.as_deref(), client_keys)?;
+
+            match verify(dir, codec).await {
+                Ok(report) => {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                    if !report.is_consistent() {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Audit failed: {}", e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Some(Commands::Keys { command }) => {
+            let keyfile = args.keyfile.ok_or("Please provide --keyfile")?;
+            match command {
+                KeyCommands::Rotate => {
+                    let key_id = Keyring::rotate(&keyfile)?;
+                    println!("Active key is now {}", key_id);
+                }
+            }
+        }
+        Some(Commands::Cdc { dir, command }) => match command {
+            CdcCommands::Tail { after, follow } => {
+                let mut tail = CdcTail::open(dir, after);
+                let mut stdout = std::io::stdout().lock();
+                loop {
+                    let envelope = match tail.try_next()? {
+                        Some(envelope) => envelope,
+                        None if follow => tail.next().await?,
+                        None => break,
+                    };
+                    serde_json::to_writer(&mut stdout, &envelope)?;
+                    writeln!(stdout)?;
+                    stdout.flush()?;
+                }
+            }
+        },
         None => {
             let file = args
                 .file
                 .ok_or("Please provide a CSV file path or use 'test' command")?;
+            let mode = OrchestratorMode::Csv { file_path: file };
 
-            let orchestrator = Orchestrator::new(OrchestratorMode::Csv { file_path: file }).await;
-            let final_states = orchestrator.process().await?;
+            let client_keys = args
+                .client_keys
+                .map(ClientKeyStore::load)
+                .transpose()?
+                .map(Arc::new);
+            let codec = codec(args.keyfile.as_deref(), client_keys)?;
+            let journal: Arc<dyn Journal + Send + Sync> = match args.journal_dir {
+                Some(dir) => Arc::new(
+                    FileJournal::open_with_codec(dir, FileJournalConfig::default(), codec.clone())
+                        .await?,
+                ),
+                None => Arc::new(InMemoryJournal::new()),
+            };
+
+            let mut orchestrator = Orchestrator::with_journal(journal.clone(), mode).await?;
+            if let Some(dir) = args.snapshot_dir {
+                let store = FileSnapshotStore::new(dir)?
+                    .with_retention(args.snapshot_retain)
+                    .with_codec(codec);
+                orchestrator = orchestrator
+                    .with_snapshots(Arc::new(store))
+                    .with_snapshot_policy(SnapshotPolicy {
+                        every_events: (args.snapshot_every > 0).then_some(args.snapshot_every),
+                        every_interval: args.snapshot_interval.map(Duration::from_secs),
+                        on_stop: true,
+                    });
+            }
+            if let Some(seconds) = args.idle_timeout {
+                orchestrator = orchestrator.with_idle_timeout(Duration::from_secs(seconds));
+            }
+            if let Some(workers) = args.workers {
+                orchestrator = orchestrator.with_backend(ExecutionBackend::WorkerPool { workers });
+            }
+            let leader = match args.replicate_listen {
+                Some(addr) => {
+                    let hub = Arc::new(SubscriptionHub::new(journal.clone(), 1024));
+                    orchestrator = orchestrator.with_callback(hub.publisher());
+                    Some(ReplicationLeader::bind(&addr, hub).await?)
+                }
+                None => None,
+            };
+            let cdc = match args.cdc_dir {
+                Some(dir) => {
+                    let sink = Arc::new(CdcSink::open(dir, CdcSinkConfig::default())?);
+                    // Events persisted while the sink was not running
+                    sink.catch_up(journal.as_ref()).await?;
+                    orchestrator = orchestrator.with_callback(sink.clone());
+                    Some(sink)
+                }
+                None => None,
+            };
+
+            let dispute_index = orchestrator.dispute_index();
+            let (final_states, report) = orchestrator.process_with_report().await?;
+            // Rejected lines are expected in the input; infrastructure failures are not
+            if report.infrastructure_failures() > 0 {
+                eprintln!("{}", serde_json::to_string_pretty(&report)?);
+            }
+            if let Some(sink) = cdc {
+                sink.catch_up(journal.as_ref()).await?;
+            }
+            if let Some(leader) = leader {
+                let highest = journal.highest_sequence().await?.unwrap_or(0);
+                let timeout = Duration::from_secs(args.replication_timeout);
+                if !leader.wait_for_ack(highest, timeout).await {
+                    eprintln!(
+                        "Warning: no follower acknowledged sequence {} (last acknowledged: {})",
+                        highest,
+                        leader.acknowledged()
+                    );
+                }
+            }
             Orchestrator::output_csv(&final_states)?;
+            if args.audit {
+                let audit = audit_journal(journal.as_ref(), Some(dispute_index.as_ref())).await?;
+                eprintln!("{}", serde_json::to_string_pretty(&audit)?);
+                if !audit.is_consistent() {
+                    std::process::exit(1);
+                }
+            }
         }
     }
 
     Ok(())
 }
+
+/// Open a file journal and audit its events, and the dispute index a node would rebuild from it
+async fn verify(dir: PathBuf, codec: Arc<dyn EnvelopeCodec>) -> Result<AuditReport, PaymentError> {
+    if !dir.is_dir() {
+        return Err(PaymentError::Engine(EngineError::PersistenceError(
+            format!("Journal directory {} does not exist", dir.display()),
+        )));
+    }
+
+    let journal: Arc<dyn Journal + Send + Sync> =
+        Arc::new(FileJournal::open_with_codec(dir, FileJournalConfig::default(), codec).await?);
+    let dispute_index = rebuild_dispute_index(journal.clone()).await?;
+    audit_journal(journal.as_ref(), Some(dispute_index.as_ref())).await
+}
+
+/// Pick the record codec from the encryption options
+fn codec(
+    keyfile: Option<&Path>,
+    client_keys: Option<Arc<ClientKeyStore>>,
+) -> Result<Arc<dyn EnvelopeCodec>, Box<dyn std::error::Error>> {
+    Ok(match (keyfile, client_keys) {
+        (_, Some(client_keys)) => Arc::new(ClientKeyCodec::new(client_keys)),
+        (Some(keyfile), None) => Arc::new(EncryptedCodec::new(Arc::new(Keyring::load(keyfile)?))),
+        (None, None) => Arc::new(JsonCodec),
+    })
+}
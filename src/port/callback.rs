@@ -14,7 +14,8 @@ pub struct CallbackContext {
 /// Infrastructure callbacks invoked after events are persisted
 ///
 /// Implementations can maintain indices, caches, or other infrastructure concerns.
-/// These are called by the Engine after successful event persistence.
+/// These are called by the Engine after successful event persistence. A failing callback
+/// is logged and does not fail the command: its event is journaled either way.
 ///
 /// Callbacks receive the CallbackContext which includes:
 /// - journal: for updating infrastructure (indices, caches)
@@ -0,0 +1,42 @@
+use crate::domain::{EngineError, EventEnvelope, PaymentError};
+
+/// EnvelopeCodec turns envelopes into the records a durable journal writes to storage
+///
+/// Journals and archives call this for every record they write or read, which makes it
+/// the place to layer storage concerns such as encryption at rest without touching the
+/// journal itself.
+pub trait EnvelopeCodec: Send + Sync {
+    /// Encode an envelope into a single-line record
+    fn encode(&self, envelope: &EventEnvelope) -> Result<String, PaymentError>;
+
+    /// Decode a record previously produced by `encode`
+    fn decode(&self, record: &str) -> Result<EventEnvelope, PaymentError>;
+
+    /// Seal a client payload stored next to the journal, such as an account snapshot
+    ///
+    /// `context` names the payload and is bound to it like a record's associated data.
+    /// Returns None when the codec keeps payloads in plaintext.
+    fn seal_payload(
+        &self,
+        _client_id: u16,
+        _context: &str,
+        _payload: &[u8],
+    ) -> Result<Option<String>, PaymentError> {
+        Ok(None)
+    }
+
+    /// Open a payload sealed by `seal_payload`; None once the client's key was erased
+    fn open_payload(
+        &self,
+        client_id: u16,
+        _context: &str,
+        _sealed: &str,
+    ) -> Result<Option<Vec<u8>>, PaymentError> {
+        Err(PaymentError::Engine(EngineError::PersistenceError(
+            format!(
+                "Payload of client {} is encrypted, a key is required",
+                client_id
+            ),
+        )))
+    }
+}
@@ -1,5 +1,5 @@
 use crate::domain::{
-    AccountState, CommandMetadata, Directive, EventEnvelope, PaymentError, TransactionTypeCommand,
+    AccountState, AppendOutcome, CommandMetadata, Directive, PaymentError, TransactionTypeCommand,
 };
 use async_trait::async_trait;
 
@@ -23,13 +23,15 @@ pub trait Engine {
     /// 4. Apply events to state (functional)
     /// 5. Execute effects
     ///
-    /// Returns (EventEnvelope, NewState) - caller is responsible for updating state
+    /// Steps 4 and 5 are skipped when the journal reports a duplicate.
+    ///
+    /// Returns (AppendOutcome, NewState) - caller is responsible for updating state
     async fn process_command(
         &self,
         command: TransactionTypeCommand,
         metadata: CommandMetadata,
         context: &Self::Context,
-    ) -> Result<(EventEnvelope, AccountState), PaymentError>;
+    ) -> Result<(AppendOutcome, AccountState), PaymentError>;
 
     /// Get the command processor/loader
     fn processor(&self) -> &dyn Processor;
@@ -0,0 +1,24 @@
+use crate::domain::{AccountState, CommandMetadata, PaymentError, TransactionTypeCommand};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// ClientExecutor routes commands to whatever owns each client's account
+///
+/// Implementations must process the commands of one client one at a time, in the order
+/// they were submitted.
+#[async_trait]
+pub trait ClientExecutor: Send + Sync {
+    /// Process a command for a client and wait for the outcome
+    async fn process_command(
+        &self,
+        client_id: u16,
+        command: TransactionTypeCommand,
+        metadata: CommandMetadata,
+    ) -> Result<(), PaymentError>;
+
+    /// Current state of every client the executor holds
+    async fn get_all_states(&self) -> Result<HashMap<u16, AccountState>, PaymentError>;
+
+    /// Stop processing and release every client
+    async fn shutdown_all(&self);
+}
@@ -15,4 +15,7 @@ pub trait DisputeIndex: Send + Sync {
 
     /// Unmark a transaction as disputed (called by infrastructure callbacks)
     async fn unmark_disputed(&self, tx_id: u32) -> Result<(), PaymentError>;
+
+    /// Every transaction currently disputed, in tx_id order (for audits, may scan the index)
+    async fn list_disputed(&self) -> Result<Vec<u32>, PaymentError>;
 }
@@ -1,4 +1,7 @@
-use crate::domain::{EventEnvelope, EventMetadata, PaymentError, TransactionTypeEvent};
+use crate::domain::{
+    AppendOutcome, ChainAnchor, DeduplicationKey, EngineError, EventEnvelope, EventMetadata,
+    GENESIS_HASH, JournalPage, JournalQuery, PaymentError, TransactionTypeEvent,
+};
 use async_trait::async_trait;
 
 /// Journal is responsible for appending and replaying events to the log.
@@ -12,13 +15,14 @@ pub trait Journal {
     /// - Adding the provided metadata
     /// - Wrapping the event
     ///
-    /// Returns the complete EventEnvelope with assigned sequence number.
-    /// Idempotent via deduplication_key - returns existing envelope if duplicate.
+    /// Returns `Appended` with the complete EventEnvelope and its assigned sequence number.
+    /// Idempotent via deduplication_key - returns `Duplicate` with the existing envelope,
+    /// without writing anything, if the key was already journaled.
     async fn append(
         &self,
         event: TransactionTypeEvent,
         metadata: EventMetadata,
-    ) -> Result<EventEnvelope, PaymentError>;
+    ) -> Result<AppendOutcome, PaymentError>;
 
     /// Replay events starting from a sequence number
     /// Returns events in order
@@ -29,4 +33,118 @@ pub trait Journal {
 
     /// Find events for a specific transaction ID
     async fn find_by_tx_id(&self, tx_id: u32) -> Result<Vec<EventEnvelope>, PaymentError>;
+
+    /// Find the event a client's command was journaled as, by its deduplication key
+    ///
+    /// Lets callers recognise a redelivery before validating it against a state that already
+    /// includes its effects. Fails like `append` when the key is known but its event is no
+    /// longer available (outside the deduplication window, or compacted away).
+    ///
+    /// The default implementation scans the client's events with `query`; implementations
+    /// with a deduplication index should override it.
+    async fn find_by_deduplication_key(
+        &self,
+        client_id: u16,
+        deduplication_key: &DeduplicationKey,
+    ) -> Result<Option<EventEnvelope>, PaymentError> {
+        let mut query = JournalQuery {
+            client_id: Some(client_id),
+            ..Default::default()
+        };
+        loop {
+            let page = self.query(&query).await?;
+            if let Some(envelope) = page
+                .envelopes
+                .into_iter()
+                .find(|envelope| envelope.deduplication_key == *deduplication_key)
+            {
+                return Ok(Some(envelope));
+            }
+            match page.next_cursor {
+                Some(cursor) => query.after = Some(cursor),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Visit the domain events of a transaction in log order, reading them in place
+    ///
+    /// The default implementation visits the envelopes returned by `find_by_tx_id`;
+    /// implementations that can lend their stored events should override it.
+    async fn visit_transaction(
+        &self,
+        tx_id: u32,
+        visitor: &mut (dyn for<'e> FnMut(&'e TransactionTypeEvent) + Send),
+    ) -> Result<(), PaymentError> {
+        for envelope in self.find_by_tx_id(tx_id).await? {
+            visitor(&envelope.event);
+        }
+        Ok(())
+    }
+
+    /// Find events matching a filter, one page at a time
+    ///
+    /// The default implementation scans `replay`; implementations with secondary
+    /// indexes should override it.
+    async fn query(&self, query: &JournalQuery) -> Result<JournalPage, PaymentError> {
+        let mut matches = Vec::new();
+        for envelope in self.replay(Some(query.lowest_sequence())).await? {
+            if query.matches(&envelope) {
+                matches.push(envelope);
+                // One extra match tells whether there is a next page
+                if matches.len() > query.limit {
+                    break;
+                }
+            }
+        }
+        Ok(JournalPage::from_matches(matches, query.limit))
+    }
+
+    /// Import an already sequenced envelope (archives, backups, migrations)
+    ///
+    /// Unlike `append`, the sequence number, timestamp and deduplication key are kept as-is.
+    /// Importing an envelope whose sequence number is already present is a no-op.
+    async fn import(&self, envelope: EventEnvelope) -> Result<(), PaymentError>;
+
+    /// Drop every event with a sequence number lower than `before_sequence` from the hot log
+    ///
+    /// Deduplication keys are kept so that redeliveries of truncated commands are still detected.
+    /// Returns the number of events removed.
+    async fn truncate(&self, before_sequence: u64) -> Result<usize, PaymentError>;
+
+    /// Last event dropped from the front of the log by truncation or compaction
+    ///
+    /// The hot log continues the hash chain from it. None if the log was never truncated.
+    async fn anchor(&self) -> Result<Option<ChainAnchor>, PaymentError> {
+        Ok(None)
+    }
+
+    /// Sequence and hash of the last envelope, which the next one must link to
+    ///
+    /// 0 and GENESIS_HASH for an empty journal that was never truncated.
+    async fn chain_head(&self) -> Result<(u64, String), PaymentError> {
+        let Some(head) = self.highest_sequence().await? else {
+            return Ok(match self.anchor().await? {
+                Some(anchor) => (anchor.sequence_nr, anchor.hash),
+                None => (0, GENESIS_HASH.to_string()),
+            });
+        };
+        let page = self
+            .query(&JournalQuery {
+                from_sequence: Some(head),
+                to_sequence: Some(head),
+                limit: 1,
+                ..Default::default()
+            })
+            .await?;
+        if let Some(envelope) = page.envelopes.into_iter().next() {
+            return Ok((head, envelope.hash));
+        }
+        match self.anchor().await? {
+            Some(anchor) if anchor.sequence_nr == head => Ok((head, anchor.hash)),
+            _ => Err(PaymentError::Engine(EngineError::PersistenceError(
+                format!("Journal head {} is not readable", head),
+            ))),
+        }
+    }
 }
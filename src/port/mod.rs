@@ -1,17 +1,25 @@
 mod callback;
+mod codec;
 mod command;
 mod engine;
 mod event;
+mod executor;
 mod indexes;
 mod journal;
 mod lookup;
+mod projection;
 mod snapshot;
+mod subscription;
 
 pub use callback::*;
+pub use codec::*;
 pub use command::*;
 pub use engine::*;
 pub use event::*;
+pub use executor::*;
 pub use indexes::*;
 pub use journal::*;
 pub use lookup::*;
+pub use projection::*;
 pub use snapshot::*;
+pub use subscription::*;
@@ -0,0 +1,19 @@
+use crate::domain::{EventEnvelope, PaymentError};
+use async_trait::async_trait;
+
+/// Read model folded from the journal's event stream
+///
+/// Projections are driven by a subscription and answer queries from their own state, so
+/// query concerns stay out of the actors and the journal. Like any subscriber they receive
+/// events at least once, in sequence order.
+#[async_trait]
+pub trait Projection: Send + Sync {
+    /// Stable name, used as the checkpoint key
+    fn name(&self) -> &str;
+
+    /// Fold one event into the read model
+    async fn apply(&self, envelope: &EventEnvelope) -> Result<(), PaymentError>;
+
+    /// Drop all state, before the projection is rebuilt from the start of the journal
+    async fn reset(&self) -> Result<(), PaymentError>;
+}
@@ -1,13 +1,33 @@
 use crate::domain::{AccountState, PaymentError};
 use async_trait::async_trait;
 
-/// Snapshotter is responsible for saving and loading the state at a given point in time
-/// of the account.
+/// Snapshotter is responsible for saving and loading the state of a client's account at a
+/// given point in time (the journal sequence of the last event included in the state).
 #[async_trait]
-pub trait Snapshotter {
-    /// Save the current state to the snapshot
-    async fn save(&self, sequence: u64, state: AccountState) -> Result<(), PaymentError>;
+pub trait Snapshotter: Send + Sync {
+    /// Save the current state of a client's account
+    async fn save(
+        &self,
+        client_id: u16,
+        sequence: u64,
+        state: AccountState,
+    ) -> Result<(), PaymentError>;
 
-    /// Load the state from the snapshot
-    async fn load(&self) -> Result<Option<(u64, AccountState)>, PaymentError>;
+    /// Load the latest snapshot of a client's account
+    async fn load(&self, client_id: u16) -> Result<Option<(u64, AccountState)>, PaymentError>;
+
+    /// Load the latest snapshot taken at or before `sequence`
+    ///
+    /// The default only considers the latest snapshot; stores that retain older ones
+    /// override it.
+    async fn load_at_or_before(
+        &self,
+        client_id: u16,
+        sequence: u64,
+    ) -> Result<Option<(u64, AccountState)>, PaymentError> {
+        Ok(self
+            .load(client_id)
+            .await?
+            .filter(|(sequence_nr, _)| *sequence_nr <= sequence))
+    }
 }
@@ -0,0 +1,38 @@
+use crate::domain::{EngineError, EventEnvelope, PaymentError};
+use async_trait::async_trait;
+
+/// Named consumer of the journal's event stream (projections, integrations, ...)
+///
+/// Events are delivered in sequence order, starting after the subscriber's last committed
+/// offset. Delivery is at-least-once: after a restart, events handled since the last
+/// checkpoint are delivered again, so handlers must be idempotent.
+#[async_trait]
+pub trait Subscriber: Send + Sync {
+    /// Stable name, used as the checkpoint key
+    fn name(&self) -> &str;
+
+    /// Handle one event; an error stops the subscription at the previous event
+    async fn handle(&self, envelope: &EventEnvelope) -> Result<(), PaymentError>;
+
+    /// The journal no longer has the events `from_sequence..=to_sequence` (e.g. they were
+    /// truncated or archived before the subscriber read them)
+    ///
+    /// By default the subscription stops with `HistoryGap`. A subscriber that can recover
+    /// otherwise (e.g. from a snapshot) returns Ok to resume after the gap.
+    async fn on_gap(&self, from_sequence: u64, to_sequence: u64) -> Result<(), PaymentError> {
+        Err(PaymentError::Engine(EngineError::HistoryGap {
+            from_sequence,
+            to_sequence,
+        }))
+    }
+}
+
+/// Durable storage of subscriber offsets
+#[async_trait]
+pub trait CheckpointStore: Send + Sync {
+    /// Last sequence number committed by a subscriber
+    async fn load(&self, subscriber: &str) -> Result<Option<u64>, PaymentError>;
+
+    /// Record that a subscriber has handled every event up to `sequence_nr`
+    async fn commit(&self, subscriber: &str, sequence_nr: u64) -> Result<(), PaymentError>;
+}
@@ -0,0 +1,84 @@
+use crate::adapter::{ClientKeyStore, FileJournal, FileSnapshotStore};
+use crate::domain::PaymentError;
+use crate::port::Journal;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Outcome of an EraseClient admin operation
+#[derive(Debug, Clone, Serialize)]
+pub struct ErasureReport {
+    pub client_id: u16,
+    /// Whether a data key existed and was destroyed
+    pub key_destroyed: bool,
+    /// Cached events replaced by Redacted tombstones
+    pub redacted_events: usize,
+    /// Snapshots of the client that were shredded
+    pub removed_snapshots: usize,
+}
+
+/// EraseClient: crypto-shred every event of a client
+///
+/// Destroys the client's data key, making its encrypted payloads permanently unreadable,
+/// then redacts the journal's in-memory copies. Nothing is removed from the log: sequence
+/// numbers, chain links and the envelope metadata stay in place, so other clients and the
+/// journal's integrity checks are unaffected. Replaying the client afterwards yields
+/// tombstones, which rebuild an empty account. The client's snapshots hold its balance,
+/// so they are shredded as well.
+///
+/// The client's actor must not be running, otherwise it keeps its balance in memory.
+pub async fn erase_client(
+    journal: &FileJournal,
+    keys: &ClientKeyStore,
+    snapshots: Option<&FileSnapshotStore>,
+    client_id: u16,
+) -> Result<ErasureReport, PaymentError> {
+    let key_destroyed = keys.erase(client_id)?;
+    let redacted_events = journal.redact_client(client_id).await;
+    let removed_snapshots = match snapshots {
+        Some(store) => store.remove_client(client_id)?,
+        None => 0,
+    };
+
+    tracing::info!(
+        "Erased client {}: key destroyed={}, {} events redacted, {} snapshots removed",
+        client_id,
+        key_destroyed,
+        redacted_events,
+        removed_snapshots
+    );
+
+    Ok(ErasureReport {
+        client_id,
+        key_destroyed,
+        redacted_events,
+        removed_snapshots,
+    })
+}
+
+/// Highest sequence compaction may archive up to without losing events a recovery reads
+///
+/// A client recovers from its oldest retained snapshot at worst, since loading falls back
+/// to it when newer ones are unreadable; a client without a snapshot replays from its
+/// first event in the hot log. The floor is the lowest of these across clients.
+pub async fn snapshot_floor(
+    journal: &(dyn Journal + Send + Sync),
+    snapshots: &FileSnapshotStore,
+) -> Result<u64, PaymentError> {
+    let mut first_sequences = HashMap::new();
+    for envelope in journal.replay(None).await? {
+        first_sequences
+            .entry(envelope.client_id)
+            .or_insert(envelope.sequence_nr);
+    }
+
+    let mut floor = u64::MAX;
+    for (client_id, first_sequence) in first_sequences {
+        let client_floor = match snapshots.list(client_id)?.first() {
+            Some(oldest) => oldest.sequence_nr,
+            None => first_sequence - 1,
+        };
+        floor = floor.min(client_floor);
+    }
+    // An empty hot log has nothing to archive
+    Ok(if floor == u64::MAX { 0 } else { floor })
+}
@@ -0,0 +1,279 @@
+use crate::adapter::{DisputeIndexCallback, InMemoryDisputeIndex};
+use crate::domain::{
+    AccountState, ChainBreak, EventEnvelope, EventKind, PaymentError, TransactionTypeEvent,
+    verify_chain_from,
+};
+use crate::port::{CallbackContext, DisputeIndex, EventCallback, EventHandler, Journal};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+/// Violations listed in a report; further ones are only counted
+const MAX_LISTED_VIOLATIONS: usize = 1000;
+
+/// Tolerance for the `total == available + held` invariant (balances are f64)
+const BALANCE_EPSILON: f64 = 1e-6;
+
+/// One inconsistency found while auditing a journal
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum AuditViolation {
+    /// Sequence numbers are not contiguous
+    SequenceGap { after: u64, next: u64 },
+    /// The hash chain is broken
+    BrokenChain(ChainBreak),
+    /// Two envelopes were persisted for the same command of a client
+    DuplicateDeduplicationKey {
+        key: String,
+        first_sequence: u64,
+        sequence_nr: u64,
+    },
+    /// A dispute, resolve or chargeback has no earlier deposit or withdrawal of the client
+    MissingOriginal {
+        sequence_nr: u64,
+        client_id: u16,
+        tx_id: u32,
+        event: EventKind,
+    },
+    /// `total` drifted from `available + held`
+    BalanceMismatch {
+        sequence_nr: u64,
+        client_id: u16,
+        available: f64,
+        held: f64,
+        total: f64,
+    },
+    /// The dispute index disagrees with the dispute events
+    DisputeIndexMismatch {
+        tx_id: u32,
+        expected_disputed: bool,
+        indexed_disputed: bool,
+    },
+}
+
+impl AuditViolation {
+    fn check(&self) -> &'static str {
+        match self {
+            AuditViolation::SequenceGap { .. } => "sequence_gap",
+            AuditViolation::BrokenChain(_) => "broken_chain",
+            AuditViolation::DuplicateDeduplicationKey { .. } => "duplicate_deduplication_key",
+            AuditViolation::MissingOriginal { .. } => "missing_original",
+            AuditViolation::BalanceMismatch { .. } => "balance_mismatch",
+            AuditViolation::DisputeIndexMismatch { .. } => "dispute_index_mismatch",
+        }
+    }
+}
+
+/// Machine-readable result of a journal audit
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditReport {
+    /// Envelopes replayed
+    pub events: u64,
+    /// Distinct clients seen
+    pub clients: usize,
+    /// Whether a dispute index was compared against the events
+    pub dispute_index_checked: bool,
+    /// Dispute events whose original is not in the replayed history but may precede the
+    /// journal's anchor (archived or truncated), so it could not be checked
+    pub unchecked_originals: u64,
+    /// Number of violations per check
+    pub violation_counts: BTreeMap<&'static str, u64>,
+    /// First violations found, in log order (at most 1000)
+    pub violations: Vec<AuditViolation>,
+}
+
+impl AuditReport {
+    pub fn is_consistent(&self) -> bool {
+        self.violation_counts.is_empty()
+    }
+
+    fn record(&mut self, violation: AuditViolation) {
+        *self.violation_counts.entry(violation.check()).or_default() += 1;
+        if self.violations.len() < MAX_LISTED_VIOLATIONS {
+            self.violations.push(violation);
+        }
+    }
+}
+
+/// Replay the whole journal and check its consistency
+///
+/// Checks that sequence numbers are contiguous, the hash chain is intact, deduplication
+/// keys are unique, every dispute/resolve/chargeback follows a deposit or withdrawal of
+/// the same client and transaction, and that `total == available + held` holds after
+/// every event. When a dispute index is given, it must mark exactly the transactions
+/// whose last dispute event is a Disputed: both a missing and an extra entry are flagged.
+///
+/// Redacted events count as originals and leave balances untouched. Once the journal has
+/// an anchor, originals before it are no longer replayed, so a reference to a transaction
+/// not seen since is counted in `unchecked_originals` rather than flagged.
+pub async fn audit_journal(
+    journal: &(dyn Journal + Send + Sync),
+    dispute_index: Option<&dyn DisputeIndex>,
+) -> Result<AuditReport, PaymentError> {
+    let envelopes = journal.replay(None).await?;
+    let mut report = AuditReport {
+        events: envelopes.len() as u64,
+        dispute_index_checked: dispute_index.is_some(),
+        ..Default::default()
+    };
+
+    for pair in envelopes.windows(2) {
+        if pair[1].sequence_nr != pair[0].sequence_nr + 1 {
+            report.record(AuditViolation::SequenceGap {
+                after: pair[0].sequence_nr,
+                next: pair[1].sequence_nr,
+            });
+        }
+    }
+
+    let anchor = journal.anchor().await?;
+    if let Err(chain_break) = verify_chain_from(anchor.as_ref(), &envelopes) {
+        report.record(AuditViolation::BrokenChain(chain_break));
+    }
+
+    let mut deduplication_keys: HashMap<u128, u64> = HashMap::with_capacity(envelopes.len());
+    let mut originals: HashSet<(u16, u32)> = HashSet::new();
+    let mut disputed: HashMap<u32, bool> = HashMap::new();
+    let mut states: HashMap<u16, AccountState> = HashMap::new();
+
+    for envelope in &envelopes {
+        if let Some(&first_sequence) =
+            deduplication_keys.get(&envelope.deduplication_key.fingerprint(envelope.client_id))
+        {
+            report.record(AuditViolation::DuplicateDeduplicationKey {
+                key: envelope.deduplication_key.as_str().to_string(),
+                first_sequence,
+                sequence_nr: envelope.sequence_nr,
+            });
+        } else {
+            deduplication_keys.insert(
+                envelope.deduplication_key.fingerprint(envelope.client_id),
+                envelope.sequence_nr,
+            );
+        }
+
+        check_original(
+            envelope,
+            anchor.is_some(),
+            &mut originals,
+            &mut disputed,
+            &mut report,
+        );
+
+        let state = states
+            .entry(envelope.client_id)
+            .or_insert_with(AccountState::empty);
+        if let Some(next) = envelope.apply(state) {
+            *state = next;
+        }
+        check_balance(envelope, state, &mut report);
+    }
+    report.clients = states.len();
+
+    if let Some(index) = dispute_index {
+        let expected: BTreeSet<u32> = disputed
+            .into_iter()
+            .filter_map(|(tx_id, disputed)| disputed.then_some(tx_id))
+            .collect();
+        let indexed: BTreeSet<u32> = index.list_disputed().await?.into_iter().collect();
+
+        for &tx_id in expected.symmetric_difference(&indexed) {
+            report.record(AuditViolation::DisputeIndexMismatch {
+                tx_id,
+                expected_disputed: expected.contains(&tx_id),
+                indexed_disputed: indexed.contains(&tx_id),
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Rebuild a dispute index by replaying the journal through DisputeIndexCallback
+///
+/// The dispute index lives in memory, so an offline audit rebuilds it through the same
+/// callback the engine uses on append, which is what a restarted node would hold.
+pub async fn rebuild_dispute_index(
+    journal: Arc<dyn Journal + Send + Sync>,
+) -> Result<Arc<InMemoryDisputeIndex>, PaymentError> {
+    let index = Arc::new(InMemoryDisputeIndex::new());
+    let callback = DisputeIndexCallback::new(index.clone());
+
+    for envelope in journal.replay(None).await? {
+        let ctx = CallbackContext {
+            journal: journal.clone(),
+            envelope,
+        };
+        match &ctx.envelope.event {
+            TransactionTypeEvent::Disputed(event) => callback.on_disputed(event, &ctx).await?,
+            TransactionTypeEvent::Resolved(event) => callback.on_resolved(event, &ctx).await?,
+            TransactionTypeEvent::Chargebacked(event) => {
+                callback.on_chargebacked(event, &ctx).await?
+            }
+            _ => {}
+        }
+    }
+
+    Ok(index)
+}
+
+/// Track originals and dispute status, flagging references to unknown transactions
+///
+/// With `anchored`, an unknown transaction may sit before the anchor and is only counted.
+fn check_original(
+    envelope: &EventEnvelope,
+    anchored: bool,
+    originals: &mut HashSet<(u16, u32)>,
+    disputed: &mut HashMap<u32, bool>,
+    report: &mut AuditReport,
+) {
+    let (tx_id, event, now_disputed) = match &envelope.event {
+        TransactionTypeEvent::Deposited(e) => {
+            originals.insert((envelope.client_id, e.tx_id));
+            return;
+        }
+        TransactionTypeEvent::Withdrawn(e) => {
+            originals.insert((envelope.client_id, e.tx_id));
+            return;
+        }
+        TransactionTypeEvent::Redacted(e) => {
+            originals.insert((envelope.client_id, e.tx_id));
+            return;
+        }
+        TransactionTypeEvent::Disputed(e) => (e.tx_id, EventKind::Disputed, true),
+        TransactionTypeEvent::Resolved(e) => (e.tx_id, EventKind::Resolved, false),
+        TransactionTypeEvent::Chargebacked(e) => (e.tx_id, EventKind::Chargebacked, false),
+    };
+
+    disputed.insert(tx_id, now_disputed);
+    if originals.contains(&(envelope.client_id, tx_id)) {
+        return;
+    }
+    if anchored {
+        report.unchecked_originals += 1;
+    } else {
+        report.record(AuditViolation::MissingOriginal {
+            sequence_nr: envelope.sequence_nr,
+            client_id: envelope.client_id,
+            tx_id,
+            event,
+        });
+    }
+}
+
+fn check_balance(envelope: &EventEnvelope, state: &AccountState, report: &mut AuditReport) {
+    let (available, held, total) = match state {
+        AccountState::Active(s) => (s.available, s.held, s.total),
+        AccountState::Frozen(s) => (s.available, s.held, s.total),
+    };
+
+    if (total - (available + held)).abs() > BALANCE_EPSILON * total.abs().max(1.0) {
+        report.record(AuditViolation::BalanceMismatch {
+            sequence_nr: envelope.sequence_nr,
+            client_id: envelope.client_id,
+            available,
+            held,
+            total,
+        });
+    }
+}
@@ -1,4 +1,7 @@
 use crate::adapter::{ClientRegistry, InMemoryDisputeIndex, InMemoryJournal};
+use crate::domain::PaymentError;
+use crate::port::{DisputeIndex, Journal};
+use crate::service::rebuild_dispute_index;
 use std::sync::Arc;
 
 /// Setup the payment system and return a client registry (Akka-style)
@@ -14,10 +17,24 @@ use std::sync::Arc;
 /// - DisputeIndex maintained via callbacks (infrastructure concern)
 /// - Simple, efficient, ready for database replacement
 pub async fn boot() -> ClientRegistry {
-    let journal: Arc<dyn crate::port::Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
-    let dispute_index: Arc<dyn crate::port::DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
 
     tracing::info!("Payment system initialized");
 
     ClientRegistry::new(journal, dispute_index)
 }
+
+/// Setup the payment system on top of an already opened journal (e.g. FileJournal)
+///
+/// The dispute index is rebuilt from the journal, so transactions an earlier run left
+/// disputed can still be resolved or charged back.
+pub async fn boot_with_journal(
+    journal: Arc<dyn Journal + Send + Sync>,
+) -> Result<ClientRegistry, PaymentError> {
+    let dispute_index: Arc<dyn DisputeIndex> = rebuild_dispute_index(journal.clone()).await?;
+
+    tracing::info!("Payment system initialized");
+
+    Ok(ClientRegistry::new(journal, dispute_index))
+}
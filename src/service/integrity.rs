@@ -0,0 +1,43 @@
+use crate::domain::{ChainBreak, PaymentError, verify_chain_from};
+use crate::port::Journal;
+use serde::Serialize;
+
+/// Result of walking a journal's hash chain
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainReport {
+    /// Envelopes whose link and content hash checked out
+    pub verified: u64,
+    /// First broken link, if any
+    pub first_break: Option<ChainBreak>,
+}
+
+impl ChainReport {
+    pub fn is_intact(&self) -> bool {
+        self.first_break.is_none()
+    }
+}
+
+/// Replay the whole journal and verify its hash chain
+///
+/// Works against any Journal implementation since it only relies on `replay` and
+/// `anchor`: a hot log that no longer starts at #1 must continue the journal's anchor.
+pub async fn verify_journal_chain(
+    journal: &(dyn Journal + Send + Sync),
+) -> Result<ChainReport, PaymentError> {
+    let anchor = journal.anchor().await?;
+    let envelopes = journal.replay(None).await?;
+
+    Ok(match verify_chain_from(anchor.as_ref(), &envelopes) {
+        Ok(verified) => ChainReport {
+            verified,
+            first_break: None,
+        },
+        Err(chain_break) => ChainReport {
+            verified: envelopes
+                .iter()
+                .take_while(|e| e.sequence_nr < chain_break.sequence_nr)
+                .count() as u64,
+            first_break: Some(chain_break),
+        },
+    })
+}
@@ -1,6 +1,18 @@
+mod admin;
+mod audit;
 mod boot;
+mod integrity;
 pub mod mock;
 pub mod orchestrator;
+mod projection;
+mod temporal;
+mod transfer;
 
+pub use admin::*;
+pub use audit::*;
 pub use boot::*;
+pub use integrity::*;
 pub use orchestrator::*;
+pub use projection::*;
+pub use temporal::*;
+pub use transfer::*;
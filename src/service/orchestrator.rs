@@ -1,44 +1,168 @@
-use crate::adapter::ClientRegistry;
+use crate::adapter::{ClientRegistry, SnapshotPolicy};
 use crate::domain::{
-    AccountState, CommandMetadata, DeduplicationKey, OrchestratorMode, TransactionTypeCommand,
+    AccountState, CommandMetadata, DeduplicationKey, ErrorCategory, ExecutionBackend,
+    OrchestratorMode, PaymentError, TransactionTypeCommand,
 };
-use std::collections::HashMap;
+use crate::port::{ClientExecutor, DisputeIndex, EventCallback, Journal, Snapshotter};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Outcome of the commands of a run, with failures counted by category and error code
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProcessingReport {
+    /// Commands read from the input
+    pub commands: u64,
+    /// Commands that failed, per error category
+    pub failures: BTreeMap<ErrorCategory, u64>,
+    /// Commands that failed, per stable error code
+    pub codes: BTreeMap<String, u64>,
+}
+
+impl ProcessingReport {
+    fn record_failure(&mut self, error: &PaymentError) {
+        *self.failures.entry(error.category()).or_default() += 1;
+        *self.codes.entry(error.code().to_string()).or_default() += 1;
+    }
+
+    /// Commands that failed, whatever the reason
+    pub fn failed(&self) -> u64 {
+        self.failures.values().sum()
+    }
+
+    /// Commands that failed because of the infrastructure rather than business rules
+    pub fn infrastructure_failures(&self) -> u64 {
+        self.failures
+            .iter()
+            .filter(|(category, _)| **category != ErrorCategory::BusinessRejection)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
 
 pub struct Orchestrator {
     registry: ClientRegistry,
     mode: OrchestratorMode,
+    backend: ExecutionBackend,
 }
 
 impl Orchestrator {
     pub async fn new(mode: OrchestratorMode) -> Self {
         let registry = super::boot().await;
-        Self { registry, mode }
+        Self {
+            registry,
+            mode,
+            backend: ExecutionBackend::default(),
+        }
+    }
+
+    /// Create an Orchestrator persisting to the given journal instead of an in-memory one
+    pub async fn with_journal(
+        journal: Arc<dyn Journal + Send + Sync>,
+        mode: OrchestratorMode,
+    ) -> Result<Self, PaymentError> {
+        let registry = super::boot_with_journal(journal).await?;
+        Ok(Self {
+            registry,
+            mode,
+            backend: ExecutionBackend::default(),
+        })
+    }
+
+    /// Register a callback on every client actor's engine (e.g. a CDC sink)
+    pub fn with_callback(mut self, callback: Arc<dyn EventCallback>) -> Self {
+        self.registry = self.registry.with_callback(callback);
+        self
+    }
+
+    /// Recover client actors from the snapshots in `snapshots` plus the journal
+    pub fn with_snapshots(mut self, snapshots: Arc<dyn Snapshotter>) -> Self {
+        self.registry = self.registry.with_snapshots(snapshots);
+        self
+    }
+
+    /// Snapshot client actors according to `policy`
+    pub fn with_snapshot_policy(mut self, policy: SnapshotPolicy) -> Self {
+        self.registry = self.registry.with_snapshot_policy(policy);
+        self
+    }
+
+    /// Passivate client actors idle for `timeout`
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.registry = self.registry.with_idle_timeout(timeout);
+        self
+    }
+
+    /// The dispute index maintained while processing, e.g. to audit the journal against it
+    pub fn dispute_index(&self) -> Arc<dyn DisputeIndex> {
+        self.registry.dispute_index()
+    }
+
+    /// Execute commands with `backend` (one actor per client by default)
+    pub fn with_backend(mut self, backend: ExecutionBackend) -> Self {
+        self.backend = backend;
+        self
     }
 
     /// Create an Orchestrator with a custom registry.
     ///
     /// ## Warning: This is NOT MEANT FOR PRODUCTION USE. Only for testing purposes.
     pub fn with_registry(registry: ClientRegistry, mode: OrchestratorMode) -> Self {
-        Self { registry, mode }
+        Self {
+            registry,
+            mode,
+            backend: ExecutionBackend::default(),
+        }
     }
 
     pub async fn process(self) -> Result<HashMap<u16, AccountState>, Box<dyn std::error::Error>> {
+        let (states, _) = self.process_with_report().await?;
+        Ok(states)
+    }
+
+    /// Process the input and also report how many commands failed, and why
+    ///
+    /// Business rejections and transient failures that outlived the registry's retries are
+    /// reported per line and processing continues. An `InfrastructureFatal` error stops the
+    /// run and is returned: the final states could not be trusted.
+    pub async fn process_with_report(
+        self,
+    ) -> Result<(HashMap<u16, AccountState>, ProcessingReport), Box<dyn std::error::Error>> {
         let OrchestratorMode::Csv { file_path } = self.mode.clone();
-        self.process_csv(&file_path).await
+        match self.backend {
+            ExecutionBackend::ActorPerClient => {
+                let states = self.process_csv(&self.registry, &file_path).await;
+                let metrics = self.registry.metrics();
+                tracing::info!(
+                    "Client actors: {} spawned, {} passivated",
+                    metrics.spawned(),
+                    metrics.passivated()
+                );
+                states
+            }
+            ExecutionBackend::WorkerPool { workers } => {
+                let pool = self.registry.worker_pool(workers).await?;
+                self.process_csv(&pool, &file_path).await
+            }
+        }
     }
 
     async fn process_csv(
-        self,
+        &self,
+        executor: &dyn ClientExecutor,
         file_path: &str,
-    ) -> Result<HashMap<u16, AccountState>, Box<dyn std::error::Error>> {
+    ) -> Result<(HashMap<u16, AccountState>, ProcessingReport), Box<dyn std::error::Error>> {
         let file_handle = File::open(file_path)?;
         let mut rdr = csv::Reader::from_reader(file_handle);
 
         let mut line_num = 0;
+        let mut report = ProcessingReport::default();
 
         for result in rdr.deserialize() {
             line_num += 1;
+            report.commands += 1;
             let command: TransactionTypeCommand = result?;
             let client_id = command.client_id();
 
@@ -46,27 +170,31 @@ impl Orchestrator {
                 deduplication_key: DeduplicationKey::new(format!("csv:{}:{}", file_path, line_num)),
             };
 
-            // Process command via registry - it will get_or_spawn the client actor
-            match self
-                .registry
-                .process_command(client_id, command, metadata)
-                .await
-            {
+            // Process command via the executor - e.g. the registry spawns the client actor
+            match executor.process_command(client_id, command, metadata).await {
                 Ok(_) => {}
-                Err(e) => eprintln!("Error processing line {}: {}", line_num, e),
+                Err(e) if e.category() == ErrorCategory::InfrastructureFatal => {
+                    eprintln!("Fatal error at line {} [{}]: {}", line_num, e.code(), e);
+                    executor.shutdown_all().await;
+                    return Err(Box::new(e));
+                }
+                Err(e) => {
+                    eprintln!("Error processing line {} [{}]: {}", line_num, e.code(), e);
+                    report.record_failure(&e);
+                }
             }
         }
 
         // Give actors time to process all messages
         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
-        // Collect all client states from the registry
-        let states = self.registry.get_all_states().await?;
+        // Collect all client states from the executor
+        let states = executor.get_all_states().await?;
 
         // Shutdown all client actors
-        self.registry.shutdown_all().await;
+        executor.shutdown_all().await;
 
-        Ok(states)
+        Ok((states, report))
     }
 
     /// Output account states as CSV to stdout
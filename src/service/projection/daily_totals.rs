@@ -0,0 +1,97 @@
+use crate::domain::{EventEnvelope, PaymentError, TransactionTypeEvent};
+use crate::port::Projection;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::RwLock;
+
+/// Amounts moved by a client on one (UTC) day
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DailyTotals {
+    pub deposited: f64,
+    pub withdrawn: f64,
+    pub disputed: f64,
+    pub resolved: f64,
+    pub charged_back: f64,
+    /// Number of events, redacted ones included
+    pub events: u64,
+}
+
+#[derive(Default)]
+struct DailyTotalsState {
+    totals: BTreeMap<(u16, NaiveDate), DailyTotals>,
+    /// Last folded sequence per client, to ignore redeliveries
+    last_sequence: HashMap<u16, u64>,
+}
+
+/// Daily totals per client, bucketed by the events' UTC date
+#[derive(Default)]
+pub struct DailyTotalsProjection {
+    state: RwLock<DailyTotalsState>,
+}
+
+impl DailyTotalsProjection {
+    pub const NAME: &'static str = "daily-totals";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Totals of a client on a day
+    pub async fn totals(&self, client_id: u16, date: NaiveDate) -> Option<DailyTotals> {
+        self.state
+            .read()
+            .await
+            .totals
+            .get(&(client_id, date))
+            .cloned()
+    }
+
+    /// Every day a client had events on, oldest first
+    pub async fn days(&self, client_id: u16) -> Vec<(NaiveDate, DailyTotals)> {
+        self.state
+            .read()
+            .await
+            .totals
+            .range((client_id, NaiveDate::MIN)..=(client_id, NaiveDate::MAX))
+            .map(|((_, date), totals)| (*date, totals.clone()))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Projection for DailyTotalsProjection {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    async fn apply(&self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        let mut state = self.state.write().await;
+        let last_sequence = state.last_sequence.entry(envelope.client_id).or_default();
+        if *last_sequence >= envelope.sequence_nr {
+            return Ok(());
+        }
+        *last_sequence = envelope.sequence_nr;
+
+        let totals = state
+            .totals
+            .entry((envelope.client_id, envelope.timestamp.date_naive()))
+            .or_default();
+        totals.events += 1;
+        match &envelope.event {
+            TransactionTypeEvent::Deposited(e) => totals.deposited += e.amount,
+            TransactionTypeEvent::Withdrawn(e) => totals.withdrawn += e.amount,
+            TransactionTypeEvent::Disputed(e) => totals.disputed += e.amount,
+            TransactionTypeEvent::Resolved(e) => totals.resolved += e.amount,
+            TransactionTypeEvent::Chargebacked(e) => totals.charged_back += e.amount,
+            TransactionTypeEvent::Redacted(_) => {}
+        }
+        Ok(())
+    }
+
+    async fn reset(&self) -> Result<(), PaymentError> {
+        *self.state.write().await = DailyTotalsState::default();
+        Ok(())
+    }
+}
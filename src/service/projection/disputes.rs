@@ -0,0 +1,80 @@
+use crate::domain::{EventEnvelope, PaymentError, TransactionTypeEvent};
+use crate::port::Projection;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tokio::sync::RwLock;
+
+/// A dispute that was neither resolved nor charged back yet
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpenDispute {
+    pub client_id: u16,
+    pub tx_id: u32,
+    pub amount: f64,
+    /// Sequence of the Disputed event
+    pub sequence_nr: u64,
+    pub disputed_at: DateTime<Utc>,
+}
+
+/// Open disputes of every client
+///
+/// Redeliveries need no tracking: they replay a suffix of the log in order, and disputes are
+/// keyed by transaction, so the model converges to the same state.
+#[derive(Default)]
+pub struct OpenDisputesProjection {
+    disputes: RwLock<BTreeMap<(u16, u32), OpenDispute>>,
+}
+
+impl OpenDisputesProjection {
+    pub const NAME: &'static str = "open-disputes";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open disputes, of one client or all of them, ordered by client and transaction
+    pub async fn open_disputes(&self, client_id: Option<u16>) -> Vec<OpenDispute> {
+        self.disputes
+            .read()
+            .await
+            .values()
+            .filter(|dispute| client_id.is_none_or(|client_id| dispute.client_id == client_id))
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Projection for OpenDisputesProjection {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    async fn apply(&self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        let key = (envelope.client_id, envelope.tx_id);
+        let mut disputes = self.disputes.write().await;
+
+        match &envelope.event {
+            TransactionTypeEvent::Disputed(e) => {
+                disputes.entry(key).or_insert(OpenDispute {
+                    client_id: envelope.client_id,
+                    tx_id: envelope.tx_id,
+                    amount: e.amount,
+                    sequence_nr: envelope.sequence_nr,
+                    disputed_at: envelope.timestamp,
+                });
+            }
+            TransactionTypeEvent::Resolved(_) | TransactionTypeEvent::Chargebacked(_) => {
+                disputes.remove(&key);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn reset(&self) -> Result<(), PaymentError> {
+        self.disputes.write().await.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use crate::domain::{EventEnvelope, EventKind, PaymentError};
+use crate::port::Projection;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// One event in a client's transaction history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    pub sequence_nr: u64,
+    pub tx_id: u32,
+    pub timestamp: DateTime<Utc>,
+    pub event: EventKind,
+    /// None for redacted events
+    pub amount: Option<f64>,
+}
+
+/// Per-client transaction history, in log order
+#[derive(Default)]
+pub struct TransactionHistoryProjection {
+    entries: RwLock<HashMap<u16, Vec<HistoryEntry>>>,
+}
+
+impl TransactionHistoryProjection {
+    pub const NAME: &'static str = "transaction-history";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// History of a client (empty if it has no events)
+    pub async fn history(&self, client_id: u16) -> Vec<HistoryEntry> {
+        self.entries
+            .read()
+            .await
+            .get(&client_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Projection for TransactionHistoryProjection {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    async fn apply(&self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        let mut entries = self.entries.write().await;
+        let history = entries.entry(envelope.client_id).or_default();
+
+        // Redelivered after a restart: already folded
+        if history
+            .last()
+            .is_some_and(|last| last.sequence_nr >= envelope.sequence_nr)
+        {
+            return Ok(());
+        }
+
+        history.push(HistoryEntry {
+            sequence_nr: envelope.sequence_nr,
+            tx_id: envelope.tx_id,
+            timestamp: envelope.timestamp,
+            event: envelope.event.kind(),
+            amount: envelope.event.amount(),
+        });
+        Ok(())
+    }
+
+    async fn reset(&self) -> Result<(), PaymentError> {
+        self.entries.write().await.clear();
+        Ok(())
+    }
+}
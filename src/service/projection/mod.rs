@@ -0,0 +1,9 @@
+mod daily_totals;
+mod disputes;
+mod history;
+mod runner;
+
+pub use daily_totals::*;
+pub use disputes::*;
+pub use history::*;
+pub use runner::*;
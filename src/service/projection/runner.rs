@@ -0,0 +1,105 @@
+use crate::adapter::{SubscriptionConfig, SubscriptionHandle, SubscriptionHub};
+use crate::domain::{EventEnvelope, PaymentError};
+use crate::port::{CheckpointStore, Projection, Subscriber};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Keeps a projection up to date through a subscription
+///
+/// The checkpoint store must be as durable as the projection's own state: an in-memory read
+/// model paired with a file checkpoint would resume after a restart with its history missing.
+pub struct ProjectionRunner<P: Projection + 'static> {
+    hub: Arc<SubscriptionHub>,
+    projection: Arc<P>,
+    checkpoints: Arc<dyn CheckpointStore>,
+    config: SubscriptionConfig,
+    handle: Option<SubscriptionHandle>,
+}
+
+impl<P: Projection + 'static> ProjectionRunner<P> {
+    /// Start feeding `projection` from its last committed offset
+    pub fn start(
+        hub: Arc<SubscriptionHub>,
+        projection: Arc<P>,
+        checkpoints: Arc<dyn CheckpointStore>,
+        config: SubscriptionConfig,
+    ) -> Self {
+        let mut runner = Self {
+            hub,
+            projection,
+            checkpoints,
+            config,
+            handle: None,
+        };
+        runner.subscribe();
+        runner
+    }
+
+    /// The projection, for queries
+    pub fn projection(&self) -> &Arc<P> {
+        &self.projection
+    }
+
+    /// Last sequence number folded into the projection
+    pub fn position(&self) -> u64 {
+        self.handle.as_ref().map_or(0, |handle| handle.position())
+    }
+
+    /// Wait until the projection has folded every event up to `sequence_nr`
+    pub async fn wait_for(&mut self, sequence_nr: u64) -> Result<(), PaymentError> {
+        match self.handle.as_mut() {
+            Some(handle) => handle.wait_for(sequence_nr).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Reset the projection and fold the whole journal into it again
+    ///
+    /// Used after a projection's logic changed, or to repair a read model. Queries see a
+    /// partially rebuilt model until `wait_for` the journal's head returns.
+    pub async fn rebuild(&mut self) -> Result<(), PaymentError> {
+        if let Some(handle) = self.handle.take() {
+            handle.stop().await?;
+        }
+
+        tracing::info!("Rebuilding projection {}", self.projection.name());
+        self.projection.reset().await?;
+        self.checkpoints.commit(self.projection.name(), 0).await?;
+        self.subscribe();
+        Ok(())
+    }
+
+    /// Stop the subscription and commit its offset
+    pub async fn stop(mut self) -> Result<u64, PaymentError> {
+        match self.handle.take() {
+            Some(handle) => handle.stop().await,
+            None => Ok(0),
+        }
+    }
+
+    fn subscribe(&mut self) {
+        let subscriber = Arc::new(ProjectionSubscriber {
+            projection: self.projection.clone(),
+        });
+        self.handle = Some(
+            self.hub
+                .subscribe(subscriber, self.checkpoints.clone(), self.config),
+        );
+    }
+}
+
+/// Adapts a Projection to the Subscriber port
+struct ProjectionSubscriber<P: Projection> {
+    projection: Arc<P>,
+}
+
+#[async_trait]
+impl<P: Projection> Subscriber for ProjectionSubscriber<P> {
+    fn name(&self) -> &str {
+        self.projection.name()
+    }
+
+    async fn handle(&self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        self.projection.apply(envelope).await
+    }
+}
@@ -0,0 +1,191 @@
+use crate::domain::{
+    AccountState, EventEnvelope, EventKind, JournalCursor, JournalQuery, PaymentError,
+};
+use crate::port::{EventHandler, Journal, Snapshotter};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Events read from the journal per query while folding
+const TEMPORAL_PAGE_SIZE: usize = 1000;
+
+/// Point in the journal's history a temporal query is evaluated at
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AsOf {
+    /// Every event up to and including this global sequence number
+    Sequence(u64),
+    /// Every event recorded at or before this wall-clock time
+    Timestamp(DateTime<Utc>),
+}
+
+impl AsOf {
+    fn includes(&self, envelope: &EventEnvelope) -> bool {
+        match self {
+            AsOf::Sequence(sequence_nr) => envelope.sequence_nr <= *sequence_nr,
+            AsOf::Timestamp(timestamp) => envelope.timestamp <= *timestamp,
+        }
+    }
+}
+
+/// Balance of an account right after one of its events was applied
+#[derive(Debug, Clone, Serialize)]
+pub struct BalancePoint {
+    pub sequence_nr: u64,
+    pub tx_id: u32,
+    pub timestamp: DateTime<Utc>,
+    pub event: EventKind,
+    pub available: f64,
+    pub held: f64,
+    pub total: f64,
+    pub locked: bool,
+}
+
+/// Account state of a client as it was at a point in history
+#[derive(Debug, Clone, Serialize)]
+pub struct TemporalReport {
+    pub client_id: u16,
+    pub as_of: AsOf,
+    /// Sequence of the snapshot the fold started from (None when replayed from the start)
+    pub snapshot_sequence: Option<u64>,
+    /// Last event of the client included in the state
+    pub last_sequence: Option<u64>,
+    pub state: AccountState,
+    /// Balance after each folded event, in log order
+    pub history: Vec<BalancePoint>,
+}
+
+/// Rebuild a client's AccountState as it was at `as_of`
+///
+/// Folds the client's envelopes (`Journal::query` by client) through `EventHandler::apply`,
+/// starting from the nearest snapshot that is not newer than `as_of`, and from an empty
+/// account when there is none. A timestamp query stops at the client's first event
+/// recorded after that time. `last_activity` reflects the timestamp of the last folded
+/// event.
+pub async fn account_state_as_of(
+    journal: &(dyn Journal + Send + Sync),
+    client_id: u16,
+    as_of: AsOf,
+    snapshotter: Option<&dyn Snapshotter>,
+) -> Result<TemporalReport, PaymentError> {
+    let snapshot = match snapshotter {
+        Some(snapshotter) => nearest_snapshot(journal, snapshotter, client_id, &as_of).await?,
+        None => None,
+    };
+    let (snapshot_sequence, mut state) = match snapshot {
+        Some((sequence_nr, state)) => (Some(sequence_nr), state),
+        None => (None, AccountState::empty()),
+    };
+
+    let mut query = JournalQuery {
+        client_id: Some(client_id),
+        to_sequence: match as_of {
+            AsOf::Sequence(sequence_nr) => Some(sequence_nr),
+            AsOf::Timestamp(_) => None,
+        },
+        after: snapshot_sequence.map(JournalCursor::after),
+        limit: TEMPORAL_PAGE_SIZE,
+        ..Default::default()
+    };
+    let mut last_sequence = None;
+    let mut history = Vec::new();
+    'pages: loop {
+        let page = journal.query(&query).await?;
+        for envelope in &page.envelopes {
+            if !as_of.includes(envelope) {
+                break 'pages;
+            }
+
+            if let Some(next) = envelope.apply(&state) {
+                state = with_last_activity(next, envelope.timestamp);
+            }
+            last_sequence = Some(envelope.sequence_nr);
+            history.push(balance_point(envelope, &state));
+        }
+        match page.next_cursor {
+            Some(cursor) => query.after = Some(cursor),
+            None => break,
+        }
+    }
+
+    Ok(TemporalReport {
+        client_id,
+        as_of,
+        snapshot_sequence,
+        last_sequence,
+        state,
+        history,
+    })
+}
+
+/// The latest snapshot that does not already include events past `as_of`
+///
+/// For timestamp queries this is decided by the envelope each snapshot was taken at, which
+/// must still be in the journal; older snapshots are tried until one qualifies.
+async fn nearest_snapshot(
+    journal: &(dyn Journal + Send + Sync),
+    snapshotter: &dyn Snapshotter,
+    client_id: u16,
+    as_of: &AsOf,
+) -> Result<Option<(u64, AccountState)>, PaymentError> {
+    let mut bound = match as_of {
+        AsOf::Sequence(sequence_nr) => *sequence_nr,
+        AsOf::Timestamp(_) => u64::MAX,
+    };
+    loop {
+        let Some((sequence_nr, state)) = snapshotter.load_at_or_before(client_id, bound).await?
+        else {
+            return Ok(None);
+        };
+        if let AsOf::Sequence(_) = as_of {
+            return Ok(Some((sequence_nr, state)));
+        }
+
+        let page = journal
+            .query(&JournalQuery {
+                client_id: Some(client_id),
+                from_sequence: Some(sequence_nr),
+                to_sequence: Some(sequence_nr),
+                limit: 1,
+                ..Default::default()
+            })
+            .await?;
+        if page.envelopes.first().is_some_and(|e| as_of.includes(e)) {
+            return Ok(Some((sequence_nr, state)));
+        }
+        match sequence_nr.checked_sub(1) {
+            Some(older) => bound = older,
+            None => return Ok(None),
+        }
+    }
+}
+
+fn with_last_activity(state: AccountState, timestamp: DateTime<Utc>) -> AccountState {
+    match state {
+        AccountState::Active(mut active) => {
+            active.last_activity = timestamp;
+            AccountState::Active(active)
+        }
+        AccountState::Frozen(mut frozen) => {
+            frozen.last_activity = timestamp;
+            AccountState::Frozen(frozen)
+        }
+    }
+}
+
+fn balance_point(envelope: &EventEnvelope, state: &AccountState) -> BalancePoint {
+    let (available, held, total, locked) = match state {
+        AccountState::Active(s) => (s.available, s.held, s.total, false),
+        AccountState::Frozen(s) => (s.available, s.held, s.total, true),
+    };
+
+    BalancePoint {
+        sequence_nr: envelope.sequence_nr,
+        tx_id: envelope.tx_id,
+        timestamp: envelope.timestamp,
+        event: envelope.event.kind(),
+        available,
+        held,
+        total,
+        locked,
+    }
+}
@@ -112,6 +112,12 @@ impl TestContext {
     }
 }
 
+impl Default for TestContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Helper to create a deposit command
 pub fn deposit(client: u16, tx: u32, amount: f64) -> TransactionTypeCommand {
     use payment::domain::Deposit;
@@ -0,0 +1,100 @@
+use payment::adapter::InMemoryJournal;
+use payment::domain::*;
+use payment::port::Journal;
+
+async fn append_deposit(journal: &InMemoryJournal, tx_id: u32, key: &str) -> EventEnvelope {
+    journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 1,
+                tx_id,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id: 1,
+                tx_id,
+                timestamp: chrono::Utc::now(),
+                deduplication_key: DeduplicationKey::new(key.to_string()),
+            },
+        )
+        .await
+        .unwrap()
+        .into_envelope()
+}
+
+#[tokio::test]
+async fn test_replay_returns_envelopes_exactly_as_appended() {
+    let journal = InMemoryJournal::new();
+
+    let keys = [
+        "csv:/tmp/data.csv:2",
+        "csv:/tmp/data.csv:007",
+        "request-abc",
+        "kafka:0:",
+    ];
+    let mut appended = Vec::new();
+    for (tx_id, key) in keys.iter().enumerate() {
+        appended.push(append_deposit(&journal, tx_id as u32 + 1, key).await);
+    }
+
+    let replayed = journal.replay(None).await.unwrap();
+    assert_eq!(replayed.len(), appended.len());
+    for (original, replayed) in appended.iter().zip(&replayed) {
+        assert_eq!(original.sequence_nr, replayed.sequence_nr);
+        assert_eq!(original.deduplication_key, replayed.deduplication_key);
+        assert_eq!(original.timestamp, replayed.timestamp);
+        assert_eq!(original.previous_hash, replayed.previous_hash);
+        assert_eq!(original.hash, replayed.hash);
+    }
+    assert!(verify_chain(replayed.iter()).is_ok());
+}
+
+#[tokio::test]
+async fn test_imported_envelopes_without_chain_keep_their_hashes() {
+    let journal = InMemoryJournal::new();
+
+    let legacy = EventEnvelope {
+        sequence_nr: 1,
+        event: TransactionTypeEvent::Deposited(Deposited {
+            client_id: 1,
+            tx_id: 1,
+            amount: 10.0,
+        }),
+        timestamp: chrono::Utc::now(),
+        client_id: 1,
+        tx_id: 1,
+        deduplication_key: DeduplicationKey::new("deposit:1:1".to_string()),
+        previous_hash: String::new(),
+        hash: "NOT-A-DIGEST".to_string(),
+    };
+    journal.import(legacy).await.unwrap();
+
+    let replayed = journal.find_by_tx_id(1).await.unwrap();
+    assert_eq!(replayed.len(), 1);
+    assert_eq!(replayed[0].previous_hash, "");
+    assert_eq!(replayed[0].hash, "NOT-A-DIGEST");
+}
+
+#[tokio::test]
+async fn test_visit_reads_events_without_materializing_envelopes() {
+    let journal = InMemoryJournal::new();
+    for tx_id in 1..=5 {
+        append_deposit(&journal, tx_id, &format!("deposit:1:{}", tx_id)).await;
+    }
+
+    let mut total = 0.0;
+    journal
+        .visit(Some(3), |event| {
+            if let TransactionTypeEvent::Deposited(deposit) = event.event() {
+                total += deposit.amount;
+            }
+        })
+        .await;
+    assert_eq!(total, 30.0);
+
+    let mut sequences = Vec::new();
+    journal
+        .visit_tx_id(4, |event| sequences.push(event.sequence_nr()))
+        .await;
+    assert_eq!(sequences, vec![4]);
+}
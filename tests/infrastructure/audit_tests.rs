@@ -0,0 +1,211 @@
+use payment::adapter::{InMemoryDisputeIndex, InMemoryJournal};
+use payment::domain::*;
+use payment::port::{DisputeIndex, Journal};
+use payment::service::{AuditViolation, Orchestrator, audit_journal, rebuild_dispute_index};
+use std::io::Write;
+use std::sync::Arc;
+
+fn metadata(client_id: u16, tx_id: u32, key: &str) -> EventMetadata {
+    EventMetadata {
+        client_id,
+        tx_id,
+        timestamp: chrono::Utc::now(),
+        deduplication_key: DeduplicationKey::new(key.to_string()),
+    }
+}
+
+async fn consistent_journal() -> Arc<InMemoryJournal> {
+    let journal = Arc::new(InMemoryJournal::new());
+    journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 1,
+                tx_id: 1,
+                amount: 100.0,
+            }),
+            metadata(1, 1, "deposit:1"),
+        )
+        .await
+        .unwrap();
+    journal
+        .append(
+            TransactionTypeEvent::Disputed(Disputed {
+                client_id: 1,
+                tx_id: 1,
+                amount: 100.0,
+            }),
+            metadata(1, 1, "dispute:1"),
+        )
+        .await
+        .unwrap();
+    journal
+}
+
+#[tokio::test]
+async fn test_audit_of_consistent_journal_reports_nothing() {
+    let journal = consistent_journal().await;
+    let index = rebuild_dispute_index(journal.clone()).await.unwrap();
+
+    let report = audit_journal(journal.as_ref(), Some(index.as_ref()))
+        .await
+        .unwrap();
+
+    assert!(report.is_consistent(), "{:?}", report.violations);
+    assert_eq!(report.events, 2);
+    assert_eq!(report.clients, 1);
+    assert!(index.is_disputed(1).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_audit_flags_missing_original_and_stale_dispute_index() {
+    let journal = consistent_journal().await;
+    journal
+        .append(
+            TransactionTypeEvent::Resolved(Resolved {
+                client_id: 2,
+                tx_id: 42,
+                amount: 5.0,
+            }),
+            metadata(2, 42, "resolve:42"),
+        )
+        .await
+        .unwrap();
+
+    // An empty index misses the dispute on tx 1
+    let index = InMemoryDisputeIndex::new();
+    let report = audit_journal(journal.as_ref(), Some(&index)).await.unwrap();
+
+    assert!(!report.is_consistent());
+    assert_eq!(report.violation_counts.get("missing_original"), Some(&1));
+    assert_eq!(
+        report.violation_counts.get("dispute_index_mismatch"),
+        Some(&1)
+    );
+    assert!(report.violations.iter().any(|v| matches!(
+        v,
+        AuditViolation::MissingOriginal {
+            tx_id: 42,
+            event: EventKind::Resolved,
+            ..
+        }
+    )));
+}
+
+#[tokio::test]
+async fn test_audit_flags_gaps_and_duplicate_keys() {
+    let source = consistent_journal().await;
+    let mut envelopes = source.replay(None).await.unwrap();
+
+    // Re-persist the deposit command under a later sequence number, leaving a gap
+    let mut duplicate = envelopes[0].clone();
+    duplicate.sequence_nr = 5;
+    duplicate.previous_hash = envelopes[1].hash.clone();
+    duplicate.hash = duplicate.content_hash();
+    envelopes.push(duplicate);
+
+    let journal = InMemoryJournal::new();
+    for envelope in envelopes {
+        journal.import(envelope).await.unwrap();
+    }
+
+    let report = audit_journal(&journal, None).await.unwrap();
+
+    assert!(!report.dispute_index_checked);
+    assert_eq!(report.violation_counts.get("sequence_gap"), Some(&1));
+    assert_eq!(
+        report.violation_counts.get("duplicate_deduplication_key"),
+        Some(&1)
+    );
+    assert!(
+        report
+            .violations
+            .iter()
+            .any(|v| matches!(v, AuditViolation::SequenceGap { after: 2, next: 5 }))
+    );
+}
+
+#[tokio::test]
+async fn test_audit_against_the_index_maintained_while_processing() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    writeln!(file, "type,client,tx,amount").unwrap();
+    writeln!(file, "deposit,1,1,10.0").unwrap();
+    writeln!(file, "deposit,1,2,5.0").unwrap();
+    writeln!(file, "dispute,1,1,").unwrap();
+    file.flush().unwrap();
+
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let orchestrator = Orchestrator::with_journal(
+        journal.clone(),
+        OrchestratorMode::Csv {
+            file_path: file.path().to_str().unwrap().to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    let index = orchestrator.dispute_index();
+    orchestrator.process().await.unwrap();
+
+    let report = audit_journal(journal.as_ref(), Some(index.as_ref()))
+        .await
+        .unwrap();
+    assert!(report.dispute_index_checked);
+    assert!(report.is_consistent(), "{:?}", report.violations);
+
+    // Drift in the live index is reported
+    index.unmark_disputed(1).await.unwrap();
+    let report = audit_journal(journal.as_ref(), Some(index.as_ref()))
+        .await
+        .unwrap();
+    assert_eq!(
+        report.violation_counts.get("dispute_index_mismatch"),
+        Some(&1)
+    );
+}
+
+#[tokio::test]
+async fn test_audit_flags_index_entries_without_a_dispute_event() {
+    let journal = consistent_journal().await;
+    let index = rebuild_dispute_index(journal.clone()).await.unwrap();
+
+    // tx 7 never saw a dispute event, so the journal alone never asks about it
+    index.mark_disputed(7, 1.0).await.unwrap();
+    let report = audit_journal(journal.as_ref(), Some(index.as_ref()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        report.violation_counts.get("dispute_index_mismatch"),
+        Some(&1)
+    );
+    assert!(report.violations.iter().any(|v| matches!(
+        v,
+        AuditViolation::DisputeIndexMismatch {
+            tx_id: 7,
+            expected_disputed: false,
+            indexed_disputed: true,
+        }
+    )));
+}
+
+#[tokio::test]
+async fn test_audit_does_not_flag_originals_before_the_anchor() {
+    let journal = consistent_journal().await;
+    // Drop the deposit and the dispute; the resolve still references the deposit
+    journal.truncate(3).await.unwrap();
+    journal
+        .append(
+            TransactionTypeEvent::Resolved(Resolved {
+                client_id: 1,
+                tx_id: 1,
+                amount: 100.0,
+            }),
+            metadata(1, 1, "resolve:1"),
+        )
+        .await
+        .unwrap();
+
+    let report = audit_journal(journal.as_ref(), None).await.unwrap();
+
+    assert!(report.is_consistent(), "{:?}", report.violations);
+    assert_eq!(report.unchecked_originals, 1);
+}
@@ -0,0 +1,223 @@
+use payment::adapter::{
+    CdcSink, CdcSinkConfig, CdcTail, ClientRegistry, InMemoryDisputeIndex, InMemoryJournal,
+    load_manifest,
+};
+use payment::domain::*;
+use payment::port::{CallbackContext, DisputeIndex, EventCallback, Journal};
+use std::sync::Arc;
+
+async fn append_deposit(journal: &dyn Journal, client_id: u16, tx_id: u32) -> EventEnvelope {
+    journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id,
+                tx_id,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id,
+                tx_id,
+                timestamp: chrono::Utc::now(),
+                deduplication_key: DeduplicationKey::new(format!("deposit:{}", tx_id)),
+            },
+        )
+        .await
+        .unwrap()
+        .into_envelope()
+}
+
+/// Deliver an envelope to the sink the way the engine does after persisting it
+async fn publish(
+    sink: &CdcSink,
+    journal: &Arc<dyn Journal + Send + Sync>,
+    envelope: EventEnvelope,
+) {
+    let TransactionTypeEvent::Deposited(event) = envelope.event.clone() else {
+        panic!("Expected a deposit");
+    };
+    let ctx = CallbackContext {
+        journal: journal.clone(),
+        envelope,
+    };
+    sink.on_deposited(&event, &ctx).await.unwrap();
+}
+
+fn drain(tail: &mut CdcTail) -> Vec<u64> {
+    std::iter::from_fn(|| tail.try_next().unwrap())
+        .map(|envelope| envelope.sequence_nr)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_cdc_sink_rotates_segments_into_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let sink = CdcSink::open(
+        dir.path(),
+        CdcSinkConfig {
+            max_segment_bytes: 1024,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    for tx_id in 1..=30 {
+        let envelope = append_deposit(journal.as_ref(), 1, tx_id).await;
+        publish(&sink, &journal, envelope).await;
+    }
+
+    let manifest = load_manifest(dir.path()).unwrap();
+    assert!(manifest.len() >= 2);
+    assert_eq!(manifest[0].first_sequence, 1);
+    for pair in manifest.windows(2) {
+        assert_eq!(pair[1].first_sequence, pair[0].last_sequence + 1);
+    }
+    for segment in &manifest {
+        let content = std::fs::read(dir.path().join(&segment.file)).unwrap();
+        assert_eq!(content.len() as u64, segment.bytes);
+        assert_eq!(segment.sha256.len(), 64);
+    }
+
+    // Completed segments plus the active one hold every event exactly once
+    let mut tail = CdcTail::open(dir.path(), 0);
+    assert_eq!(drain(&mut tail), (1..=30).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_cdc_sink_fills_gaps_from_the_journal() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let sink = CdcSink::open(dir.path(), CdcSinkConfig::default()).unwrap();
+
+    // Events 1-4 were persisted without reaching the sink (failed or missed callbacks)
+    for tx_id in 1..=4 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+    }
+    let envelope = append_deposit(journal.as_ref(), 2, 5).await;
+    publish(&sink, &journal, envelope.clone()).await;
+    // A redelivery is ignored
+    publish(&sink, &journal, envelope).await;
+
+    assert_eq!(sink.last_sequence().await, 5);
+    let mut tail = CdcTail::open(dir.path(), 0);
+    assert_eq!(drain(&mut tail), vec![1, 2, 3, 4, 5]);
+}
+
+#[tokio::test]
+async fn test_cdc_sink_reports_events_the_journal_no_longer_has() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let sink = CdcSink::open(dir.path(), CdcSinkConfig::default()).unwrap();
+
+    for tx_id in 1..=2 {
+        let envelope = append_deposit(journal.as_ref(), 1, tx_id).await;
+        publish(&sink, &journal, envelope).await;
+    }
+    // Events 3 and 4 never reach the sink and are truncated before it looks for them
+    for tx_id in 3..=4 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+    }
+    journal.truncate(5).await.unwrap();
+
+    // A live event past the gap is not written over it
+    let envelope = append_deposit(journal.as_ref(), 1, 5).await;
+    publish(&sink, &journal, envelope).await;
+    assert_eq!(sink.last_sequence().await, 2);
+
+    assert!(matches!(
+        sink.catch_up(journal.as_ref()).await,
+        Err(PaymentError::Engine(EngineError::HistoryGap {
+            from_sequence: 3,
+            to_sequence: 4,
+        }))
+    ));
+    let mut tail = CdcTail::open(dir.path(), 0);
+    assert_eq!(drain(&mut tail), vec![1, 2]);
+}
+
+#[tokio::test]
+async fn test_cdc_sink_resumes_and_catches_up_after_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+
+    let sink = CdcSink::open(dir.path(), CdcSinkConfig::default()).unwrap();
+    for tx_id in 1..=3 {
+        let envelope = append_deposit(journal.as_ref(), 1, tx_id).await;
+        publish(&sink, &journal, envelope).await;
+    }
+    drop(sink);
+
+    // Persisted while the sink was down
+    for tx_id in 4..=6 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+    }
+
+    let sink = CdcSink::open(dir.path(), CdcSinkConfig::default()).unwrap();
+    assert_eq!(sink.last_sequence().await, 3);
+    assert_eq!(sink.catch_up(journal.as_ref()).await.unwrap(), 6);
+
+    // A consumer resumes from its own position
+    let mut tail = CdcTail::open(dir.path(), 2);
+    assert_eq!(drain(&mut tail), vec![3, 4, 5, 6]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_cdc_tail_follows_live_events_across_rotations() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let sink = Arc::new(
+        CdcSink::open(
+            dir.path(),
+            CdcSinkConfig {
+                max_segment_bytes: 2048,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    );
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    let registry = ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+    .with_callback(sink);
+
+    let consumer = tokio::spawn({
+        let dir = dir.path().to_path_buf();
+        async move {
+            let mut tail =
+                CdcTail::open(dir, 0).with_poll_interval(std::time::Duration::from_millis(5));
+            let mut seen = Vec::new();
+            while seen.len() < 40 {
+                seen.push(tail.next().await.unwrap().sequence_nr);
+            }
+            seen
+        }
+    });
+
+    for tx_id in 1..=40u32 {
+        let client_id = (tx_id % 4) as u16;
+        registry
+            .process_command(
+                client_id,
+                TransactionTypeCommand::Deposit(Deposit {
+                    client_id,
+                    tx_id,
+                    amount: 1.0,
+                }),
+                CommandMetadata {
+                    deduplication_key: DeduplicationKey::new(format!("deposit:{}", tx_id)),
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let seen = tokio::time::timeout(std::time::Duration::from_secs(10), consumer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(seen, (1..=40).collect::<Vec<_>>());
+    assert!(!load_manifest(dir.path()).unwrap().is_empty());
+}
@@ -0,0 +1,263 @@
+use payment::adapter::{
+    ClientKeyCodec, ClientKeyStore, FileJournal, FileJournalConfig, FileSnapshotStore,
+};
+use payment::domain::*;
+use payment::port::{EventHandler, Journal, Snapshotter};
+use payment::service::{erase_client, verify_journal_chain};
+use std::path::Path;
+use std::sync::Arc;
+
+async fn open_journal(dir: &Path) -> (FileJournal, Arc<ClientKeyStore>) {
+    let keys = Arc::new(ClientKeyStore::load(dir.join("keys")).unwrap());
+    let journal = FileJournal::open_with_codec(
+        dir.join("journal"),
+        FileJournalConfig::default(),
+        Arc::new(ClientKeyCodec::new(keys.clone())),
+    )
+    .await
+    .unwrap();
+    (journal, keys)
+}
+
+async fn append_deposit(journal: &FileJournal, client_id: u16, tx_id: u32, amount: f64) {
+    journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id,
+                tx_id,
+                amount,
+            }),
+            EventMetadata {
+                client_id,
+                tx_id,
+                deduplication_key: DeduplicationKey::new(format!(
+                    "deposit:{}:{}",
+                    client_id, tx_id
+                )),
+                timestamp: chrono::Utc::now(),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+fn balance(envelopes: &[EventEnvelope], client_id: u16) -> f64 {
+    let mut state = AccountState::Active(ActiveAccountState {
+        available: 0.0,
+        held: 0.0,
+        total: 0.0,
+        last_activity: chrono::Utc::now(),
+    });
+    for envelope in envelopes.iter().filter(|e| e.client_id == client_id) {
+        state = envelope.apply(&state).unwrap();
+    }
+    match state {
+        AccountState::Active(s) => s.total,
+        AccountState::Frozen(s) => s.total,
+    }
+}
+
+#[tokio::test]
+async fn test_erased_client_replays_as_tombstones() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let (journal, keys) = open_journal(dir.path()).await;
+        append_deposit(&journal, 1, 1, 100.0).await;
+        append_deposit(&journal, 2, 2, 50.0).await;
+        append_deposit(&journal, 1, 3, 25.0).await;
+
+        let report = erase_client(&journal, &keys, None, 1).await.unwrap();
+        assert!(report.key_destroyed);
+        assert_eq!(report.redacted_events, 2);
+
+        // In-memory copies are redacted right away
+        let cached = journal.find_by_tx_id(1).await.unwrap();
+        assert!(matches!(cached[0].event, TransactionTypeEvent::Redacted(_)));
+    }
+
+    let (journal, _keys) = open_journal(dir.path()).await;
+    let envelopes = journal.replay(None).await.unwrap();
+
+    assert_eq!(envelopes.len(), 3);
+    assert!(matches!(
+        envelopes[0].event,
+        TransactionTypeEvent::Redacted(_)
+    ));
+    assert!(matches!(
+        envelopes[1].event,
+        TransactionTypeEvent::Deposited(_)
+    ));
+    assert!(matches!(
+        envelopes[2].event,
+        TransactionTypeEvent::Redacted(_)
+    ));
+
+    assert_eq!(balance(&envelopes, 1), 0.0);
+    assert_eq!(balance(&envelopes, 2), 50.0);
+    assert!(verify_journal_chain(&journal).await.unwrap().is_intact());
+}
+
+#[tokio::test]
+async fn test_returning_client_gets_a_fresh_key() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let (journal, keys) = open_journal(dir.path()).await;
+        append_deposit(&journal, 1, 1, 100.0).await;
+        erase_client(&journal, &keys, None, 1).await.unwrap();
+        append_deposit(&journal, 1, 2, 10.0).await;
+    }
+
+    let (journal, _keys) = open_journal(dir.path()).await;
+    let envelopes = journal.replay(None).await.unwrap();
+
+    assert!(matches!(
+        envelopes[0].event,
+        TransactionTypeEvent::Redacted(_)
+    ));
+    assert!(matches!(
+        envelopes[1].event,
+        TransactionTypeEvent::Deposited(_)
+    ));
+    assert_eq!(balance(&envelopes, 1), 10.0);
+}
+
+#[tokio::test]
+async fn test_client_key_is_written_whole_and_owner_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let (journal, _keys) = open_journal(dir.path()).await;
+    append_deposit(&journal, 1, 1, 100.0).await;
+
+    let key_files: Vec<_> = std::fs::read_dir(dir.path().join("keys"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(
+        key_files,
+        vec![dir.path().join("keys").join("client-1.key")]
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&key_files[0])
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[tokio::test]
+async fn test_erasing_unknown_client_is_a_no_op() {
+    let dir = tempfile::tempdir().unwrap();
+    let (journal, keys) = open_journal(dir.path()).await;
+    append_deposit(&journal, 2, 1, 100.0).await;
+
+    let report = erase_client(&journal, &keys, None, 1).await.unwrap();
+    assert!(!report.key_destroyed);
+    assert_eq!(report.redacted_events, 0);
+}
+
+#[tokio::test]
+async fn test_missing_key_is_an_error_not_an_erasure() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let (journal, _keys) = open_journal(dir.path()).await;
+        append_deposit(&journal, 1, 1, 100.0).await;
+    }
+
+    // Wrong key directory
+    let other_keys = Arc::new(ClientKeyStore::load(dir.path().join("other-keys")).unwrap());
+    let result = FileJournal::open_with_codec(
+        dir.path().join("journal"),
+        FileJournalConfig::default(),
+        Arc::new(ClientKeyCodec::new(other_keys)),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::PersistenceError(_)))
+    ));
+
+    // Key file removed without an erasure record
+    std::fs::remove_file(dir.path().join("keys").join("client-1.key")).unwrap();
+    let keys = Arc::new(ClientKeyStore::load(dir.path().join("keys")).unwrap());
+    let result = FileJournal::open_with_codec(
+        dir.path().join("journal"),
+        FileJournalConfig::default(),
+        Arc::new(ClientKeyCodec::new(keys)),
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_interrupted_erasure_is_finished_not_undone() {
+    let dir = tempfile::tempdir().unwrap();
+    let keys = ClientKeyStore::load(dir.path()).unwrap();
+    let sealed = keys.seal(1, b"balance", b"aad").unwrap();
+    let key_path = dir.path().join("client-1.key");
+    let key_file = std::fs::read(&key_path).unwrap();
+
+    // Crash after the erasure record was written, before the key file was destroyed
+    assert!(keys.erase(1).unwrap());
+    std::fs::write(&key_path, key_file).unwrap();
+
+    let keys = ClientKeyStore::load(dir.path()).unwrap();
+    assert_eq!(keys.open(1, &sealed, b"aad").unwrap(), None);
+    assert!(!key_path.exists());
+}
+
+fn snapshot_store(dir: &Path, keys: Arc<ClientKeyStore>) -> FileSnapshotStore {
+    FileSnapshotStore::new(dir.join("snapshots"))
+        .unwrap()
+        .with_codec(Arc::new(ClientKeyCodec::new(keys)))
+}
+
+fn account(total: f64) -> AccountState {
+    AccountState::Active(ActiveAccountState {
+        available: total,
+        held: 0.0,
+        total,
+        last_activity: chrono::Utc::now(),
+    })
+}
+
+#[tokio::test]
+async fn test_snapshots_are_sealed_and_shredded_with_the_client() {
+    let dir = tempfile::tempdir().unwrap();
+    let (journal, keys) = open_journal(dir.path()).await;
+    append_deposit(&journal, 1, 1, 1234.5).await;
+    let store = snapshot_store(dir.path(), keys.clone());
+    store.save(1, 1, account(1234.5)).await.unwrap();
+
+    for entry in std::fs::read_dir(dir.path().join("snapshots")).unwrap() {
+        let content = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+        assert!(!content.contains("1234.5"));
+        assert!(!content.contains("available"));
+    }
+    let (sequence_nr, state) = store.load(1).await.unwrap().unwrap();
+    assert_eq!(sequence_nr, 1);
+    assert!(matches!(state, AccountState::Active(s) if s.total == 1234.5));
+
+    let report = erase_client(&journal, &keys, Some(&store), 1)
+        .await
+        .unwrap();
+    assert_eq!(report.removed_snapshots, 1);
+    assert!(store.load(1).await.unwrap().is_none());
+    assert!(store.list(1).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_snapshot_of_erased_client_is_not_restored() {
+    let dir = tempfile::tempdir().unwrap();
+    let (journal, keys) = open_journal(dir.path()).await;
+    append_deposit(&journal, 1, 1, 100.0).await;
+    let store = snapshot_store(dir.path(), keys.clone());
+    store.save(1, 1, account(100.0)).await.unwrap();
+
+    // Snapshot directory not passed to the erasure
+    erase_client(&journal, &keys, None, 1).await.unwrap();
+
+    assert_eq!(store.list(1).unwrap().len(), 1);
+    assert!(store.load(1).await.unwrap().is_none());
+}
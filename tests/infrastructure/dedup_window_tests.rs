@@ -0,0 +1,155 @@
+use payment::adapter::{InMemoryJournal, InMemoryJournalConfig};
+use payment::domain::*;
+use payment::port::Journal;
+use payment::service::audit_journal;
+
+fn deposit(
+    tx_id: u32,
+    timestamp: chrono::DateTime<chrono::Utc>,
+) -> (TransactionTypeEvent, EventMetadata) {
+    (
+        TransactionTypeEvent::Deposited(Deposited {
+            client_id: 1,
+            tx_id,
+            amount: 10.0,
+        }),
+        EventMetadata {
+            client_id: 1,
+            tx_id,
+            timestamp,
+            deduplication_key: DeduplicationKey::new(format!("deposit:1:{}", tx_id)),
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_count_window_reports_late_duplicate() {
+    let journal = InMemoryJournal::with_config(InMemoryJournalConfig {
+        deduplication_window: DeduplicationWindow::Count(2),
+        ..Default::default()
+    });
+
+    for tx_id in 1..=3 {
+        let (event, metadata) = deposit(tx_id, chrono::Utc::now());
+        journal.append(event, metadata).await.unwrap();
+    }
+
+    // tx 3 is still inside the window and is deduplicated as before
+    let (event, metadata) = deposit(3, chrono::Utc::now());
+    let outcome = journal.append(event, metadata).await.unwrap();
+    assert!(outcome.is_duplicate());
+    assert_eq!(outcome.envelope().sequence_nr, 3);
+
+    // tx 1 has been evicted: the duplicate is reported rather than applied twice
+    let (event, metadata) = deposit(1, chrono::Utc::now());
+    let result = journal.append(event, metadata).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::DuplicateOutsideWindow(_)))
+    ));
+
+    assert_eq!(journal.replay(None).await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_time_window_expires_old_keys() {
+    let journal = InMemoryJournal::with_config(InMemoryJournalConfig {
+        deduplication_window: DeduplicationWindow::Time(chrono::TimeDelta::hours(1)),
+        ..Default::default()
+    });
+
+    let (event, metadata) = deposit(1, chrono::Utc::now() - chrono::TimeDelta::hours(2));
+    journal.append(event, metadata).await.unwrap();
+    let (event, metadata) = deposit(2, chrono::Utc::now());
+    journal.append(event, metadata).await.unwrap();
+
+    let (event, metadata) = deposit(1, chrono::Utc::now());
+    let result = journal.append(event, metadata).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::DuplicateOutsideWindow(_)))
+    ));
+
+    let (event, metadata) = deposit(2, chrono::Utc::now());
+    let outcome = journal.append(event, metadata).await.unwrap();
+    assert!(outcome.is_duplicate());
+    assert_eq!(outcome.envelope().sequence_nr, 2);
+}
+
+#[tokio::test]
+async fn test_unbounded_window_keeps_every_key() {
+    let journal = InMemoryJournal::new();
+
+    for tx_id in 1..=100 {
+        let (event, metadata) = deposit(tx_id, chrono::Utc::now() - chrono::TimeDelta::days(365));
+        journal.append(event, metadata).await.unwrap();
+    }
+
+    let (event, metadata) = deposit(1, chrono::Utc::now());
+    let outcome = journal.append(event, metadata).await.unwrap();
+    assert!(outcome.is_duplicate());
+    assert_eq!(outcome.envelope().sequence_nr, 1);
+    assert_eq!(journal.replay(None).await.unwrap().len(), 100);
+}
+
+fn client_deposit(client_id: u16, key: &str) -> (TransactionTypeEvent, EventMetadata) {
+    (
+        TransactionTypeEvent::Deposited(Deposited {
+            client_id,
+            tx_id: client_id as u32,
+            amount: 10.0,
+        }),
+        EventMetadata {
+            client_id,
+            tx_id: client_id as u32,
+            timestamp: chrono::Utc::now(),
+            deduplication_key: DeduplicationKey::new(key.to_string()),
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_count_window_spans_every_shard() {
+    let journal = InMemoryJournal::with_config(InMemoryJournalConfig {
+        deduplication_window: DeduplicationWindow::Count(2),
+        shards: 4,
+    });
+
+    // Clients 1, 2 and 3 live in different shards but share the window of two keys
+    for client_id in 1..=3 {
+        let (event, metadata) = client_deposit(client_id, &format!("deposit:{}", client_id));
+        journal.append(event, metadata).await.unwrap();
+    }
+
+    let (event, metadata) = client_deposit(1, "deposit:1");
+    assert!(matches!(
+        journal.append(event, metadata).await,
+        Err(PaymentError::Engine(EngineError::DuplicateOutsideWindow(_)))
+    ));
+    let (event, metadata) = client_deposit(3, "deposit:3");
+    assert!(
+        journal
+            .append(event, metadata)
+            .await
+            .unwrap()
+            .is_duplicate()
+    );
+}
+
+#[tokio::test]
+async fn test_keys_are_scoped_per_client_in_journal_and_audit() {
+    // Clients 1 and 5 share a shard, clients 1 and 2 do not
+    let journal = InMemoryJournal::with_config(InMemoryJournalConfig {
+        shards: 4,
+        ..Default::default()
+    });
+    for client_id in [1, 5, 2] {
+        let (event, metadata) = client_deposit(client_id, "import:batch-7");
+        let outcome = journal.append(event, metadata).await.unwrap();
+        assert!(!outcome.is_duplicate());
+    }
+    assert_eq!(journal.replay(None).await.unwrap().len(), 3);
+
+    let report = audit_journal(&journal, None).await.unwrap();
+    assert!(report.is_consistent(), "{:?}", report.violations);
+}
@@ -0,0 +1,168 @@
+use payment::adapter::{EncryptedCodec, FileJournal, FileJournalConfig, Keyring};
+use payment::domain::*;
+use payment::port::Journal;
+use payment::service::verify_journal_chain;
+use std::path::Path;
+use std::sync::Arc;
+
+async fn open_encrypted(dir: &Path, keyfile: &Path) -> FileJournal {
+    let keyring = Arc::new(Keyring::load(keyfile).unwrap());
+    FileJournal::open_with_codec(
+        dir,
+        FileJournalConfig::default(),
+        Arc::new(EncryptedCodec::new(keyring)),
+    )
+    .await
+    .unwrap()
+}
+
+async fn append_deposit(journal: &FileJournal, tx_id: u32) {
+    journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 7,
+                tx_id,
+                amount: 42.5,
+            }),
+            EventMetadata {
+                client_id: 7,
+                tx_id,
+                deduplication_key: DeduplicationKey::new(format!("deposit:7:{}", tx_id)),
+                timestamp: chrono::Utc::now(),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+fn segment_content(dir: &Path) -> String {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_payloads_are_encrypted_but_metadata_is_plaintext() {
+    let dir = tempfile::tempdir().unwrap();
+    let keyfile = dir.path().join("journal.keys");
+    Keyring::rotate(&keyfile).unwrap();
+
+    let journal_dir = dir.path().join("journal");
+    {
+        let journal = open_encrypted(&journal_dir, &keyfile).await;
+        append_deposit(&journal, 1).await;
+    }
+
+    let content = segment_content(&journal_dir);
+    assert!(!content.contains("Deposited"));
+    assert!(!content.contains("42.5"));
+    assert!(content.contains("\"tx_id\":1"));
+    assert!(content.contains("\"client_id\":7"));
+    assert!(content.contains("\"key_id\":\"k1\""));
+
+    let journal = open_encrypted(&journal_dir, &keyfile).await;
+    let events = journal.find_by_tx_id(1).await.unwrap();
+    match &events[0].event {
+        TransactionTypeEvent::Deposited(deposit) => assert_eq!(deposit.amount, 42.5),
+        _ => panic!("Expected Deposited event"),
+    }
+    assert!(verify_journal_chain(&journal).await.unwrap().is_intact());
+}
+
+#[tokio::test]
+async fn test_records_stay_readable_after_key_rotation() {
+    let dir = tempfile::tempdir().unwrap();
+    let keyfile = dir.path().join("journal.keys");
+    let journal_dir = dir.path().join("journal");
+
+    Keyring::rotate(&keyfile).unwrap();
+    {
+        let journal = open_encrypted(&journal_dir, &keyfile).await;
+        append_deposit(&journal, 1).await;
+    }
+
+    assert_eq!(Keyring::rotate(&keyfile).unwrap(), "k2");
+    {
+        let journal = open_encrypted(&journal_dir, &keyfile).await;
+        append_deposit(&journal, 2).await;
+    }
+
+    let content = segment_content(&journal_dir);
+    assert!(content.contains("\"key_id\":\"k1\""));
+    assert!(content.contains("\"key_id\":\"k2\""));
+
+    let journal = open_encrypted(&journal_dir, &keyfile).await;
+    assert_eq!(journal.replay(None).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_encrypted_journal_requires_the_right_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let keyfile = dir.path().join("journal.keys");
+    let other_keyfile = dir.path().join("other.keys");
+    let journal_dir = dir.path().join("journal");
+
+    Keyring::rotate(&keyfile).unwrap();
+    Keyring::rotate(&other_keyfile).unwrap();
+    {
+        let journal = open_encrypted(&journal_dir, &keyfile).await;
+        append_deposit(&journal, 1).await;
+    }
+
+    assert!(
+        FileJournal::open(&journal_dir, FileJournalConfig::default())
+            .await
+            .is_err()
+    );
+
+    let keyring = Arc::new(Keyring::load(&other_keyfile).unwrap());
+    let result = FileJournal::open_with_codec(
+        &journal_dir,
+        FileJournalConfig::default(),
+        Arc::new(EncryptedCodec::new(keyring)),
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_rotation_follows_the_highest_key_id_and_keeps_the_keyfile_private() {
+    let dir = tempfile::tempdir().unwrap();
+    let keyfile = dir.path().join("journal.keys");
+
+    assert_eq!(Keyring::rotate(&keyfile).unwrap(), "k1");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&keyfile).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // Ids are not contiguous once a key was removed by hand
+    let content = std::fs::read_to_string(&keyfile).unwrap();
+    let key_hex = content.split_whitespace().nth(1).unwrap().to_string();
+    std::fs::write(&keyfile, format!("k3 {}\n", key_hex)).unwrap();
+
+    assert_eq!(Keyring::rotate(&keyfile).unwrap(), "k4");
+    assert_eq!(Keyring::load(&keyfile).unwrap().active_key_id(), "k4");
+}
+
+#[test]
+fn test_malformed_key_hex_is_an_error_not_a_panic() {
+    let dir = tempfile::tempdir().unwrap();
+    let keyfile = dir.path().join("journal.keys");
+
+    // 64 bytes, with a multi-byte character straddling a digit pair
+    std::fs::write(&keyfile, format!("k1 a\u{e9}{}\n", "a".repeat(61))).unwrap();
+    assert!(matches!(
+        Keyring::load(&keyfile),
+        Err(PaymentError::Engine(EngineError::PersistenceError(_)))
+    ));
+
+    // Signs are not hex digits
+    std::fs::write(&keyfile, format!("k1 +a{}\n", "a".repeat(62))).unwrap();
+    assert!(Keyring::load(&keyfile).is_err());
+}
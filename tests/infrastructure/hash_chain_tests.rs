@@ -0,0 +1,196 @@
+use payment::adapter::{FileJournal, FileJournalConfig, InMemoryJournal};
+use payment::domain::*;
+use payment::port::Journal;
+use payment::service::verify_journal_chain;
+
+async fn append_deposits(journal: &(dyn Journal + Send + Sync), count: u32) {
+    for tx_id in 1..=count {
+        journal
+            .append(
+                TransactionTypeEvent::Deposited(Deposited {
+                    client_id: 1,
+                    tx_id,
+                    amount: 100.0,
+                }),
+                EventMetadata {
+                    client_id: 1,
+                    tx_id,
+                    deduplication_key: DeduplicationKey::new(format!("deposit:1:{}", tx_id)),
+                    timestamp: chrono::Utc::now(),
+                },
+            )
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_appended_envelopes_form_a_chain() {
+    let journal = InMemoryJournal::new();
+    append_deposits(&journal, 3).await;
+
+    let events = journal.replay(None).await.unwrap();
+    assert_eq!(events[0].previous_hash, GENESIS_HASH);
+    assert_eq!(events[1].previous_hash, events[0].hash);
+    assert_eq!(events[2].previous_hash, events[1].hash);
+    assert_eq!(verify_chain(&events), Ok(3));
+}
+
+#[tokio::test]
+async fn test_edited_envelope_is_reported() {
+    let journal = InMemoryJournal::new();
+    append_deposits(&journal, 3).await;
+
+    let mut events = journal.replay(None).await.unwrap();
+    if let TransactionTypeEvent::Deposited(deposit) = &mut events[1].event {
+        deposit.amount = 1_000_000.0;
+    }
+
+    let chain_break = verify_chain(&events).unwrap_err();
+    assert_eq!(chain_break.sequence_nr, 2);
+    assert!(matches!(
+        chain_break.reason,
+        ChainBreakReason::ContentMismatch { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_removed_envelope_is_reported() {
+    let journal = InMemoryJournal::new();
+    append_deposits(&journal, 3).await;
+
+    let mut events = journal.replay(None).await.unwrap();
+    events.remove(1);
+
+    let chain_break = verify_chain(&events).unwrap_err();
+    assert_eq!(chain_break.sequence_nr, 3);
+    assert!(matches!(
+        chain_break.reason,
+        ChainBreakReason::BrokenLink { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_tampered_file_journal_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let journal = FileJournal::open(dir.path(), FileJournalConfig::default())
+            .await
+            .unwrap();
+        append_deposits(&journal, 3).await;
+        assert!(verify_journal_chain(&journal).await.unwrap().is_intact());
+    }
+
+    let segment = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .unwrap();
+    let content = std::fs::read_to_string(&segment).unwrap();
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    lines[2] = lines[2].replace("\"amount\":100.0", "\"amount\":5.0");
+    std::fs::write(&segment, lines.join("\n") + "\n").unwrap();
+
+    let journal = FileJournal::open(dir.path(), FileJournalConfig::default())
+        .await
+        .unwrap();
+    let report = verify_journal_chain(&journal).await.unwrap();
+
+    assert_eq!(report.verified, 2);
+    assert_eq!(report.first_break.unwrap().sequence_nr, 3);
+}
+
+fn segment_files(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut segments: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    segments.sort();
+    segments
+}
+
+#[tokio::test]
+async fn test_forged_tombstone_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let journal = FileJournal::open(dir.path(), FileJournalConfig::default())
+            .await
+            .unwrap();
+        append_deposits(&journal, 3).await;
+    }
+
+    // Swap a deposit for a tombstone claiming to be shredded, keeping the stored hash
+    let segment = &segment_files(dir.path())[0];
+    let content = std::fs::read_to_string(segment).unwrap();
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let mut record: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    record["event"] = serde_json::json!({
+        "type": "Redacted",
+        "client_id": 1,
+        "tx_id": 1,
+        "shredded": true
+    });
+    lines[0] = record.to_string();
+    std::fs::write(segment, lines.join("\n") + "\n").unwrap();
+
+    let journal = FileJournal::open(dir.path(), FileJournalConfig::default())
+        .await
+        .unwrap();
+    let chain_break = verify_journal_chain(&journal)
+        .await
+        .unwrap()
+        .first_break
+        .unwrap();
+
+    assert_eq!(chain_break.sequence_nr, 1);
+    assert!(matches!(
+        chain_break.reason,
+        ChainBreakReason::ContentMismatch { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_deleted_leading_segment_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = FileJournalConfig {
+        segment_size: 2,
+        ..Default::default()
+    };
+    {
+        let journal = FileJournal::open(dir.path(), config).await.unwrap();
+        append_deposits(&journal, 4).await;
+    }
+
+    std::fs::remove_file(&segment_files(dir.path())[0]).unwrap();
+
+    let journal = FileJournal::open(dir.path(), config).await.unwrap();
+    let chain_break = verify_journal_chain(&journal)
+        .await
+        .unwrap()
+        .first_break
+        .unwrap();
+
+    assert_eq!(chain_break.sequence_nr, 3);
+    assert_eq!(
+        chain_break.reason,
+        ChainBreakReason::MissingHistory {
+            expected_sequence: 1
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_compacted_journal_continues_its_anchor() {
+    let journal = InMemoryJournal::new();
+    append_deposits(&journal, 4).await;
+    journal.truncate(3).await.unwrap();
+
+    let report = verify_journal_chain(&journal).await.unwrap();
+    assert!(report.is_intact());
+    assert_eq!(report.verified, 2);
+
+    // Without the anchor the same events no longer prove where they start
+    let events = journal.replay(None).await.unwrap();
+    assert!(verify_chain(&events).is_err());
+}
@@ -1,4 +1,4 @@
-use payment::adapter::InMemoryJournal;
+use payment::adapter::{FileJournal, FileJournalConfig, InMemoryJournal};
 use payment::domain::*;
 use payment::port::Journal;
 use std::sync::Arc;
@@ -26,7 +26,8 @@ async fn test_duplicate_deduplication_key_returns_existing_event() {
             metadata1,
         )
         .await
-        .unwrap();
+        .unwrap()
+        .into_envelope();
 
     let metadata2 = EventMetadata {
         client_id: 1,
@@ -35,17 +36,19 @@ async fn test_duplicate_deduplication_key_returns_existing_event() {
         deduplication_key: dedup_key.clone(),
     };
 
-    let envelope2 = journal
+    let outcome = journal
         .append(
             TransactionTypeEvent::Deposited(Deposited {
                 client_id: 1,
                 tx_id: 1,
-                amount: 200.0, // Different amount, but should return original
+                amount: 100.0,
             }),
             metadata2,
         )
         .await
         .unwrap();
+    assert!(outcome.is_duplicate());
+    let envelope2 = outcome.into_envelope();
 
     assert_eq!(envelope1.sequence_nr, envelope2.sequence_nr);
     assert_eq!(envelope1.tx_id, envelope2.tx_id);
@@ -53,7 +56,7 @@ async fn test_duplicate_deduplication_key_returns_existing_event() {
     match (&envelope1.event, &envelope2.event) {
         (TransactionTypeEvent::Deposited(d1), TransactionTypeEvent::Deposited(d2)) => {
             assert_eq!(d1.amount, 100.0);
-            assert_eq!(d2.amount, 100.0); // Original amount, not 200.0
+            assert_eq!(d2.amount, 100.0);
         }
         _ => panic!("Expected Deposited events"),
     }
@@ -179,7 +182,8 @@ async fn test_idempotency_across_transaction_types() {
             metadata1,
         )
         .await
-        .unwrap();
+        .unwrap()
+        .into_envelope();
 
     let metadata2 = EventMetadata {
         client_id: 1,
@@ -188,7 +192,7 @@ async fn test_idempotency_across_transaction_types() {
         deduplication_key: dedup_key.clone(),
     };
 
-    let envelope2 = journal
+    let outcome = journal
         .append(
             TransactionTypeEvent::Deposited(Deposited {
                 client_id: 1,
@@ -199,6 +203,8 @@ async fn test_idempotency_across_transaction_types() {
         )
         .await
         .unwrap();
+    assert!(outcome.is_duplicate());
+    let envelope2 = outcome.into_envelope();
 
     assert_eq!(envelope1.sequence_nr, envelope2.sequence_nr);
 
@@ -206,3 +212,75 @@ async fn test_idempotency_across_transaction_types() {
     let events = journal.replay(None).await.unwrap();
     assert_eq!(events.len(), 1);
 }
+
+fn deposit(amount: f64) -> (TransactionTypeEvent, EventMetadata) {
+    (
+        TransactionTypeEvent::Deposited(Deposited {
+            client_id: 1,
+            tx_id: 1,
+            amount,
+        }),
+        EventMetadata {
+            client_id: 1,
+            tx_id: 1,
+            timestamp: chrono::Utc::now(),
+            deduplication_key: DeduplicationKey::new("deposit:1:1".to_string()),
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_reused_key_with_different_payload_is_rejected() {
+    let journal = InMemoryJournal::new();
+
+    let (event, metadata) = deposit(100.0);
+    journal.append(event, metadata).await.unwrap();
+
+    let (event, metadata) = deposit(200.0);
+    let result = journal.append(event, metadata).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::IdempotencyConflict(key))) if key == "deposit:1:1"
+    ));
+
+    // The original is untouched and an identical redelivery is still a duplicate
+    assert_eq!(journal.replay(None).await.unwrap().len(), 1);
+    let (event, metadata) = deposit(100.0);
+    assert!(
+        journal
+            .append(event, metadata)
+            .await
+            .unwrap()
+            .is_duplicate()
+    );
+}
+
+#[tokio::test]
+async fn test_payload_fingerprints_survive_reopening_file_journal() {
+    let dir = tempfile::tempdir().unwrap();
+
+    {
+        let journal = FileJournal::open(dir.path(), FileJournalConfig::default())
+            .await
+            .unwrap();
+        let (event, metadata) = deposit(100.0);
+        journal.append(event, metadata).await.unwrap();
+    }
+
+    let journal = FileJournal::open(dir.path(), FileJournalConfig::default())
+        .await
+        .unwrap();
+    let (event, metadata) = deposit(200.0);
+    assert!(matches!(
+        journal.append(event, metadata).await,
+        Err(PaymentError::Engine(EngineError::IdempotencyConflict(_)))
+    ));
+    let (event, metadata) = deposit(100.0);
+    assert!(
+        journal
+            .append(event, metadata)
+            .await
+            .unwrap()
+            .is_duplicate()
+    );
+}
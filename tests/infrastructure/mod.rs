@@ -1,3 +1,17 @@
 mod ordering_tests;
 mod idempotency_tests;
-
+mod retention_tests;
+mod hash_chain_tests;
+mod encryption_tests;
+mod crypto_shredding_tests;
+mod dedup_window_tests;
+mod arena_tests;
+mod sharding_tests;
+mod transfer_tests;
+mod audit_tests;
+mod query_tests;
+mod subscription_tests;
+mod cdc_tests;
+mod replication_tests;
+mod supervision_tests;
+mod retry_tests;
@@ -38,7 +38,8 @@ async fn test_events_applied_in_sequence_order() {
             metadata1,
         )
         .await
-        .unwrap();
+        .unwrap()
+        .into_envelope();
 
     let envelope2 = journal
         .append(
@@ -50,7 +51,8 @@ async fn test_events_applied_in_sequence_order() {
             metadata2,
         )
         .await
-        .unwrap();
+        .unwrap()
+        .into_envelope();
 
     let envelope3 = journal
         .append(
@@ -62,7 +64,8 @@ async fn test_events_applied_in_sequence_order() {
             metadata3,
         )
         .await
-        .unwrap();
+        .unwrap()
+        .into_envelope();
 
     assert_eq!(envelope1.sequence_nr, 1);
     assert_eq!(envelope2.sequence_nr, 2);
@@ -0,0 +1,120 @@
+use chrono::{TimeDelta, Utc};
+use payment::adapter::InMemoryJournal;
+use payment::domain::*;
+use payment::port::Journal;
+
+/// 20 events over 20 days: clients 1..=4, every 5th event a chargeback, the rest deposits
+async fn journal() -> InMemoryJournal {
+    let journal = InMemoryJournal::new();
+    let start = Utc::now() - TimeDelta::days(20);
+
+    for i in 1..=20u32 {
+        let client_id = (i % 4) as u16 + 1;
+        let event = if i % 5 == 0 {
+            TransactionTypeEvent::Chargebacked(Chargebacked {
+                client_id,
+                tx_id: i,
+                amount: 1.0,
+            })
+        } else {
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id,
+                tx_id: i,
+                amount: 1.0,
+            })
+        };
+        journal
+            .append(
+                event,
+                EventMetadata {
+                    client_id,
+                    tx_id: i,
+                    timestamp: start + TimeDelta::days(i as i64),
+                    deduplication_key: DeduplicationKey::new(format!("test:{}", i)),
+                },
+            )
+            .await
+            .unwrap();
+    }
+    journal
+}
+
+fn sequences(page: &JournalPage) -> Vec<u64> {
+    page.envelopes.iter().map(|e| e.sequence_nr).collect()
+}
+
+#[tokio::test]
+async fn test_query_chargebacks_in_time_range() {
+    let journal = journal().await;
+
+    // "All chargebacks of the last week"
+    let page = journal
+        .query(&JournalQuery {
+            kinds: vec![EventKind::Chargebacked],
+            from_timestamp: Some(Utc::now() - TimeDelta::days(7)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(sequences(&page), vec![15, 20]);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[tokio::test]
+async fn test_query_by_client_and_sequence_range() {
+    let journal = journal().await;
+
+    let page = journal
+        .query(&JournalQuery {
+            client_id: Some(2),
+            from_sequence: Some(5),
+            to_sequence: Some(17),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(sequences(&page), vec![5, 9, 13, 17]);
+    assert!(page.envelopes.iter().all(|e| e.client_id == 2));
+}
+
+#[tokio::test]
+async fn test_query_pages_with_cursor_across_shards() {
+    let journal = journal().await;
+    let mut query = JournalQuery {
+        kinds: vec![EventKind::Deposited],
+        limit: 6,
+        ..Default::default()
+    };
+
+    let mut seen = Vec::new();
+    loop {
+        let page = journal.query(&query).await.unwrap();
+        seen.extend(sequences(&page));
+        match page.next_cursor {
+            Some(cursor) => query.after = Some(cursor),
+            None => break,
+        }
+    }
+
+    let expected: Vec<u64> = (1..=20).filter(|i| i % 5 != 0).collect();
+    assert_eq!(seen, expected);
+}
+
+#[tokio::test]
+async fn test_query_sees_redacted_events_as_redacted() {
+    let journal = journal().await;
+    journal.redact_client(1).await;
+
+    let query = |kind| JournalQuery {
+        client_id: Some(1),
+        kinds: vec![kind],
+        ..Default::default()
+    };
+    let redacted = journal.query(&query(EventKind::Redacted)).await.unwrap();
+    let deposited = journal.query(&query(EventKind::Deposited)).await.unwrap();
+
+    assert_eq!(sequences(&redacted), vec![4, 8, 12, 16, 20]);
+    assert!(deposited.envelopes.is_empty());
+}
@@ -0,0 +1,359 @@
+use payment::adapter::{
+    ClientRegistry, FileJournal, FileJournalConfig, InMemoryDisputeIndex, InMemoryJournal,
+    ReplicaJournal, ReplicationFollower, ReplicationLeader, ReplicationRole, SubscriptionHub,
+};
+use payment::domain::*;
+use payment::port::{DisputeIndex, Journal};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+async fn append_deposit(journal: &dyn Journal, client_id: u16, tx_id: u32) {
+    journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id,
+                tx_id,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id,
+                tx_id,
+                timestamp: chrono::Utc::now(),
+                deduplication_key: DeduplicationKey::new(format!("deposit:{}", tx_id)),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+struct Leader {
+    journal: Arc<dyn Journal + Send + Sync>,
+    registry: ClientRegistry,
+    replication: ReplicationLeader,
+}
+
+async fn leader() -> Leader {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let hub = Arc::new(SubscriptionHub::new(journal.clone(), 64));
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    let registry = ClientRegistry::with_namespace(
+        journal.clone(),
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+    .with_callback(hub.publisher());
+    let replication = ReplicationLeader::bind("127.0.0.1:0", hub).await.unwrap();
+
+    Leader {
+        journal,
+        registry,
+        replication,
+    }
+}
+
+async fn deposit(registry: &ClientRegistry, client_id: u16, tx_id: u32) {
+    registry
+        .process_command(
+            client_id,
+            TransactionTypeCommand::Deposit(Deposit {
+                client_id,
+                tx_id,
+                amount: 10.0,
+            }),
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new(format!("deposit:{}", tx_id)),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_follower_replicates_history_and_live_events() {
+    let leader = leader().await;
+    for tx_id in 1..=10 {
+        append_deposit(leader.journal.as_ref(), 1, tx_id).await;
+    }
+
+    let replica = Arc::new(ReplicaJournal::new(Arc::new(InMemoryJournal::new())));
+    let mut follower = ReplicationFollower::start(
+        leader.replication.local_addr().to_string(),
+        replica.clone(),
+        Duration::from_millis(20),
+    );
+    follower.wait_for(10).await.unwrap();
+
+    for tx_id in 11..=30 {
+        deposit(&leader.registry, (tx_id % 3) as u16, tx_id).await;
+    }
+    follower.wait_for(30).await.unwrap();
+    assert!(
+        leader
+            .replication
+            .wait_for_ack(30, Duration::from_secs(5))
+            .await
+    );
+
+    let expected = leader.journal.replay(None).await.unwrap();
+    let replicated = replica.replay(None).await.unwrap();
+    assert_eq!(replicated.len(), 30);
+    for (expected, replicated) in expected.iter().zip(&replicated) {
+        assert_eq!(expected.sequence_nr, replicated.sequence_nr);
+        assert_eq!(expected.hash, replicated.hash);
+    }
+    assert_eq!(verify_chain(replicated.iter()), Ok(30));
+}
+
+#[tokio::test]
+async fn test_follower_rejects_writes_until_promoted() {
+    let leader = leader().await;
+    for tx_id in 1..=5 {
+        append_deposit(leader.journal.as_ref(), 1, tx_id).await;
+    }
+
+    let replica = Arc::new(ReplicaJournal::new(Arc::new(InMemoryJournal::new())));
+    let mut follower = ReplicationFollower::start(
+        leader.replication.local_addr().to_string(),
+        replica.clone(),
+        Duration::from_millis(20),
+    );
+    follower.wait_for(5).await.unwrap();
+
+    assert_eq!(replica.role(), ReplicationRole::Follower);
+    let rejected = replica
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 1,
+                tx_id: 99,
+                amount: 1.0,
+            }),
+            EventMetadata {
+                client_id: 1,
+                tx_id: 99,
+                timestamp: chrono::Utc::now(),
+                deduplication_key: DeduplicationKey::new("deposit:99".to_string()),
+            },
+        )
+        .await;
+    assert!(matches!(
+        rejected,
+        Err(PaymentError::Engine(EngineError::ReadOnlyReplica(_)))
+    ));
+
+    // Failover: the leader goes away and the follower takes over the sequence
+    drop(leader);
+    assert_eq!(follower.promote().await.unwrap(), 5);
+    assert_eq!(replica.role(), ReplicationRole::Leader);
+    append_deposit(replica.as_ref(), 1, 6).await;
+
+    let events = replica.replay(None).await.unwrap();
+    assert_eq!(events.last().unwrap().sequence_nr, 6);
+    assert_eq!(verify_chain(events.iter()), Ok(6));
+}
+
+#[tokio::test]
+async fn test_follower_resumes_from_its_own_position() {
+    let leader = leader().await;
+    for tx_id in 1..=5 {
+        append_deposit(leader.journal.as_ref(), 1, tx_id).await;
+    }
+
+    let replica = Arc::new(ReplicaJournal::new(Arc::new(InMemoryJournal::new())));
+    let mut follower = ReplicationFollower::start(
+        leader.replication.local_addr().to_string(),
+        replica.clone(),
+        Duration::from_millis(20),
+    );
+    follower.wait_for(5).await.unwrap();
+    follower.promote().await.unwrap();
+
+    // A new session announces the replica's position and only receives what it misses
+    for tx_id in 6..=8 {
+        append_deposit(leader.journal.as_ref(), 1, tx_id).await;
+    }
+    let mut follower = ReplicationFollower::start(
+        leader.replication.local_addr().to_string(),
+        replica.clone(),
+        Duration::from_millis(20),
+    );
+    follower.wait_for(8).await.unwrap();
+    assert_eq!(replica.replay(Some(6)).await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_follower_stops_on_diverged_leader_history() {
+    let leader = leader().await;
+    for tx_id in 1..=5 {
+        append_deposit(leader.journal.as_ref(), 1, tx_id).await;
+    }
+
+    let replica = Arc::new(ReplicaJournal::new(Arc::new(InMemoryJournal::new())));
+    let mut follower = ReplicationFollower::start(
+        leader.replication.local_addr().to_string(),
+        replica.clone(),
+        Duration::from_millis(20),
+    );
+    follower.wait_for(5).await.unwrap();
+    follower.promote().await.unwrap();
+
+    // Both sides write their own sequence 6, so the leader's 7 does not link to the replica
+    append_deposit(replica.as_ref(), 2, 100).await;
+    for tx_id in 6..=8 {
+        append_deposit(leader.journal.as_ref(), 1, tx_id).await;
+    }
+    let mut follower = ReplicationFollower::start(
+        leader.replication.local_addr().to_string(),
+        replica.clone(),
+        Duration::from_millis(20),
+    );
+    let failure = tokio::time::timeout(Duration::from_secs(5), follower.failed())
+        .await
+        .unwrap();
+    assert!(matches!(
+        failure,
+        PaymentError::Engine(EngineError::HistoryConflict(_))
+    ));
+    assert!(matches!(
+        follower.wait_for(7).await,
+        Err(PaymentError::Engine(EngineError::HistoryConflict(_)))
+    ));
+    assert_eq!(replica.highest_sequence().await.unwrap(), Some(6));
+}
+
+#[tokio::test]
+async fn test_follower_reports_history_the_leader_archived() {
+    let leader = leader().await;
+    for tx_id in 1..=10 {
+        append_deposit(leader.journal.as_ref(), 1, tx_id).await;
+    }
+    leader.journal.truncate(6).await.unwrap();
+
+    let replica = Arc::new(ReplicaJournal::new(Arc::new(InMemoryJournal::new())));
+    let mut follower = ReplicationFollower::start(
+        leader.replication.local_addr().to_string(),
+        replica.clone(),
+        Duration::from_millis(20),
+    );
+    let failure = tokio::time::timeout(Duration::from_secs(5), follower.failed())
+        .await
+        .unwrap();
+    assert!(matches!(
+        failure,
+        PaymentError::Engine(EngineError::HistoryGap {
+            from_sequence: 1,
+            to_sequence: 5,
+        })
+    ));
+    assert_eq!(replica.highest_sequence().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_follower_rejects_envelope_whose_content_does_not_match_its_hash() {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    // A correctly linked envelope whose amount was altered after hashing
+    let source = InMemoryJournal::new();
+    append_deposit(&source, 1, 1).await;
+    let mut envelope = source.replay(None).await.unwrap().remove(0);
+    envelope.event = TransactionTypeEvent::Deposited(Deposited {
+        client_id: 1,
+        tx_id: 1,
+        amount: 1_000_000.0,
+    });
+
+    // Leader speaking the wire protocol directly, so it can send what a real one never would
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut hello = String::new();
+        BufReader::new(reader).read_line(&mut hello).await.unwrap();
+        let message = serde_json::json!({ "type": "event", "envelope": envelope });
+        writer
+            .write_all(format!("{}\n", message).as_bytes())
+            .await
+            .unwrap();
+        // Keep the connection open until the follower gives up
+        std::future::pending::<()>().await;
+    });
+
+    let replica = Arc::new(ReplicaJournal::new(Arc::new(InMemoryJournal::new())));
+    let mut follower =
+        ReplicationFollower::start(addr.to_string(), replica.clone(), Duration::from_millis(20));
+    let failure = tokio::time::timeout(Duration::from_secs(5), follower.failed())
+        .await
+        .unwrap();
+    assert!(matches!(
+        failure,
+        PaymentError::Engine(EngineError::HistoryConflict(_))
+    ));
+    assert_eq!(replica.highest_sequence().await.unwrap(), None);
+}
+
+/// Leader and follower as two processes of the binary, on one machine
+#[tokio::test]
+async fn test_replication_between_two_processes() {
+    let dir = tempfile::tempdir().unwrap();
+    let leader_dir = dir.path().join("leader");
+    let follower_dir = dir.path().join("follower");
+    let csv = dir.path().join("transactions.csv");
+    std::fs::write(
+        &csv,
+        "type,client,tx,amount\n\
+         deposit,1,1,100.0\n\
+         deposit,2,2,50.0\n\
+         withdrawal,1,3,20.0\n\
+         dispute,2,2,\n\
+         chargeback,2,2,\n",
+    )
+    .unwrap();
+
+    // Reserve a free port for the leader
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+
+    let binary = env!("CARGO_BIN_EXE_payment");
+    let mut follower = Command::new(binary)
+        .args(["journal", "--dir"])
+        .arg(&follower_dir)
+        .args(["follow", &addr])
+        .spawn()
+        .unwrap();
+
+    let leader = Command::new(binary)
+        .arg("--journal-dir")
+        .arg(&leader_dir)
+        .args(["--replicate-listen", &addr])
+        .arg(&csv)
+        .output()
+        .unwrap();
+    follower.kill().unwrap();
+    follower.wait().unwrap();
+
+    assert!(leader.status.success());
+    // The leader only exits early once a follower acknowledged its last event
+    assert!(!String::from_utf8_lossy(&leader.stderr).contains("no follower acknowledged"));
+
+    let expected = FileJournal::open(&leader_dir, FileJournalConfig::default())
+        .await
+        .unwrap()
+        .replay(None)
+        .await
+        .unwrap();
+    let replicated = FileJournal::open(&follower_dir, FileJournalConfig::default())
+        .await
+        .unwrap()
+        .replay(None)
+        .await
+        .unwrap();
+    assert_eq!(expected.len(), 5);
+    assert_eq!(
+        expected.iter().map(|e| &e.hash).collect::<Vec<_>>(),
+        replicated.iter().map(|e| &e.hash).collect::<Vec<_>>()
+    );
+}
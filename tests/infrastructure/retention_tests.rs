@@ -0,0 +1,404 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use payment::adapter::{FileJournal, FileJournalConfig, FileSnapshotStore, JsonCodec};
+use payment::domain::*;
+use payment::port::{EnvelopeCodec, Journal, Snapshotter};
+use payment::service::snapshot_floor;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Codec that cannot write transaction `UNWRITABLE_TX_ID`
+struct FailingCodec;
+
+const UNWRITABLE_TX_ID: u32 = 3;
+
+impl EnvelopeCodec for FailingCodec {
+    fn encode(&self, envelope: &EventEnvelope) -> Result<String, PaymentError> {
+        if envelope.tx_id == UNWRITABLE_TX_ID {
+            return Err(PaymentError::Engine(EngineError::PersistenceError(
+                "disk full".to_string(),
+            )));
+        }
+        JsonCodec.encode(envelope)
+    }
+
+    fn decode(&self, record: &str) -> Result<EventEnvelope, PaymentError> {
+        JsonCodec.decode(record)
+    }
+}
+
+async fn append_deposit(journal: &FileJournal, tx_id: u32, timestamp: DateTime<Utc>) {
+    journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 1,
+                tx_id,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id: 1,
+                tx_id,
+                deduplication_key: DeduplicationKey::new(format!("deposit:1:{}", tx_id)),
+                timestamp,
+            },
+        )
+        .await
+        .unwrap();
+}
+
+fn config() -> FileJournalConfig {
+    FileJournalConfig {
+        segment_size: 2,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_file_journal_survives_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+
+    {
+        let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+        for tx_id in 1..=5 {
+            append_deposit(&journal, tx_id, Utc::now()).await;
+        }
+    }
+
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+    assert_eq!(journal.highest_sequence().await.unwrap(), Some(5));
+    assert_eq!(journal.find_by_tx_id(3).await.unwrap().len(), 1);
+
+    // Deduplication keys are recovered from the segments as well
+    append_deposit(&journal, 5, Utc::now()).await;
+    assert_eq!(journal.replay(None).await.unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn test_reopen_drops_torn_last_record() {
+    let dir = tempfile::tempdir().unwrap();
+
+    {
+        let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+        for tx_id in 1..=3 {
+            append_deposit(&journal, tx_id, Utc::now()).await;
+        }
+    }
+
+    // A crash halfway through writing the fourth record
+    let newest = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .max()
+        .unwrap();
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&newest)
+        .unwrap();
+    file.write_all(br#"{"sequence_nr":4,"event":{"type":"Depos"#)
+        .unwrap();
+    drop(file);
+
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+    assert_eq!(journal.highest_sequence().await.unwrap(), Some(3));
+
+    // The next append takes the torn record's place and survives another reopen
+    append_deposit(&journal, 4, Utc::now()).await;
+    drop(journal);
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+    let sequences: Vec<_> = journal
+        .replay(None)
+        .await
+        .unwrap()
+        .iter()
+        .map(|e| (e.sequence_nr, e.tx_id))
+        .collect();
+    assert_eq!(sequences, vec![(1, 1), (2, 2), (3, 3), (4, 4)]);
+}
+
+#[tokio::test]
+async fn test_compaction_archives_segments_below_snapshot_floor() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+    let old = Utc::now() - TimeDelta::days(365);
+
+    for tx_id in 1..=5 {
+        append_deposit(&journal, tx_id, old).await;
+    }
+
+    // Segments: [1,2] [3,4] [5]; only the first is fully covered by the snapshot at 3
+    let report = journal
+        .compact(&RetentionPolicy::default(), 3)
+        .await
+        .unwrap();
+
+    assert_eq!(report.archived_segments, 1);
+    assert_eq!(report.truncated_events, 2);
+    assert_eq!(report.hot_from_sequence, Some(3));
+    assert!(journal.find_by_tx_id(1).await.unwrap().is_empty());
+    assert_eq!(journal.find_by_tx_id(3).await.unwrap().len(), 1);
+    assert_eq!(journal.archive().list().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_snapshot_floor_is_the_oldest_snapshot_any_client_recovers_from() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = FileJournal::open(dir.path().join("journal"), config())
+        .await
+        .unwrap();
+    let snapshots = FileSnapshotStore::new(dir.path().join("snapshots"))
+        .unwrap()
+        .with_retention(2);
+    assert_eq!(snapshot_floor(&journal, &snapshots).await.unwrap(), 0);
+
+    for tx_id in 1..=3 {
+        append_deposit(&journal, tx_id, Utc::now()).await;
+    }
+    journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 2,
+                tx_id: 4,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id: 2,
+                tx_id: 4,
+                deduplication_key: DeduplicationKey::new("deposit:2:4".to_string()),
+                timestamp: Utc::now(),
+            },
+        )
+        .await
+        .unwrap();
+    append_deposit(&journal, 5, Utc::now()).await;
+
+    // Client 2 has no snapshot and replays from its first event
+    snapshots.save(1, 5, AccountState::empty()).await.unwrap();
+    assert_eq!(snapshot_floor(&journal, &snapshots).await.unwrap(), 3);
+
+    // Client 1 may still fall back to its older snapshot
+    snapshots.save(1, 2, AccountState::empty()).await.unwrap();
+    snapshots.save(2, 4, AccountState::empty()).await.unwrap();
+    assert_eq!(snapshot_floor(&journal, &snapshots).await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn test_compaction_keeps_events_within_dispute_horizon() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+
+    for tx_id in 1..=4 {
+        append_deposit(&journal, tx_id, Utc::now()).await;
+    }
+
+    let report = journal
+        .compact(&RetentionPolicy::default(), 4)
+        .await
+        .unwrap();
+
+    assert_eq!(report.archived_segments, 0);
+    assert_eq!(journal.find_by_tx_id(1).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_archived_segment_can_be_restored() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+    let old = Utc::now() - TimeDelta::days(365);
+
+    for tx_id in 1..=4 {
+        append_deposit(&journal, tx_id, old).await;
+    }
+    journal
+        .compact(&RetentionPolicy::default(), 4)
+        .await
+        .unwrap();
+    assert!(journal.replay(None).await.unwrap().is_empty());
+
+    let archives = journal.archive().list().unwrap();
+    assert_eq!(archives.len(), 2);
+    assert_eq!(journal.archive().read(&archives[0]).unwrap().len(), 2);
+
+    for archive in &archives {
+        journal.restore_archive(archive).await.unwrap();
+    }
+
+    let events = journal.replay(None).await.unwrap();
+    let sequences: Vec<_> = events.iter().map(|e| e.sequence_nr).collect();
+    assert_eq!(sequences, vec![1, 2, 3, 4]);
+    assert_eq!(journal.find_by_tx_id(1).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_reopen_after_archiving_every_segment_continues_the_chain() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = Utc::now() - TimeDelta::days(365);
+
+    let head = {
+        let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+        for tx_id in 1..=4 {
+            append_deposit(&journal, tx_id, old).await;
+        }
+        journal
+            .compact(&RetentionPolicy::default(), 4)
+            .await
+            .unwrap();
+        journal.anchor().await.unwrap().unwrap()
+    };
+    assert_eq!(head.sequence_nr, 4);
+
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+    assert_eq!(journal.highest_sequence().await.unwrap(), Some(4));
+
+    // A redelivered command whose event was archived is still recognized
+    let redelivered = journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 1,
+                tx_id: 1,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id: 1,
+                tx_id: 1,
+                deduplication_key: DeduplicationKey::new("deposit:1:1".to_string()),
+                timestamp: Utc::now(),
+            },
+        )
+        .await;
+    assert!(matches!(
+        redelivered,
+        Err(PaymentError::Engine(EngineError::DuplicateOutsideWindow(_)))
+    ));
+
+    append_deposit(&journal, 5, Utc::now()).await;
+    let events = journal.replay(None).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].sequence_nr, 5);
+    assert_eq!(events[0].previous_hash, head.hash);
+}
+
+#[tokio::test]
+async fn test_truncation_survives_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+
+    {
+        let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+        for tx_id in 1..=5 {
+            append_deposit(&journal, tx_id, Utc::now()).await;
+        }
+        // Drops the first segment and half of the second one
+        assert_eq!(journal.truncate(4).await.unwrap(), 3);
+    }
+
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+    let sequences: Vec<_> = journal
+        .replay(None)
+        .await
+        .unwrap()
+        .iter()
+        .map(|e| e.sequence_nr)
+        .collect();
+    assert_eq!(sequences, vec![4, 5]);
+    assert_eq!(journal.anchor().await.unwrap().unwrap().sequence_nr, 3);
+    assert!(journal.find_by_tx_id(1).await.unwrap().is_empty());
+
+    // Truncated commands are still recognized, and new appends continue the chain
+    let redelivered = journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 1,
+                tx_id: 2,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id: 1,
+                tx_id: 2,
+                deduplication_key: DeduplicationKey::new("deposit:1:2".to_string()),
+                timestamp: Utc::now(),
+            },
+        )
+        .await;
+    assert!(matches!(
+        redelivered,
+        Err(PaymentError::Engine(EngineError::DuplicateOutsideWindow(_)))
+    ));
+    append_deposit(&journal, 6, Utc::now()).await;
+    assert_eq!(journal.highest_sequence().await.unwrap(), Some(6));
+}
+
+#[tokio::test]
+async fn test_failed_write_leaves_no_trace_in_the_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = FileJournal::open_with_codec(dir.path(), config(), Arc::new(FailingCodec))
+        .await
+        .unwrap();
+
+    for tx_id in 1..=2 {
+        append_deposit(&journal, tx_id, Utc::now()).await;
+    }
+    let failed = journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 1,
+                tx_id: UNWRITABLE_TX_ID,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id: 1,
+                tx_id: UNWRITABLE_TX_ID,
+                deduplication_key: DeduplicationKey::new("deposit:1:3".to_string()),
+                timestamp: Utc::now(),
+            },
+        )
+        .await;
+    assert!(failed.is_err());
+
+    // Neither served, nor deduplicated against, nor holding on to its sequence number
+    assert_eq!(journal.highest_sequence().await.unwrap(), Some(2));
+    assert!(
+        journal
+            .find_by_tx_id(UNWRITABLE_TX_ID)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    append_deposit(&journal, 4, Utc::now()).await;
+
+    drop(journal);
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+    let sequences: Vec<_> = journal
+        .replay(None)
+        .await
+        .unwrap()
+        .iter()
+        .map(|e| (e.sequence_nr, e.tx_id))
+        .collect();
+    assert_eq!(sequences, vec![(1, 1), (2, 2), (3, 4)]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_dropped_appends_leave_disk_and_index_in_step() {
+    let dir = tempfile::tempdir().unwrap();
+    let now = Utc::now();
+
+    let appended = {
+        let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+        // Each append is polled once and dropped, typically while its record is written
+        for tx_id in 1..=20 {
+            let _ = tokio::time::timeout(
+                std::time::Duration::ZERO,
+                append_deposit(&journal, tx_id, now),
+            )
+            .await;
+        }
+        append_deposit(&journal, 21, now).await;
+        let envelopes = journal.replay(None).await.unwrap();
+        let sequences: Vec<u64> = envelopes.iter().map(|e| e.sequence_nr).collect();
+        assert_eq!(sequences, (1..=envelopes.len() as u64).collect::<Vec<_>>());
+        envelopes.len()
+    };
+
+    // Nothing reached the disk that the index did not see, so no sequence was reissued
+    let journal = FileJournal::open(dir.path(), config()).await.unwrap();
+    assert_eq!(journal.replay(None).await.unwrap().len(), appended);
+}
@@ -0,0 +1,246 @@
+use async_trait::async_trait;
+use payment::adapter::{
+    CallTimeouts, ClientRegistry, InMemoryDisputeIndex, InMemoryJournal, RetryPolicy,
+};
+use payment::domain::*;
+use payment::port::{CallbackContext, DisputeIndex, EventCallback, Journal, Snapshotter};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Holds up the first deposit it is notified about
+#[derive(Default)]
+struct SlowFirstDeposit(AtomicBool);
+
+#[async_trait]
+impl EventCallback for SlowFirstDeposit {
+    async fn on_deposited(
+        &self,
+        _event: &Deposited,
+        _ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        if !self.0.swap(true, Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        Ok(())
+    }
+}
+
+/// Holds up the first withdrawal it is notified about, after it was journaled
+#[derive(Default)]
+struct SlowFirstWithdrawal(AtomicBool);
+
+#[async_trait]
+impl EventCallback for SlowFirstWithdrawal {
+    async fn on_withdrawn(
+        &self,
+        _event: &Withdrawn,
+        _ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        if !self.0.swap(true, Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        Ok(())
+    }
+}
+
+/// Takes 200 ms to find that there is no snapshot, slowing down every recovery
+struct SlowSnapshots;
+
+#[async_trait]
+impl Snapshotter for SlowSnapshots {
+    async fn save(
+        &self,
+        _client_id: u16,
+        _sequence_nr: u64,
+        _state: AccountState,
+    ) -> Result<(), PaymentError> {
+        Ok(())
+    }
+
+    async fn load(&self, _client_id: u16) -> Result<Option<(u64, AccountState)>, PaymentError> {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        Ok(None)
+    }
+}
+
+fn registry(retry: RetryPolicy) -> ClientRegistry {
+    registry_with_callback(Arc::new(SlowFirstDeposit::default()), retry)
+}
+
+fn registry_with_callback(callback: Arc<dyn EventCallback>, retry: RetryPolicy) -> ClientRegistry {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+    .with_callback(callback)
+    .with_timeouts(CallTimeouts {
+        command: Duration::from_millis(50),
+        ..CallTimeouts::default()
+    })
+    .with_retry_policy(retry)
+}
+
+fn command(
+    command: TransactionTypeCommand,
+    key: &str,
+) -> (TransactionTypeCommand, CommandMetadata) {
+    (
+        command,
+        CommandMetadata {
+            deduplication_key: DeduplicationKey::new(key.to_string()),
+        },
+    )
+}
+
+fn deposit() -> (TransactionTypeCommand, CommandMetadata) {
+    command(
+        TransactionTypeCommand::Deposit(Deposit {
+            client_id: 1,
+            tx_id: 1,
+            amount: 10.0,
+        }),
+        "deposit:1:1",
+    )
+}
+
+#[test]
+fn test_backoff_grows_exponentially_up_to_the_cap() {
+    let policy = RetryPolicy {
+        max_retries: 10,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+        multiplier: 2.0,
+        jitter: 0.0,
+    };
+    let delays: Vec<_> = (1..=5).map(|retry| policy.backoff(retry)).collect();
+    assert_eq!(
+        delays,
+        [10, 20, 40, 50, 50].map(Duration::from_millis).to_vec()
+    );
+
+    let jittered = RetryPolicy {
+        jitter: 0.5,
+        ..policy
+    };
+    for _ in 0..100 {
+        let delay = jittered.backoff(2);
+        assert!(delay >= Duration::from_millis(10) && delay <= Duration::from_millis(20));
+    }
+}
+
+#[tokio::test]
+async fn test_timeout_is_reported_as_call_timeout() {
+    let registry = registry(RetryPolicy::never());
+
+    let (command, metadata) = deposit();
+    let result = registry.process_command(1, command, metadata).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::CallTimeout {
+            client_id: 1,
+            timeout_ms: 50
+        }))
+    ));
+}
+
+#[tokio::test]
+async fn test_timed_out_command_is_retried_and_applied_once() {
+    let registry = registry(RetryPolicy {
+        max_retries: 10,
+        ..RetryPolicy::default()
+    });
+
+    let (command, metadata) = deposit();
+    registry
+        .process_command(1, command, metadata)
+        .await
+        .unwrap();
+
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert!(matches!(state, AccountState::Active(s) if s.total == 10.0));
+}
+
+#[tokio::test]
+async fn test_retry_of_committed_withdrawal_is_answered_as_duplicate() {
+    let registry = registry_with_callback(
+        Arc::new(SlowFirstWithdrawal::default()),
+        RetryPolicy {
+            max_retries: 10,
+            ..RetryPolicy::default()
+        },
+    );
+
+    let (deposit, metadata) = deposit();
+    registry
+        .process_command(1, deposit, metadata)
+        .await
+        .unwrap();
+
+    // The first attempt commits the withdrawal, then times out; validating the retry
+    // against the spent balance would fail with InsufficientFunds
+    let (withdrawal, metadata) = command(
+        TransactionTypeCommand::Withdrawal(Withdraw {
+            client_id: 1,
+            tx_id: 2,
+            amount: 10.0,
+        }),
+        "withdrawal:1:2",
+    );
+    registry
+        .process_command(1, withdrawal, metadata)
+        .await
+        .unwrap();
+
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert!(matches!(state, AccountState::Active(s) if s.total == 0.0));
+}
+
+#[tokio::test]
+async fn test_business_rejection_is_not_retried() {
+    // A retry would wait for at least a second
+    let registry = registry(RetryPolicy {
+        initial_backoff: Duration::from_secs(1),
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    });
+
+    let (command, metadata) = command(
+        TransactionTypeCommand::Withdrawal(Withdraw {
+            client_id: 1,
+            tx_id: 2,
+            amount: 5.0,
+        }),
+        "withdrawal:1:2",
+    );
+    let result = tokio::time::timeout(
+        Duration::from_millis(500),
+        registry.process_command(1, command, metadata),
+    )
+    .await
+    .expect("rejection returned without retrying");
+    assert!(matches!(result, Err(PaymentError::Transaction(_))));
+}
+
+#[tokio::test]
+async fn test_slow_spawn_times_out_per_configuration() {
+    let registry = registry(RetryPolicy::never())
+        .with_snapshots(Arc::new(SlowSnapshots))
+        .with_timeouts(CallTimeouts {
+            spawn: Duration::from_millis(20),
+            ..CallTimeouts::default()
+        });
+
+    let (command, metadata) = deposit();
+    let result = registry.process_command(1, command, metadata).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::CallTimeout {
+            client_id: 1,
+            timeout_ms: 20
+        }))
+    ));
+}
@@ -0,0 +1,405 @@
+use async_trait::async_trait;
+use payment::adapter::{
+    ClientRegistry, FileCheckpointStore, InMemoryCheckpointStore, InMemoryDisputeIndex,
+    InMemoryJournal, SubscriptionConfig, SubscriptionHub,
+};
+use payment::domain::*;
+use payment::port::{CallbackContext, CheckpointStore, DisputeIndex, Journal, Subscriber};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Subscriber recording the sequence numbers it handled and the gaps it was told about
+struct Recorder {
+    name: String,
+    seen: Mutex<Vec<u64>>,
+    gaps: Mutex<Vec<(u64, u64)>>,
+    /// Resume after a gap instead of stopping
+    skip_gaps: bool,
+}
+
+impl Recorder {
+    fn new(name: &str) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.to_string(),
+            seen: Mutex::new(Vec::new()),
+            gaps: Mutex::new(Vec::new()),
+            skip_gaps: false,
+        })
+    }
+
+    fn skipping_gaps(name: &str) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.to_string(),
+            seen: Mutex::new(Vec::new()),
+            gaps: Mutex::new(Vec::new()),
+            skip_gaps: true,
+        })
+    }
+
+    fn seen(&self) -> Vec<u64> {
+        self.seen.lock().unwrap().clone()
+    }
+
+    fn gaps(&self) -> Vec<(u64, u64)> {
+        self.gaps.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Subscriber for Recorder {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn handle(&self, envelope: &EventEnvelope) -> Result<(), PaymentError> {
+        self.seen.lock().unwrap().push(envelope.sequence_nr);
+        Ok(())
+    }
+
+    async fn on_gap(&self, from_sequence: u64, to_sequence: u64) -> Result<(), PaymentError> {
+        self.gaps.lock().unwrap().push((from_sequence, to_sequence));
+        if self.skip_gaps {
+            return Ok(());
+        }
+        Err(PaymentError::Engine(EngineError::HistoryGap {
+            from_sequence,
+            to_sequence,
+        }))
+    }
+}
+
+async fn append_deposit(journal: &dyn Journal, client_id: u16, tx_id: u32) {
+    journal
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id,
+                tx_id,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id,
+                tx_id,
+                timestamp: chrono::Utc::now(),
+                deduplication_key: DeduplicationKey::new(format!("deposit:{}", tx_id)),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+async fn deposit(registry: &ClientRegistry, client_id: u16, tx_id: u32) {
+    registry
+        .process_command(
+            client_id,
+            TransactionTypeCommand::Deposit(Deposit {
+                client_id,
+                tx_id,
+                amount: 10.0,
+            }),
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new(format!("deposit:{}", tx_id)),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_subscription_catches_up_from_checkpoint() {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    for tx_id in 1..=25 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+    }
+
+    let checkpoints = Arc::new(InMemoryCheckpointStore::new());
+    checkpoints.commit("recorder", 10).await.unwrap();
+
+    let hub = SubscriptionHub::new(journal, 64);
+    let recorder = Recorder::new("recorder");
+    let mut handle = hub.subscribe(
+        recorder.clone(),
+        checkpoints.clone(),
+        SubscriptionConfig {
+            page_size: 4,
+            checkpoint_interval: 100,
+            ..Default::default()
+        },
+    );
+
+    handle.wait_for(25).await.unwrap();
+    assert_eq!(handle.stop().await.unwrap(), 25);
+    assert_eq!(recorder.seen(), (11..=25).collect::<Vec<_>>());
+    assert_eq!(checkpoints.load("recorder").await.unwrap(), Some(25));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_subscription_switches_to_live_events() {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    for tx_id in 1..=5 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+    }
+
+    let hub = SubscriptionHub::new(journal.clone(), 64);
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    let registry = ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+    .with_callback(hub.publisher());
+
+    let recorder = Recorder::new("recorder");
+    let mut handle = hub.subscribe(
+        recorder.clone(),
+        Arc::new(InMemoryCheckpointStore::new()),
+        SubscriptionConfig::default(),
+    );
+    handle.wait_for(5).await.unwrap();
+
+    // Live events from several clients, published in whatever order the actors finish
+    let mut tasks = Vec::new();
+    for client_id in 1..=4u16 {
+        let registry = registry.clone();
+        tasks.push(tokio::spawn(async move {
+            for i in 0..10u32 {
+                deposit(&registry, client_id, 100 + client_id as u32 * 100 + i).await;
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    handle.wait_for(45).await.unwrap();
+    handle.stop().await.unwrap();
+    assert_eq!(recorder.seen(), (1..=45).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_subscription_resumes_after_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    for tx_id in 1..=10 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+    }
+
+    let hub = SubscriptionHub::new(journal.clone(), 64);
+    let first = Recorder::new("projection");
+    let mut handle = hub.subscribe(
+        first.clone(),
+        Arc::new(FileCheckpointStore::new(dir.path()).unwrap()),
+        SubscriptionConfig::default(),
+    );
+    handle.wait_for(10).await.unwrap();
+    handle.stop().await.unwrap();
+
+    for tx_id in 11..=15 {
+        append_deposit(journal.as_ref(), 2, tx_id).await;
+    }
+
+    // A new process reading the same checkpoint directory only sees the new events
+    let second = Recorder::new("projection");
+    let mut handle = hub.subscribe(
+        second.clone(),
+        Arc::new(FileCheckpointStore::new(dir.path()).unwrap()),
+        SubscriptionConfig::default(),
+    );
+    handle.wait_for(15).await.unwrap();
+    handle.stop().await.unwrap();
+
+    assert_eq!(first.seen(), (1..=10).collect::<Vec<_>>());
+    assert_eq!(second.seen(), (11..=15).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_subscription_reports_truncated_events() {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    for tx_id in 1..=10 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+    }
+    journal.truncate(6).await.unwrap();
+    let hub = SubscriptionHub::new(journal, 64);
+
+    // By default the subscription stops at the gap
+    let recorder = Recorder::new("strict");
+    let handle = hub.subscribe(
+        recorder.clone(),
+        Arc::new(InMemoryCheckpointStore::new()),
+        SubscriptionConfig::default(),
+    );
+    assert!(matches!(
+        handle.stop().await,
+        Err(PaymentError::Engine(EngineError::HistoryGap {
+            from_sequence: 1,
+            to_sequence: 5,
+        }))
+    ));
+    assert!(recorder.seen().is_empty());
+    assert_eq!(recorder.gaps(), vec![(1, 5)]);
+
+    // A subscriber accepting the gap resumes after it
+    let recorder = Recorder::skipping_gaps("lenient");
+    let mut handle = hub.subscribe(
+        recorder.clone(),
+        Arc::new(InMemoryCheckpointStore::new()),
+        SubscriptionConfig::default(),
+    );
+    handle.wait_for(10).await.unwrap();
+    assert_eq!(handle.stop().await.unwrap(), 10);
+    assert_eq!(recorder.seen(), (6..=10).collect::<Vec<_>>());
+    assert_eq!(recorder.gaps(), vec![(1, 5)]);
+}
+
+#[tokio::test]
+async fn test_subscription_gives_up_on_unreadable_events_after_timeout() {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    for tx_id in 1..=3 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+    }
+    let hub = SubscriptionHub::new(journal.clone(), 64);
+
+    let recorder = Recorder::skipping_gaps("recorder");
+    let mut handle = hub.subscribe(
+        recorder.clone(),
+        Arc::new(InMemoryCheckpointStore::new()),
+        SubscriptionConfig {
+            gap_timeout: Duration::from_millis(50),
+            ..Default::default()
+        },
+    );
+    handle.wait_for(3).await.unwrap();
+
+    // Sequence 5 is published live, but 4 never becomes readable in the journal
+    let mut envelope = journal.replay(Some(3)).await.unwrap().remove(0);
+    envelope.sequence_nr = 5;
+    let TransactionTypeEvent::Deposited(deposited) = envelope.event.clone() else {
+        unreachable!()
+    };
+    hub.publisher()
+        .on_deposited(&deposited, &CallbackContext { journal, envelope })
+        .await
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), handle.wait_for(5))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(recorder.seen(), vec![1, 2, 3, 5]);
+    assert_eq!(recorder.gaps(), vec![(4, 4)]);
+}
+
+#[tokio::test]
+async fn test_subscription_waits_for_the_missing_event_to_be_published() {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    for tx_id in 1..=3 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+    }
+    let hub = SubscriptionHub::new(journal.clone(), 64);
+    let publisher = hub.publisher();
+
+    let recorder = Recorder::new("recorder");
+    let mut handle = hub.subscribe(
+        recorder.clone(),
+        Arc::new(InMemoryCheckpointStore::new()),
+        SubscriptionConfig {
+            gap_timeout: Duration::from_secs(60),
+            ..Default::default()
+        },
+    );
+    handle.wait_for(3).await.unwrap();
+
+    // Sequence 5 is published ahead of 4, which is still being appended
+    let mut early = journal.replay(Some(3)).await.unwrap().remove(0);
+    early.sequence_nr = 5;
+    let TransactionTypeEvent::Deposited(deposited) = early.event.clone() else {
+        unreachable!()
+    };
+    publisher
+        .on_deposited(
+            &deposited,
+            &CallbackContext {
+                journal: journal.clone(),
+                envelope: early,
+            },
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(handle.position(), 3);
+
+    // Once 4 and 5 are appended and published, the subscription reads them long before
+    // the gap timeout
+    for tx_id in 4..=5 {
+        append_deposit(journal.as_ref(), 1, tx_id).await;
+        let envelope = journal.replay(Some(tx_id as u64)).await.unwrap().remove(0);
+        publisher
+            .on_deposited(
+                &deposited,
+                &CallbackContext {
+                    journal: journal.clone(),
+                    envelope,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    tokio::time::timeout(Duration::from_secs(5), handle.wait_for(5))
+        .await
+        .unwrap()
+        .unwrap();
+    handle.stop().await.unwrap();
+    assert_eq!(recorder.seen(), vec![1, 2, 3, 4, 5]);
+    assert!(recorder.gaps().is_empty());
+}
+
+#[tokio::test]
+async fn test_subscription_commits_on_the_interval_and_on_stop() {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let hub = SubscriptionHub::new(journal.clone(), 64);
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    let registry = ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+    .with_callback(hub.publisher());
+
+    let checkpoints = Arc::new(InMemoryCheckpointStore::new());
+    let mut handle = hub.subscribe(
+        Recorder::new("recorder"),
+        checkpoints.clone(),
+        SubscriptionConfig {
+            checkpoint_interval: 4,
+            ..Default::default()
+        },
+    );
+
+    // Live events one at a time: an idle subscription does not commit after each one
+    for tx_id in 1..=3 {
+        deposit(&registry, 1, tx_id).await;
+        handle.wait_for(tx_id as u64).await.unwrap();
+    }
+    assert_eq!(checkpoints.load("recorder").await.unwrap(), None);
+
+    for tx_id in 4..=5 {
+        deposit(&registry, 1, tx_id).await;
+    }
+    handle.wait_for(5).await.unwrap();
+    assert_eq!(checkpoints.load("recorder").await.unwrap(), Some(4));
+
+    assert_eq!(handle.stop().await.unwrap(), 5);
+    assert_eq!(checkpoints.load("recorder").await.unwrap(), Some(5));
+}
+
+#[tokio::test]
+async fn test_file_checkpoint_store_rejects_path_like_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let checkpoints = FileCheckpointStore::new(dir.path()).unwrap();
+
+    let error = checkpoints.commit("../escape", 1).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_configuration");
+    assert_eq!(error.category(), ErrorCategory::InfrastructureFatal);
+    assert!(checkpoints.load(".hidden").await.is_err());
+    assert_eq!(checkpoints.load("unknown").await.unwrap(), None);
+}
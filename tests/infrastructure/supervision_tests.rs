@@ -0,0 +1,324 @@
+use async_trait::async_trait;
+use payment::adapter::{
+    ClientRegistry, InMemoryDisputeIndex, InMemoryJournal, RetryPolicy, SupervisionConfig,
+};
+use payment::domain::*;
+use payment::port::{ClientExecutor, DisputeIndex, Journal};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Journal that persists every event but reports transactions from `FAULTY_TX_ID` on with
+/// sequence 0, like an out-of-order delivery
+struct RewindingJournal(InMemoryJournal);
+
+const FAULTY_TX_ID: u32 = 100;
+
+#[async_trait]
+impl Journal for RewindingJournal {
+    async fn append(
+        &self,
+        event: TransactionTypeEvent,
+        metadata: EventMetadata,
+    ) -> Result<AppendOutcome, PaymentError> {
+        let faulty = metadata.tx_id >= FAULTY_TX_ID;
+        match self.0.append(event, metadata).await? {
+            AppendOutcome::Appended(mut envelope) if faulty => {
+                envelope.sequence_nr = 0;
+                Ok(AppendOutcome::Appended(envelope))
+            }
+            outcome => Ok(outcome),
+        }
+    }
+
+    async fn replay(&self, from_sequence: Option<u64>) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.0.replay(from_sequence).await
+    }
+
+    async fn highest_sequence(&self) -> Result<Option<u64>, PaymentError> {
+        self.0.highest_sequence().await
+    }
+
+    async fn find_by_tx_id(&self, tx_id: u32) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.0.find_by_tx_id(tx_id).await
+    }
+
+    async fn query(&self, query: &JournalQuery) -> Result<JournalPage, PaymentError> {
+        self.0.query(query).await
+    }
+
+    async fn import(&self, envelope: EventEnvelope) -> Result<(), PaymentError> {
+        self.0.import(envelope).await
+    }
+
+    async fn truncate(&self, before_sequence: u64) -> Result<usize, PaymentError> {
+        self.0.truncate(before_sequence).await
+    }
+}
+
+/// Journal whose per-client reads (actor recovery) take a while once `slow` is set,
+/// recording how many ran at the same time
+#[derive(Default)]
+struct SlowRecoveryJournal {
+    inner: InMemoryJournal,
+    slow: AtomicBool,
+    running: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+#[async_trait]
+impl Journal for SlowRecoveryJournal {
+    async fn append(
+        &self,
+        event: TransactionTypeEvent,
+        metadata: EventMetadata,
+    ) -> Result<AppendOutcome, PaymentError> {
+        self.inner.append(event, metadata).await
+    }
+
+    async fn replay(&self, from_sequence: Option<u64>) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.inner.replay(from_sequence).await
+    }
+
+    async fn highest_sequence(&self) -> Result<Option<u64>, PaymentError> {
+        self.inner.highest_sequence().await
+    }
+
+    async fn find_by_tx_id(&self, tx_id: u32) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.inner.find_by_tx_id(tx_id).await
+    }
+
+    async fn query(&self, query: &JournalQuery) -> Result<JournalPage, PaymentError> {
+        if query.client_id.is_some() && self.slow.load(Ordering::SeqCst) {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+        }
+        self.inner.query(query).await
+    }
+
+    async fn import(&self, envelope: EventEnvelope) -> Result<(), PaymentError> {
+        self.inner.import(envelope).await
+    }
+
+    async fn truncate(&self, before_sequence: u64) -> Result<usize, PaymentError> {
+        self.inner.truncate(before_sequence).await
+    }
+}
+
+/// Registry that reports failures without retrying them
+fn registry(config: SupervisionConfig) -> ClientRegistry {
+    let journal: Arc<dyn Journal + Send + Sync> =
+        Arc::new(RewindingJournal(InMemoryJournal::new()));
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+    .with_supervision(config)
+    .with_retry_policy(RetryPolicy::never())
+}
+
+async fn deposit(
+    executor: &dyn ClientExecutor,
+    client_id: u16,
+    tx_id: u32,
+    amount: f64,
+) -> Result<(), PaymentError> {
+    executor
+        .process_command(
+            client_id,
+            TransactionTypeCommand::Deposit(Deposit {
+                client_id,
+                tx_id,
+                amount,
+            }),
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new(format!("deposit:{}", tx_id)),
+            },
+        )
+        .await
+}
+
+/// Failures reach the supervisor after the caller got its error
+async fn wait_for_quarantine(registry: &ClientRegistry, client_id: u16) {
+    for _ in 0..200 {
+        if registry.is_quarantined(client_id).await.unwrap() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("client {} was not quarantined", client_id);
+}
+
+fn total(state: &AccountState) -> f64 {
+    match state {
+        AccountState::Active(s) => s.total,
+        AccountState::Frozen(s) => s.total,
+    }
+}
+
+#[tokio::test]
+async fn test_sequence_violation_is_typed_and_actor_restarts_from_journal() {
+    let registry = registry(SupervisionConfig::default());
+    deposit(&registry, 1, 1, 10.0).await.unwrap();
+    deposit(&registry, 1, 2, 10.0).await.unwrap();
+
+    let result = deposit(&registry, 1, FAULTY_TX_ID, 5.0).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::SequenceViolation {
+            client_id: 1,
+            last_sequence: 2,
+            sequence_nr: 0,
+        }))
+    ));
+
+    // The restarted actor rebuilt its state from the journal, which holds the deposit
+    deposit(&registry, 1, 3, 1.0).await.unwrap();
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(total(&state), 26.0);
+    assert_eq!(registry.metrics().restarted(), 1);
+    assert!(!registry.is_quarantined(1).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_repeated_failures_quarantine_the_client() {
+    let registry = registry(SupervisionConfig {
+        max_restarts: 1,
+        window: Duration::from_secs(60),
+    });
+    deposit(&registry, 1, 1, 10.0).await.unwrap();
+    deposit(&registry, 2, 2, 10.0).await.unwrap();
+
+    for tx_id in [FAULTY_TX_ID, FAULTY_TX_ID + 1] {
+        let result = deposit(&registry, 1, tx_id, 1.0).await;
+        assert!(matches!(
+            result,
+            Err(PaymentError::Engine(EngineError::SequenceViolation { .. }))
+        ));
+    }
+
+    wait_for_quarantine(&registry, 1).await;
+    let result = deposit(&registry, 1, 3, 1.0).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::ClientQuarantined(1)))
+    ));
+    assert_eq!(registry.metrics().quarantined(), 1);
+
+    // Other clients keep working
+    deposit(&registry, 2, 4, 5.0).await.unwrap();
+    let state = registry.get_state(2).await.unwrap().unwrap();
+    assert_eq!(total(&state), 15.0);
+}
+
+#[tokio::test]
+async fn test_released_client_recovers_from_the_journal() {
+    let registry = registry(SupervisionConfig {
+        max_restarts: 0,
+        window: Duration::from_secs(60),
+    });
+    deposit(&registry, 1, 1, 10.0).await.unwrap();
+    assert!(deposit(&registry, 1, FAULTY_TX_ID, 1.0).await.is_err());
+    wait_for_quarantine(&registry, 1).await;
+    assert!(registry.get_state(1).await.unwrap().is_none());
+
+    registry.release(1).await.unwrap();
+    deposit(&registry, 1, 2, 5.0).await.unwrap();
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(total(&state), 16.0);
+}
+
+#[tokio::test]
+async fn test_retried_sequence_violation_is_answered_by_the_restarted_actor() {
+    let registry = registry(SupervisionConfig::default()).with_retry_policy(RetryPolicy {
+        max_retries: 10,
+        ..RetryPolicy::default()
+    });
+    deposit(&registry, 1, 1, 10.0).await.unwrap();
+
+    // The deposit was journaled before the violation: the retry finds it as a duplicate
+    deposit(&registry, 1, FAULTY_TX_ID, 5.0).await.unwrap();
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(total(&state), 15.0);
+    assert_eq!(registry.metrics().restarted(), 1);
+}
+
+#[tokio::test]
+async fn test_pool_recovers_a_client_after_a_sequence_violation() {
+    let pool = registry(SupervisionConfig::default())
+        .worker_pool(1)
+        .await
+        .unwrap();
+    deposit(&pool, 1, 1, 10.0).await.unwrap();
+
+    let result = deposit(&pool, 1, FAULTY_TX_ID, 5.0).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::SequenceViolation { .. }))
+    ));
+    // The stale account is dropped instead of being served
+    assert!(pool.get_all_states().await.unwrap().is_empty());
+
+    deposit(&pool, 1, 2, 1.0).await.unwrap();
+    let states = pool.get_all_states().await.unwrap();
+    assert_eq!(total(&states[&1]), 16.0);
+    pool.shutdown_all().await;
+}
+
+#[tokio::test]
+async fn test_pool_retries_per_the_registry_policy() {
+    let pool = registry(SupervisionConfig::default())
+        .with_retry_policy(RetryPolicy {
+            max_retries: 10,
+            ..RetryPolicy::default()
+        })
+        .worker_pool(1)
+        .await
+        .unwrap();
+    deposit(&pool, 1, 1, 10.0).await.unwrap();
+
+    // The retry recovers the client and finds the journaled deposit as a duplicate
+    deposit(&pool, 1, FAULTY_TX_ID, 5.0).await.unwrap();
+    let states = pool.get_all_states().await.unwrap();
+    assert_eq!(total(&states[&1]), 15.0);
+    pool.shutdown_all().await;
+}
+
+#[tokio::test]
+async fn test_cold_start_recoveries_run_concurrently() {
+    let journal = Arc::new(SlowRecoveryJournal::default());
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    let registry = ClientRegistry::with_namespace(
+        journal.clone(),
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    );
+    for client_id in 1..=4u16 {
+        deposit(&registry, client_id, client_id as u32, 10.0)
+            .await
+            .unwrap();
+    }
+    registry.shutdown_all().await;
+
+    // Every client replays its journal at once instead of one after another
+    journal.slow.store(true, Ordering::SeqCst);
+    let spawns: Vec<_> = (1..=4u16)
+        .map(|client_id| {
+            let registry = registry.clone();
+            tokio::spawn(async move { registry.get_or_spawn(client_id).await })
+        })
+        .collect();
+    for spawn in spawns {
+        spawn.await.unwrap().unwrap();
+    }
+
+    assert!(journal.peak.load(Ordering::SeqCst) > 1);
+    for client_id in 1..=4u16 {
+        let state = registry.get_state(client_id).await.unwrap().unwrap();
+        assert_eq!(total(&state), 10.0);
+    }
+}
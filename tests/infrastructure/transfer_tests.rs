@@ -0,0 +1,172 @@
+use payment::adapter::{FileJournal, FileJournalConfig, InMemoryJournal};
+use payment::domain::*;
+use payment::port::Journal;
+use payment::service::{ExportFilter, export_journal, import_journal};
+
+async fn populated_journal() -> InMemoryJournal {
+    let journal = InMemoryJournal::new();
+    for tx_id in 1..=6u32 {
+        let client_id = (tx_id % 2) as u16 + 1;
+        journal
+            .append(
+                TransactionTypeEvent::Deposited(Deposited {
+                    client_id,
+                    tx_id,
+                    amount: tx_id as f64,
+                }),
+                EventMetadata {
+                    client_id,
+                    tx_id,
+                    timestamp: chrono::Utc::now() - chrono::TimeDelta::days(tx_id as i64),
+                    deduplication_key: DeduplicationKey::new(format!(
+                        "deposit:{}:{}",
+                        client_id, tx_id
+                    )),
+                },
+            )
+            .await
+            .unwrap();
+    }
+    journal
+}
+
+#[tokio::test]
+async fn test_export_then_import_preserves_envelopes() {
+    let source = populated_journal().await;
+    let mut buffer = Vec::new();
+    let report = export_journal(&source, &ExportFilter::default(), &mut buffer)
+        .await
+        .unwrap();
+    assert_eq!(report.envelopes, 6);
+
+    let dir = tempfile::tempdir().unwrap();
+    let target = FileJournal::open(dir.path(), FileJournalConfig::default())
+        .await
+        .unwrap();
+    let report = import_journal(&target, buffer.as_slice()).await.unwrap();
+    assert_eq!(report.envelopes, 6);
+    assert_eq!(report.last_sequence, Some(6));
+
+    let original = source.replay(None).await.unwrap();
+    let imported = target.replay(None).await.unwrap();
+    for (a, b) in original.iter().zip(&imported) {
+        assert_eq!(a.sequence_nr, b.sequence_nr);
+        assert_eq!(a.deduplication_key, b.deduplication_key);
+        assert_eq!(a.timestamp, b.timestamp);
+        assert_eq!(a.hash, b.hash);
+    }
+    assert_eq!(verify_chain(imported.iter()), Ok(6));
+
+    // Imported deduplication keys still deduplicate
+    let duplicate = target
+        .append(
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 2,
+                tx_id: 1,
+                amount: 1.0,
+            }),
+            EventMetadata {
+                client_id: 2,
+                tx_id: 1,
+                timestamp: chrono::Utc::now(),
+                deduplication_key: DeduplicationKey::new("deposit:2:1".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+    assert!(duplicate.is_duplicate());
+    assert_eq!(duplicate.envelope().sequence_nr, 1);
+}
+
+#[tokio::test]
+async fn test_export_filters_range_and_client() {
+    let source = populated_journal().await;
+    let filter = ExportFilter {
+        from_sequence: Some(2),
+        to_sequence: Some(5),
+        client_id: Some(1),
+    };
+
+    let mut buffer = Vec::new();
+    let report = export_journal(&source, &filter, &mut buffer).await.unwrap();
+
+    assert_eq!(report.envelopes, 2);
+    assert_eq!(report.first_sequence, Some(2));
+    assert_eq!(report.last_sequence, Some(4));
+    assert_eq!(String::from_utf8(buffer).unwrap().lines().count(), 2);
+}
+
+#[tokio::test]
+async fn test_import_reports_malformed_line() {
+    let journal = InMemoryJournal::new();
+    let input = "\n{not json}\n";
+
+    let result = import_journal(&journal, input.as_bytes()).await;
+    match result {
+        Err(PaymentError::Engine(EngineError::PersistenceError(message))) => {
+            assert!(message.starts_with("Line 2"));
+        }
+        other => panic!(
+            "Expected a persistence error, got {:?}",
+            other.map(|r| r.envelopes)
+        ),
+    }
+}
+
+async fn export(journal: &InMemoryJournal, filter: &ExportFilter) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    export_journal(journal, filter, &mut buffer).await.unwrap();
+    buffer
+}
+
+#[tokio::test]
+async fn test_import_rejects_diverging_history() {
+    // Same commands at different times: a different chain
+    let ours = populated_journal().await;
+    let theirs = populated_journal().await;
+
+    let target = InMemoryJournal::new();
+    let prefix = ExportFilter {
+        to_sequence: Some(3),
+        ..ExportFilter::default()
+    };
+    import_journal(&target, export(&ours, &prefix).await.as_slice())
+        .await
+        .unwrap();
+
+    // Resuming with the same history is fine
+    import_journal(
+        &target,
+        export(&ours, &ExportFilter::default()).await.as_slice(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(target.highest_sequence().await.unwrap(), Some(6));
+
+    // Overlapping sequences with other hashes
+    let result = import_journal(
+        &target,
+        export(&theirs, &ExportFilter::default()).await.as_slice(),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::HistoryConflict(_)))
+    ));
+
+    // A continuation that does not link to the head
+    let target = InMemoryJournal::new();
+    import_journal(&target, export(&ours, &prefix).await.as_slice())
+        .await
+        .unwrap();
+    let rest = ExportFilter {
+        from_sequence: Some(4),
+        ..ExportFilter::default()
+    };
+    let result = import_journal(&target, export(&theirs, &rest).await.as_slice()).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::HistoryConflict(_)))
+    ));
+    assert_eq!(target.highest_sequence().await.unwrap(), Some(3));
+}
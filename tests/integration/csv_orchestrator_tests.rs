@@ -1,5 +1,7 @@
-use payment::adapter::{ClientRegistry, InMemoryDisputeIndex, InMemoryJournal};
-use payment::domain::OrchestratorMode;
+use payment::adapter::{
+    ClientRegistry, FileJournal, FileJournalConfig, InMemoryDisputeIndex, InMemoryJournal,
+};
+use payment::domain::{AccountState, OrchestratorMode};
 use payment::port::{DisputeIndex, Journal};
 use payment::service::Orchestrator;
 use std::io::Write;
@@ -219,3 +221,45 @@ async fn test_csv_processing_invalid_withdrawal() {
         _ => panic!("Expected Active state"),
     }
 }
+
+#[tokio::test]
+async fn test_dispute_from_earlier_run_can_be_resolved() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let run = |lines: &'static [&'static str]| {
+        let dir = dir.path().to_path_buf();
+        async move {
+            let mut temp_file = NamedTempFile::new().unwrap();
+            writeln!(temp_file, "type,client,tx,amount").unwrap();
+            for line in lines {
+                writeln!(temp_file, "{}", line).unwrap();
+            }
+            temp_file.flush().unwrap();
+
+            let journal = FileJournal::open(dir, FileJournalConfig::default())
+                .await
+                .unwrap();
+            let orchestrator = Orchestrator::with_journal(
+                Arc::new(journal),
+                OrchestratorMode::Csv {
+                    file_path: temp_file.path().to_str().unwrap().to_string(),
+                },
+            )
+            .await
+            .unwrap();
+            orchestrator.process_with_report().await.unwrap()
+        }
+    };
+
+    run(&["deposit,1,1,10.0", "dispute,1,1,"]).await;
+    let (states, report) = run(&["resolve,1,1,"]).await;
+
+    assert_eq!(report.failed(), 0);
+    match &states[&1] {
+        AccountState::Active(active) => {
+            assert_eq!(active.available, 10.0);
+            assert_eq!(active.held, 0.0);
+        }
+        other => panic!("Expected Active state, got {:?}", other),
+    }
+}
@@ -0,0 +1,252 @@
+use async_trait::async_trait;
+use payment::adapter::{ClientRegistry, InMemoryDisputeIndex, InMemoryJournal};
+use payment::domain::*;
+use payment::port::{DisputeIndex, Journal};
+use payment::service::Orchestrator;
+use std::io::Write;
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+
+/// Journal whose disk "fails" when appending transaction `FAILING_TX_ID` or reading the
+/// events of client `UNRECOVERABLE_CLIENT_ID`
+struct FailingJournal(InMemoryJournal);
+
+const FAILING_TX_ID: u32 = 3;
+const UNRECOVERABLE_CLIENT_ID: u16 = 9;
+
+#[async_trait]
+impl Journal for FailingJournal {
+    async fn append(
+        &self,
+        event: TransactionTypeEvent,
+        metadata: EventMetadata,
+    ) -> Result<AppendOutcome, PaymentError> {
+        if metadata.tx_id == FAILING_TX_ID {
+            return Err(PaymentError::Engine(EngineError::PersistenceError(
+                "disk full".to_string(),
+            )));
+        }
+        self.0.append(event, metadata).await
+    }
+
+    async fn replay(&self, from_sequence: Option<u64>) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.0.replay(from_sequence).await
+    }
+
+    async fn highest_sequence(&self) -> Result<Option<u64>, PaymentError> {
+        self.0.highest_sequence().await
+    }
+
+    async fn find_by_tx_id(&self, tx_id: u32) -> Result<Vec<EventEnvelope>, PaymentError> {
+        self.0.find_by_tx_id(tx_id).await
+    }
+
+    async fn query(&self, query: &JournalQuery) -> Result<JournalPage, PaymentError> {
+        if query.client_id == Some(UNRECOVERABLE_CLIENT_ID) {
+            return Err(PaymentError::Engine(EngineError::PersistenceError(
+                "corrupt segment".to_string(),
+            )));
+        }
+        self.0.query(query).await
+    }
+
+    async fn import(&self, envelope: EventEnvelope) -> Result<(), PaymentError> {
+        self.0.import(envelope).await
+    }
+
+    async fn truncate(&self, before_sequence: u64) -> Result<usize, PaymentError> {
+        self.0.truncate(before_sequence).await
+    }
+}
+
+fn registry(journal: Arc<dyn Journal + Send + Sync>) -> ClientRegistry {
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+}
+
+fn orchestrator(
+    journal: Arc<dyn Journal + Send + Sync>,
+    lines: &[&str],
+) -> (Orchestrator, NamedTempFile) {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type,client,tx,amount").unwrap();
+    for line in lines {
+        writeln!(file, "{}", line).unwrap();
+    }
+    file.flush().unwrap();
+
+    let registry = registry(journal);
+    let orchestrator = Orchestrator::with_registry(
+        registry,
+        OrchestratorMode::Csv {
+            file_path: file.path().to_str().unwrap().to_string(),
+        },
+    );
+    (orchestrator, file)
+}
+
+#[test]
+fn test_errors_carry_code_category_and_retryability() {
+    let rejection = PaymentError::Transaction(TransactionError::InsufficientFunds);
+    assert_eq!(rejection.code(), "insufficient_funds");
+    assert_eq!(rejection.category(), ErrorCategory::BusinessRejection);
+    assert!(!rejection.is_retryable());
+
+    let timeout = PaymentError::Engine(EngineError::CallTimeout {
+        client_id: 1,
+        timeout_ms: 500,
+    });
+    assert_eq!(timeout.code(), "call_timeout");
+    assert_eq!(timeout.category(), ErrorCategory::InfrastructureTransient);
+    assert!(timeout.is_retryable());
+
+    let fatal = PaymentError::Engine(EngineError::PersistenceError("disk full".to_string()));
+    assert_eq!(fatal.code(), "persistence_failed");
+    assert_eq!(fatal.category(), ErrorCategory::InfrastructureFatal);
+    assert!(!fatal.is_retryable());
+
+    assert_eq!(
+        serde_json::to_string(&ErrorCategory::InfrastructureTransient).unwrap(),
+        "\"infrastructure_transient\""
+    );
+}
+
+#[tokio::test]
+async fn test_frozen_account_withdrawal_is_a_business_rejection() {
+    let (orchestrator, _file) = orchestrator(
+        Arc::new(InMemoryJournal::new()),
+        &[
+            "deposit,1,1,10.0",
+            "dispute,1,1,",
+            "chargeback,1,1,",
+            "deposit,1,2,5.0",
+            "withdrawal,1,3,1.0",
+        ],
+    );
+
+    let (_, report) = orchestrator.process_with_report().await.unwrap();
+    assert_eq!(report.codes.get("account_locked"), Some(&1));
+    assert_eq!(report.infrastructure_failures(), 0);
+}
+
+#[tokio::test]
+async fn test_report_counts_rejections_by_code_and_keeps_processing() {
+    let (orchestrator, _file) = orchestrator(
+        Arc::new(InMemoryJournal::new()),
+        &[
+            "deposit,1,1,10.0",
+            "withdrawal,1,2,50.0",
+            "withdrawal,2,3,1.0",
+            "dispute,1,99,",
+            "deposit,1,4,5.0",
+        ],
+    );
+
+    let (states, report) = orchestrator.process_with_report().await.unwrap();
+    assert_eq!(report.commands, 5);
+    assert_eq!(report.failed(), 3);
+    assert_eq!(report.failures[&ErrorCategory::BusinessRejection], 3);
+    assert_eq!(report.codes["insufficient_funds"], 2);
+    assert_eq!(report.codes["resource_not_loaded"], 1);
+    assert!(matches!(&states[&1], AccountState::Active(s) if s.total == 15.0));
+}
+
+#[tokio::test]
+async fn test_fatal_error_stops_the_run() {
+    let (orchestrator, _file) = orchestrator(
+        Arc::new(FailingJournal(InMemoryJournal::new())),
+        &[
+            "deposit,1,1,10.0",
+            "deposit,1,2,10.0",
+            "deposit,1,3,10.0",
+            "deposit,1,4,10.0",
+        ],
+    );
+
+    let error = orchestrator.process_with_report().await.unwrap_err();
+    let error = error.downcast_ref::<PaymentError>().unwrap();
+    assert_eq!(error.code(), "persistence_failed");
+    assert_eq!(error.category(), ErrorCategory::InfrastructureFatal);
+}
+
+#[tokio::test]
+async fn test_failed_recovery_is_fatal_and_not_retried() {
+    let registry = registry(Arc::new(FailingJournal(InMemoryJournal::new())));
+
+    let result = registry
+        .process_command(
+            UNRECOVERABLE_CLIENT_ID,
+            TransactionTypeCommand::Deposit(Deposit {
+                client_id: UNRECOVERABLE_CLIENT_ID,
+                tx_id: 1,
+                amount: 10.0,
+            }),
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new("deposit:9:1".to_string()),
+            },
+        )
+        .await;
+
+    let error = result.unwrap_err();
+    assert_eq!(error.code(), "actor_failed");
+    assert_eq!(error.category(), ErrorCategory::InfrastructureFatal);
+    // One command is one failure, not one per retry
+    assert!(
+        !registry
+            .is_quarantined(UNRECOVERABLE_CLIENT_ID)
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_event_that_no_longer_applies_fails_recovery() {
+    // A chargeback without held funds, e.g. written by an older, laxer handler
+    let journal = Arc::new(InMemoryJournal::new());
+    journal
+        .append(
+            TransactionTypeEvent::Chargebacked(Chargebacked {
+                client_id: 1,
+                tx_id: 1,
+                amount: 10.0,
+            }),
+            EventMetadata {
+                client_id: 1,
+                tx_id: 1,
+                timestamp: chrono::Utc::now(),
+                deduplication_key: DeduplicationKey::new("chargeback:1".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+    let registry = registry(journal);
+
+    let result = registry
+        .process_command(
+            1,
+            TransactionTypeCommand::Deposit(Deposit {
+                client_id: 1,
+                tx_id: 2,
+                amount: 10.0,
+            }),
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new("deposit:1:2".to_string()),
+            },
+        )
+        .await;
+
+    match result {
+        Err(PaymentError::Engine(EngineError::ActorFailed { reason, .. })) => {
+            assert!(
+                reason.contains("event 1 could not be applied"),
+                "{}",
+                reason
+            )
+        }
+        other => panic!("expected a failed recovery, got {:?}", other),
+    }
+}
@@ -1,3 +1,11 @@
 mod multi_client_tests;
 mod csv_orchestrator_tests;
 
+mod temporal_query_tests;
+mod projection_tests;
+mod snapshot_recovery_tests;
+mod snapshot_policy_tests;
+mod passivation_tests;
+mod worker_pool_tests;
+mod redelivery_tests;
+mod error_taxonomy_tests;
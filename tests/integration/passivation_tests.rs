@@ -0,0 +1,133 @@
+use payment::adapter::{ClientRegistry, FileSnapshotStore, InMemoryDisputeIndex, InMemoryJournal};
+use payment::domain::*;
+use payment::port::{DisputeIndex, Journal};
+use payment::service::Orchestrator;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn registry(idle_timeout: Duration) -> ClientRegistry {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+    .with_idle_timeout(idle_timeout)
+}
+
+async fn deposit(registry: &ClientRegistry, tx_id: u32, amount: f64) {
+    registry
+        .process_command(
+            1,
+            TransactionTypeCommand::Deposit(Deposit {
+                client_id: 1,
+                tx_id,
+                amount,
+            }),
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new(format!("deposit:{}", tx_id)),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+async fn wait_for_passivation(registry: &ClientRegistry, passivated: u64) {
+    for _ in 0..200 {
+        if registry.metrics().passivated() == passivated
+            && registry.get_state(1).await.unwrap().is_none()
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("client actor was not passivated");
+}
+
+#[tokio::test]
+async fn test_idle_actor_snapshots_and_stops() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(FileSnapshotStore::new(dir.path()).unwrap());
+    let registry = registry(Duration::from_millis(50)).with_snapshots(store.clone());
+
+    deposit(&registry, 1, 10.0).await;
+    deposit(&registry, 2, 5.0).await;
+    wait_for_passivation(&registry, 1).await;
+
+    // The name is released before the final snapshot is written
+    for _ in 0..200 {
+        if !store.list(1).unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    let snapshots = store.list(1).unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].sequence_nr, 2);
+}
+
+#[tokio::test]
+async fn test_next_command_respawns_and_recovers_passivated_actor() {
+    let registry = registry(Duration::from_millis(50));
+
+    deposit(&registry, 1, 10.0).await;
+    wait_for_passivation(&registry, 1).await;
+
+    deposit(&registry, 2, 5.0).await;
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert!(matches!(state, AccountState::Active(s) if s.total == 15.0));
+    assert_eq!(registry.metrics().spawned(), 2);
+}
+
+#[tokio::test]
+async fn test_active_actor_is_not_passivated() {
+    let registry = registry(Duration::from_millis(100));
+
+    for tx_id in 1..=10 {
+        deposit(&registry, tx_id, 1.0).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(registry.metrics().passivated(), 0);
+    assert_eq!(registry.metrics().spawned(), 1);
+    registry.shutdown_all().await;
+}
+
+#[tokio::test]
+async fn test_passivated_client_is_reported_in_all_states() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(FileSnapshotStore::new(dir.path()).unwrap());
+    let registry = registry(Duration::from_millis(50)).with_snapshots(store);
+
+    deposit(&registry, 1, 10.0).await;
+    deposit(&registry, 2, 5.0).await;
+    wait_for_passivation(&registry, 1).await;
+
+    let states = registry.get_all_states().await.unwrap();
+    assert!(matches!(&states[&1], AccountState::Active(s) if s.total == 15.0));
+    // Reporting does not respawn the actor
+    assert_eq!(registry.metrics().spawned(), 1);
+}
+
+#[tokio::test]
+async fn test_passivated_client_appears_in_csv_output() {
+    let registry = registry(Duration::from_millis(50));
+    deposit(&registry, 1, 10.0).await;
+    wait_for_passivation(&registry, 1).await;
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    writeln!(file, "type,client,tx,amount").unwrap();
+    writeln!(file, "deposit,2,7,3.0").unwrap();
+    file.flush().unwrap();
+
+    let orchestrator = Orchestrator::with_registry(
+        registry,
+        OrchestratorMode::Csv {
+            file_path: file.path().to_str().unwrap().to_string(),
+        },
+    );
+    let states = orchestrator.process().await.unwrap();
+    assert!(matches!(&states[&1], AccountState::Active(s) if s.total == 10.0));
+    assert!(matches!(&states[&2], AccountState::Active(s) if s.total == 3.0));
+}
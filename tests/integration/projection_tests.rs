@@ -0,0 +1,247 @@
+use chrono::{TimeZone, Utc};
+use payment::adapter::{
+    ClientRegistry, InMemoryCheckpointStore, InMemoryDisputeIndex, InMemoryJournal,
+    SubscriptionConfig, SubscriptionHub,
+};
+use payment::domain::*;
+use payment::port::{DisputeIndex, Journal};
+use payment::service::{
+    DailyTotalsProjection, OpenDisputesProjection, ProjectionRunner, TransactionHistoryProjection,
+};
+use std::sync::Arc;
+
+async fn append(journal: &dyn Journal, event: TransactionTypeEvent, day: u32) {
+    let (client_id, tx_id) = match &event {
+        TransactionTypeEvent::Deposited(e) => (e.client_id, e.tx_id),
+        TransactionTypeEvent::Withdrawn(e) => (e.client_id, e.tx_id),
+        TransactionTypeEvent::Disputed(e) => (e.client_id, e.tx_id),
+        TransactionTypeEvent::Resolved(e) => (e.client_id, e.tx_id),
+        TransactionTypeEvent::Chargebacked(e) => (e.client_id, e.tx_id),
+        TransactionTypeEvent::Redacted(e) => (e.client_id, e.tx_id),
+    };
+    let key = format!("{}:{}:{}", event.kind().as_str(), client_id, tx_id);
+    journal
+        .append(
+            event,
+            EventMetadata {
+                client_id,
+                tx_id,
+                timestamp: Utc.with_ymd_and_hms(2025, 3, day, 12, 0, 0).unwrap(),
+                deduplication_key: DeduplicationKey::new(key),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+/// Two clients over two days, with one settled and one open dispute
+async fn seeded_journal() -> Arc<dyn Journal + Send + Sync> {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let events = [
+        (
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 1,
+                tx_id: 1,
+                amount: 100.0,
+            }),
+            1,
+        ),
+        (
+            TransactionTypeEvent::Deposited(Deposited {
+                client_id: 2,
+                tx_id: 2,
+                amount: 50.0,
+            }),
+            1,
+        ),
+        (
+            TransactionTypeEvent::Withdrawn(Withdrawn {
+                client_id: 1,
+                tx_id: 3,
+                amount: 30.0,
+            }),
+            1,
+        ),
+        (
+            TransactionTypeEvent::Disputed(Disputed {
+                client_id: 1,
+                tx_id: 1,
+                amount: 100.0,
+            }),
+            2,
+        ),
+        (
+            TransactionTypeEvent::Disputed(Disputed {
+                client_id: 2,
+                tx_id: 2,
+                amount: 50.0,
+            }),
+            2,
+        ),
+        (
+            TransactionTypeEvent::Resolved(Resolved {
+                client_id: 1,
+                tx_id: 1,
+                amount: 100.0,
+            }),
+            2,
+        ),
+    ];
+    for (event, day) in events {
+        append(journal.as_ref(), event, day).await;
+    }
+    journal
+}
+
+fn hub(journal: Arc<dyn Journal + Send + Sync>) -> Arc<SubscriptionHub> {
+    Arc::new(SubscriptionHub::new(journal, 64))
+}
+
+#[tokio::test]
+async fn test_transaction_history_projection() {
+    let journal = seeded_journal().await;
+    let mut runner = ProjectionRunner::start(
+        hub(journal),
+        Arc::new(TransactionHistoryProjection::new()),
+        Arc::new(InMemoryCheckpointStore::new()),
+        SubscriptionConfig::default(),
+    );
+    runner.wait_for(6).await.unwrap();
+
+    let history = runner.projection().history(1).await;
+    let summary: Vec<(u32, EventKind, Option<f64>)> = history
+        .iter()
+        .map(|entry| (entry.tx_id, entry.event, entry.amount))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (1, EventKind::Deposited, Some(100.0)),
+            (3, EventKind::Withdrawn, Some(30.0)),
+            (1, EventKind::Disputed, Some(100.0)),
+            (1, EventKind::Resolved, Some(100.0)),
+        ]
+    );
+    assert!(runner.projection().history(9).await.is_empty());
+    runner.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_daily_totals_and_open_disputes_projections() {
+    let journal = seeded_journal().await;
+    let hub = hub(journal);
+    let checkpoints = Arc::new(InMemoryCheckpointStore::new());
+
+    let mut totals = ProjectionRunner::start(
+        hub.clone(),
+        Arc::new(DailyTotalsProjection::new()),
+        checkpoints.clone(),
+        SubscriptionConfig::default(),
+    );
+    let mut disputes = ProjectionRunner::start(
+        hub,
+        Arc::new(OpenDisputesProjection::new()),
+        checkpoints,
+        SubscriptionConfig::default(),
+    );
+    totals.wait_for(6).await.unwrap();
+    disputes.wait_for(6).await.unwrap();
+
+    let day1 = chrono::NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+    let client_1 = totals.projection().totals(1, day1).await.unwrap();
+    assert_eq!(client_1.deposited, 100.0);
+    assert_eq!(client_1.withdrawn, 30.0);
+    assert_eq!(client_1.events, 2);
+    assert_eq!(totals.projection().days(1).await.len(), 2);
+    assert_eq!(totals.projection().days(2).await.len(), 2);
+
+    let open = disputes.projection().open_disputes(None).await;
+    assert_eq!(open.len(), 1);
+    assert_eq!((open[0].client_id, open[0].tx_id), (2, 2));
+    assert!(
+        disputes
+            .projection()
+            .open_disputes(Some(1))
+            .await
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_projection_rebuild_from_zero() {
+    let journal = seeded_journal().await;
+    let mut runner = ProjectionRunner::start(
+        hub(journal),
+        Arc::new(TransactionHistoryProjection::new()),
+        Arc::new(InMemoryCheckpointStore::new()),
+        SubscriptionConfig {
+            page_size: 2,
+            checkpoint_interval: 1,
+            ..Default::default()
+        },
+    );
+    runner.wait_for(6).await.unwrap();
+
+    runner.rebuild().await.unwrap();
+    runner.wait_for(6).await.unwrap();
+
+    // Rebuilt from an empty model, not appended to the previous one
+    assert_eq!(runner.projection().history(1).await.len(), 4);
+    assert_eq!(runner.projection().history(2).await.len(), 2);
+    assert_eq!(runner.stop().await.unwrap(), 6);
+}
+
+#[tokio::test]
+async fn test_projection_follows_live_events() {
+    let journal = seeded_journal().await;
+    let hub = hub(journal.clone());
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    let registry = ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+    .with_callback(hub.publisher());
+
+    let mut runner = ProjectionRunner::start(
+        hub,
+        Arc::new(OpenDisputesProjection::new()),
+        Arc::new(InMemoryCheckpointStore::new()),
+        SubscriptionConfig::default(),
+    );
+    runner.wait_for(6).await.unwrap();
+
+    // Processed by a client actor, whose engine publishes the events to the hub
+    registry
+        .process_command(
+            3,
+            TransactionTypeCommand::Deposit(Deposit {
+                client_id: 3,
+                tx_id: 30,
+                amount: 5.0,
+            }),
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new("deposit:3:30".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+    registry
+        .process_command(
+            3,
+            TransactionTypeCommand::Dispute(Dispute {
+                client_id: 3,
+                tx_id: 30,
+            }),
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new("dispute:3:30".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+
+    runner.wait_for(8).await.unwrap();
+    let open = runner.projection().open_disputes(Some(3)).await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].amount, 5.0);
+}
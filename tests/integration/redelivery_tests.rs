@@ -0,0 +1,302 @@
+use async_trait::async_trait;
+use payment::adapter::{ClientRegistry, InMemoryDisputeIndex, InMemoryJournal};
+use payment::domain::*;
+use payment::port::{CallbackContext, ClientExecutor, DisputeIndex, EventCallback, Journal};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the deposits it is notified about
+#[derive(Default)]
+struct CountingCallback(AtomicUsize);
+
+#[async_trait]
+impl EventCallback for CountingCallback {
+    async fn on_deposited(
+        &self,
+        _event: &Deposited,
+        _ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Fails on the first deposit it is notified about
+#[derive(Default)]
+struct FailingOnceCallback(AtomicUsize);
+
+#[async_trait]
+impl EventCallback for FailingOnceCallback {
+    async fn on_deposited(
+        &self,
+        _event: &Deposited,
+        _ctx: &CallbackContext,
+    ) -> Result<(), PaymentError> {
+        if self.0.fetch_add(1, Ordering::SeqCst) == 0 {
+            return Err(PaymentError::Engine(EngineError::SideEffectError(
+                "notification service down".to_string(),
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn registry() -> (ClientRegistry, Arc<dyn Journal + Send + Sync>) {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    let registry = ClientRegistry::with_namespace(
+        journal.clone(),
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    );
+    (registry, journal)
+}
+
+async fn send(
+    executor: &dyn ClientExecutor,
+    kind: &str,
+    command: TransactionTypeCommand,
+) -> Result<(), PaymentError> {
+    let key = format!("{}:{}:{}", kind, command.client_id(), command.tx_id());
+    executor
+        .process_command(
+            command.client_id(),
+            command,
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new(key),
+            },
+        )
+        .await
+}
+
+async fn deposit(
+    executor: &dyn ClientExecutor,
+    tx_id: u32,
+    amount: f64,
+) -> Result<(), PaymentError> {
+    let command = TransactionTypeCommand::Deposit(Deposit {
+        client_id: 1,
+        tx_id,
+        amount,
+    });
+    send(executor, "deposit", command).await
+}
+
+async fn withdraw(
+    executor: &dyn ClientExecutor,
+    tx_id: u32,
+    amount: f64,
+) -> Result<(), PaymentError> {
+    let command = TransactionTypeCommand::Withdrawal(Withdraw {
+        client_id: 1,
+        tx_id,
+        amount,
+    });
+    send(executor, "withdrawal", command).await
+}
+
+async fn dispute(executor: &dyn ClientExecutor, tx_id: u32) -> Result<(), PaymentError> {
+    let command = TransactionTypeCommand::Dispute(Dispute {
+        client_id: 1,
+        tx_id,
+    });
+    send(executor, "dispute", command).await
+}
+
+async fn resolve(executor: &dyn ClientExecutor, tx_id: u32) -> Result<(), PaymentError> {
+    let command = TransactionTypeCommand::Resolve(Resolve {
+        client_id: 1,
+        tx_id,
+    });
+    send(executor, "resolve", command).await
+}
+
+async fn chargeback(executor: &dyn ClientExecutor, tx_id: u32) -> Result<(), PaymentError> {
+    let command = TransactionTypeCommand::Chargeback(Chargeback {
+        client_id: 1,
+        tx_id,
+    });
+    send(executor, "chargeback", command).await
+}
+
+fn total(state: &AccountState) -> f64 {
+    match state {
+        AccountState::Active(s) => s.total,
+        other => panic!("unexpected account state: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_actor_ignores_redelivery_of_old_command() {
+    let (registry, journal) = registry();
+
+    for tx_id in 1..=3 {
+        deposit(&registry, tx_id, 10.0).await.unwrap();
+    }
+
+    // tx 1 is two events behind the actor's last sequence
+    deposit(&registry, 1, 10.0).await.unwrap();
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(total(&state), 30.0);
+    assert_eq!(journal.replay(None).await.unwrap().len(), 3);
+
+    // Same actor keeps serving new commands
+    deposit(&registry, 4, 10.0).await.unwrap();
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(total(&state), 40.0);
+    assert_eq!(registry.metrics().spawned(), 1);
+    assert_eq!(registry.metrics().restarted(), 0);
+}
+
+#[tokio::test]
+async fn test_redelivery_does_not_rerun_callbacks() {
+    let (registry, _journal) = registry();
+    let callback = Arc::new(CountingCallback::default());
+    let registry = registry.with_callback(callback.clone());
+
+    deposit(&registry, 1, 10.0).await.unwrap();
+    deposit(&registry, 2, 10.0).await.unwrap();
+    deposit(&registry, 1, 10.0).await.unwrap();
+    deposit(&registry, 2, 10.0).await.unwrap();
+
+    assert_eq!(callback.0.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_worker_pool_ignores_redelivery_of_old_command() {
+    let (registry, _journal) = registry();
+    let pool = registry.worker_pool(2).await.unwrap();
+
+    for tx_id in 1..=3 {
+        deposit(&pool, tx_id, 10.0).await.unwrap();
+    }
+    deposit(&pool, 1, 10.0).await.unwrap();
+    deposit(&pool, 4, 10.0).await.unwrap();
+
+    let states = pool.get_all_states().await.unwrap();
+    assert_eq!(total(&states[&1]), 40.0);
+    pool.shutdown_all().await;
+}
+
+#[tokio::test]
+async fn test_conflicting_redelivery_is_rejected_without_failing_actor() {
+    let (registry, _journal) = registry();
+
+    deposit(&registry, 1, 10.0).await.unwrap();
+    let result = deposit(&registry, 1, 25.0).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::IdempotencyConflict(_)))
+    ));
+
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(total(&state), 10.0);
+    assert_eq!(registry.metrics().restarted(), 0);
+}
+
+#[tokio::test]
+async fn test_withdrawal_redelivery_after_balance_was_spent() {
+    let (registry, journal) = registry();
+
+    deposit(&registry, 1, 10.0).await.unwrap();
+    withdraw(&registry, 2, 10.0).await.unwrap();
+
+    // Validating it again would fail with InsufficientFunds
+    withdraw(&registry, 2, 10.0).await.unwrap();
+
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(total(&state), 0.0);
+    assert_eq!(journal.replay(None).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_dispute_and_resolve_redeliveries_after_resolution() {
+    let (registry, journal) = registry();
+
+    deposit(&registry, 1, 10.0).await.unwrap();
+    dispute(&registry, 1).await.unwrap();
+    resolve(&registry, 1).await.unwrap();
+
+    // The transaction is no longer disputed, so neither would validate again
+    dispute(&registry, 1).await.unwrap();
+    resolve(&registry, 1).await.unwrap();
+
+    match registry.get_state(1).await.unwrap().unwrap() {
+        AccountState::Active(state) => {
+            assert_eq!(state.available, 10.0);
+            assert_eq!(state.held, 0.0);
+        }
+        other => panic!("unexpected account state: {:?}", other),
+    }
+    assert_eq!(journal.replay(None).await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_chargeback_redelivery_after_account_was_frozen() {
+    let (registry, journal) = registry();
+
+    deposit(&registry, 1, 10.0).await.unwrap();
+    dispute(&registry, 1).await.unwrap();
+    chargeback(&registry, 1).await.unwrap();
+
+    dispute(&registry, 1).await.unwrap();
+    chargeback(&registry, 1).await.unwrap();
+
+    match registry.get_state(1).await.unwrap().unwrap() {
+        AccountState::Frozen(state) => {
+            assert_eq!(state.total, 0.0);
+            assert_eq!(state.held, 0.0);
+        }
+        other => panic!("unexpected account state: {:?}", other),
+    }
+    assert_eq!(journal.replay(None).await.unwrap().len(), 3);
+    assert_eq!(registry.metrics().restarted(), 0);
+}
+
+#[tokio::test]
+async fn test_worker_pool_ignores_redelivered_withdrawal() {
+    let (registry, _journal) = registry();
+    let pool = registry.worker_pool(2).await.unwrap();
+
+    deposit(&pool, 1, 10.0).await.unwrap();
+    withdraw(&pool, 2, 10.0).await.unwrap();
+    withdraw(&pool, 2, 10.0).await.unwrap();
+
+    let states = pool.get_all_states().await.unwrap();
+    assert_eq!(total(&states[&1]), 0.0);
+    pool.shutdown_all().await;
+}
+
+#[tokio::test]
+async fn test_redelivery_with_different_kind_is_a_conflict() {
+    let (registry, _journal) = registry();
+
+    deposit(&registry, 1, 10.0).await.unwrap();
+    let command = TransactionTypeCommand::Withdrawal(Withdraw {
+        client_id: 1,
+        tx_id: 1,
+        amount: 10.0,
+    });
+    let result = send(&registry, "deposit", command).await;
+    assert!(matches!(
+        result,
+        Err(PaymentError::Engine(EngineError::IdempotencyConflict(_)))
+    ));
+}
+
+#[tokio::test]
+async fn test_callback_failure_then_retry_keeps_the_journaled_deposit() {
+    let (registry, journal) = registry();
+    let registry = registry.with_callback(Arc::new(FailingOnceCallback::default()));
+
+    // The deposit is journaled before its callback fails
+    deposit(&registry, 1, 10.0).await.unwrap();
+    // The caller's retry is answered as a duplicate
+    deposit(&registry, 1, 10.0).await.unwrap();
+    deposit(&registry, 2, 5.0).await.unwrap();
+
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(total(&state), 15.0);
+    assert_eq!(journal.replay(None).await.unwrap().len(), 2);
+    assert_eq!(registry.metrics().restarted(), 0);
+}
@@ -0,0 +1,178 @@
+use payment::adapter::{
+    ClientRegistry, FileSnapshotStore, InMemoryDisputeIndex, InMemoryJournal,
+    SNAPSHOT_SCHEMA_VERSION, SnapshotPolicy,
+};
+use payment::domain::*;
+use payment::port::{ClientExecutor, DisputeIndex, Journal, Snapshotter};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn registry(store: Arc<FileSnapshotStore>, policy: SnapshotPolicy) -> ClientRegistry {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+    .with_snapshots(store)
+    .with_snapshot_policy(policy)
+}
+
+async fn deposit(executor: &dyn ClientExecutor, tx_id: u32) {
+    executor
+        .process_command(
+            1,
+            TransactionTypeCommand::Deposit(Deposit {
+                client_id: 1,
+                tx_id,
+                amount: 10.0,
+            }),
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new(format!("deposit:{}", tx_id)),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+/// Sequences of client 1's snapshots once the newest one reaches `sequence_nr`
+async fn wait_for_snapshot(store: &FileSnapshotStore, sequence_nr: u64) -> Vec<u64> {
+    for _ in 0..200 {
+        let sequences: Vec<u64> = store
+            .list(1)
+            .unwrap()
+            .iter()
+            .map(|m| m.sequence_nr)
+            .collect();
+        if sequences.last() == Some(&sequence_nr) {
+            return sequences;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("no snapshot at sequence {}", sequence_nr);
+}
+
+#[tokio::test]
+async fn test_snapshots_every_n_events_and_prunes_old_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(
+        FileSnapshotStore::new(dir.path())
+            .unwrap()
+            .with_retention(2),
+    );
+    let registry = registry(
+        store.clone(),
+        SnapshotPolicy {
+            every_events: Some(2),
+            ..SnapshotPolicy::never()
+        },
+    );
+
+    for tx_id in 1..=6 {
+        deposit(&registry, tx_id).await;
+        // Let each background write finish so none is skipped
+        if tx_id % 2 == 0 {
+            wait_for_snapshot(&store, tx_id as u64).await;
+        }
+    }
+    assert_eq!(wait_for_snapshot(&store, 6).await, vec![4, 6]);
+
+    let (sequence_nr, state) = store.load(1).await.unwrap().unwrap();
+    assert_eq!(sequence_nr, 6);
+    assert!(matches!(state, AccountState::Active(s) if s.total == 60.0));
+}
+
+#[tokio::test]
+async fn test_snapshots_on_first_event_after_interval() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(FileSnapshotStore::new(dir.path()).unwrap());
+    let registry = registry(
+        store.clone(),
+        SnapshotPolicy {
+            every_interval: Some(Duration::from_millis(50)),
+            ..SnapshotPolicy::never()
+        },
+    );
+
+    deposit(&registry, 1).await;
+    deposit(&registry, 2).await;
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert!(store.list(1).unwrap().is_empty());
+
+    deposit(&registry, 3).await;
+    assert_eq!(wait_for_snapshot(&store, 3).await, vec![3]);
+}
+
+#[tokio::test]
+async fn test_snapshots_on_shutdown_with_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(FileSnapshotStore::new(dir.path()).unwrap());
+    let registry = registry(
+        store.clone(),
+        SnapshotPolicy {
+            on_stop: true,
+            ..SnapshotPolicy::never()
+        },
+    );
+
+    for tx_id in 1..=3 {
+        deposit(&registry, tx_id).await;
+    }
+    assert!(store.list(1).unwrap().is_empty());
+
+    registry.shutdown_all().await;
+    let snapshots = store.list(1).unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].client_id, 1);
+    assert_eq!(snapshots[0].sequence_nr, 3);
+    assert_eq!(snapshots[0].schema_version, SNAPSHOT_SCHEMA_VERSION);
+
+    // Nothing new since that snapshot: stopping again writes no other one
+    registry.get_or_spawn(1).await.unwrap();
+    registry.shutdown_all().await;
+    assert_eq!(store.list(1).unwrap(), snapshots);
+}
+
+#[tokio::test]
+async fn test_rejects_snapshots_from_a_newer_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileSnapshotStore::new(dir.path()).unwrap();
+    std::fs::write(
+        dir.path().join(format!("client-1-{:020}.snapshot", 5)),
+        r#"{"schema_version":99,"client_id":1,"sequence_nr":5,"frozen":false,"available":1.0,
+            "held":0.0,"total":1.0,"last_activity":"2026-01-01T00:00:00Z",
+            "taken_at":"2026-01-01T00:00:00Z"}"#,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        store.load(1).await,
+        Err(PaymentError::Engine(EngineError::PersistenceError(_)))
+    ));
+}
+
+#[tokio::test]
+async fn test_worker_pool_snapshots_per_policy() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(FileSnapshotStore::new(dir.path()).unwrap());
+    let registry = registry(
+        store.clone(),
+        SnapshotPolicy {
+            every_events: Some(2),
+            on_stop: true,
+            ..SnapshotPolicy::never()
+        },
+    );
+    let pool = registry.worker_pool(2).await.unwrap();
+
+    deposit(&pool, 1).await;
+    deposit(&pool, 2).await;
+    assert_eq!(wait_for_snapshot(&store, 2).await, vec![2]);
+
+    deposit(&pool, 3).await;
+    pool.shutdown_all().await;
+    let (sequence_nr, state) = store.load(1).await.unwrap().unwrap();
+    assert_eq!(sequence_nr, 3);
+    assert!(matches!(state, AccountState::Active(s) if s.total == 30.0));
+}
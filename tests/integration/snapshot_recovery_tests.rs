@@ -0,0 +1,272 @@
+use payment::adapter::{
+    ClientRegistry, FileSnapshotStore, InMemoryDisputeIndex, InMemoryJournal, SnapshotPolicy,
+};
+use payment::domain::*;
+use payment::port::{DisputeIndex, Journal, Snapshotter};
+use std::sync::Arc;
+
+fn registry(
+    journal: Arc<dyn Journal + Send + Sync>,
+    snapshots: Option<Arc<FileSnapshotStore>>,
+) -> ClientRegistry {
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    let registry = ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    );
+    match snapshots {
+        // Only the snapshots saved by the tests themselves
+        Some(snapshots) => registry
+            .with_snapshots(snapshots)
+            .with_snapshot_policy(SnapshotPolicy::never()),
+        None => registry,
+    }
+}
+
+async fn process(
+    registry: &ClientRegistry,
+    command: TransactionTypeCommand,
+    key: &str,
+) -> Result<(), PaymentError> {
+    let client_id = match &command {
+        TransactionTypeCommand::Deposit(c) => c.client_id,
+        TransactionTypeCommand::Withdrawal(c) => c.client_id,
+        TransactionTypeCommand::Dispute(c) => c.client_id,
+        TransactionTypeCommand::Resolve(c) => c.client_id,
+        TransactionTypeCommand::Chargeback(c) => c.client_id,
+    };
+    registry
+        .process_command(
+            client_id,
+            command,
+            CommandMetadata {
+                deduplication_key: DeduplicationKey::new(key.to_string()),
+            },
+        )
+        .await
+}
+
+async fn deposit(registry: &ClientRegistry, client_id: u16, tx_id: u32, amount: f64) {
+    process(
+        registry,
+        TransactionTypeCommand::Deposit(Deposit {
+            client_id,
+            tx_id,
+            amount,
+        }),
+        &format!("deposit:{}", tx_id),
+    )
+    .await
+    .unwrap();
+}
+
+fn balances(state: &AccountState) -> (f64, f64, f64, bool) {
+    match state {
+        AccountState::Active(s) => (s.available, s.held, s.total, false),
+        AccountState::Frozen(s) => (s.available, s.held, s.total, true),
+    }
+}
+
+#[tokio::test]
+async fn test_file_snapshot_store_round_trips_frozen_accounts() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileSnapshotStore::new(dir.path()).unwrap();
+    assert!(store.load(7).await.unwrap().is_none());
+
+    let frozen = AccountState::Frozen(FrozenAccountState {
+        available: 1.5,
+        held: 0.0,
+        total: 1.5,
+        last_activity: chrono::Utc::now(),
+    });
+    store.save(7, 42, frozen).await.unwrap();
+
+    let (sequence_nr, state) = store.load(7).await.unwrap().unwrap();
+    assert_eq!(sequence_nr, 42);
+    assert_eq!(balances(&state), (1.5, 0.0, 1.5, true));
+    assert!(store.load(8).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_respawned_actor_recovers_from_snapshot_and_journal() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let store = Arc::new(FileSnapshotStore::new(dir.path()).unwrap());
+    let registry = registry(journal.clone(), Some(store.clone()));
+
+    deposit(&registry, 1, 1, 100.0).await;
+    deposit(&registry, 2, 2, 5.0).await;
+    deposit(&registry, 1, 3, 20.0).await;
+
+    // Snapshot taken after sequence 3, then more activity
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    store.save(1, 3, state).await.unwrap();
+    deposit(&registry, 1, 4, 30.0).await;
+    process(
+        &registry,
+        TransactionTypeCommand::Dispute(Dispute {
+            client_id: 1,
+            tx_id: 4,
+        }),
+        "dispute:4",
+    )
+    .await
+    .unwrap();
+
+    registry.shutdown_all().await;
+    assert!(registry.get_state(1).await.unwrap().is_none());
+
+    // The next command respawns the actor from snapshot + events 4 and 5
+    process(
+        &registry,
+        TransactionTypeCommand::Withdrawal(Withdraw {
+            client_id: 1,
+            tx_id: 6,
+            amount: 120.0,
+        }),
+        "withdraw:6",
+    )
+    .await
+    .unwrap();
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(balances(&state), (0.0, 30.0, 30.0, false));
+}
+
+#[tokio::test]
+async fn test_recovery_only_replays_events_after_the_snapshot() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let store = Arc::new(FileSnapshotStore::new(dir.path()).unwrap());
+    let registry = registry(journal.clone(), Some(store.clone()));
+
+    deposit(&registry, 1, 1, 10.0).await;
+    deposit(&registry, 1, 2, 10.0).await;
+    registry.shutdown_all().await;
+
+    // A snapshot at sequence 1 that disagrees with the journal shows which events were folded
+    store
+        .save(
+            1,
+            1,
+            AccountState::Active(ActiveAccountState {
+                available: 1000.0,
+                held: 0.0,
+                total: 1000.0,
+                last_activity: chrono::Utc::now(),
+            }),
+        )
+        .await
+        .unwrap();
+
+    deposit(&registry, 1, 3, 10.0).await;
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(balances(&state), (1020.0, 0.0, 1020.0, false));
+}
+
+#[tokio::test]
+async fn test_respawned_actor_replays_journal_without_snapshots() {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let registry = registry(journal.clone(), None);
+
+    deposit(&registry, 1, 1, 100.0).await;
+    deposit(&registry, 2, 2, 5.0).await;
+    process(
+        &registry,
+        TransactionTypeCommand::Withdrawal(Withdraw {
+            client_id: 1,
+            tx_id: 3,
+            amount: 40.0,
+        }),
+        "withdraw:3",
+    )
+    .await
+    .unwrap();
+
+    registry.shutdown_all().await;
+    registry.get_or_spawn(1).await.unwrap();
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(balances(&state), (60.0, 0.0, 60.0, false));
+
+    // The recovered actor keeps validating against its rebuilt balance
+    let rejected = process(
+        &registry,
+        TransactionTypeCommand::Withdrawal(Withdraw {
+            client_id: 1,
+            tx_id: 4,
+            amount: 70.0,
+        }),
+        "withdraw:4",
+    )
+    .await;
+    assert!(rejected.is_err());
+    deposit(&registry, 1, 5, 10.0).await;
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(balances(&state), (70.0, 0.0, 70.0, false));
+}
+
+/// Cut a snapshot file in half, as a crash mid-write on a filesystem without rename
+/// ordering would
+fn corrupt_snapshot(dir: &std::path::Path, client_id: u16, sequence_nr: u64) {
+    let path = dir.join(format!("client-{}-{:020}.snapshot", client_id, sequence_nr));
+    let content = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &content[..content.len() / 2]).unwrap();
+}
+
+#[tokio::test]
+async fn test_corrupt_newest_snapshot_falls_back_to_the_previous_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let store = Arc::new(
+        FileSnapshotStore::new(dir.path())
+            .unwrap()
+            .with_retention(2),
+    );
+    let registry = registry(journal.clone(), Some(store.clone()));
+
+    deposit(&registry, 1, 1, 10.0).await;
+    store
+        .save(1, 1, registry.get_state(1).await.unwrap().unwrap())
+        .await
+        .unwrap();
+    deposit(&registry, 1, 2, 20.0).await;
+    store
+        .save(1, 2, registry.get_state(1).await.unwrap().unwrap())
+        .await
+        .unwrap();
+    deposit(&registry, 1, 3, 30.0).await;
+    registry.shutdown_all().await;
+
+    corrupt_snapshot(dir.path(), 1, 2);
+    let (sequence_nr, state) = store.load(1).await.unwrap().unwrap();
+    assert_eq!(sequence_nr, 1);
+    assert_eq!(balances(&state), (10.0, 0.0, 10.0, false));
+
+    // Recovery folds events 2 and 3 onto the older snapshot
+    registry.get_or_spawn(1).await.unwrap();
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(balances(&state), (60.0, 0.0, 60.0, false));
+}
+
+#[tokio::test]
+async fn test_corrupt_only_snapshot_falls_back_to_a_full_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let store = Arc::new(FileSnapshotStore::new(dir.path()).unwrap());
+    let registry = registry(journal.clone(), Some(store.clone()));
+
+    deposit(&registry, 1, 1, 10.0).await;
+    store
+        .save(1, 1, registry.get_state(1).await.unwrap().unwrap())
+        .await
+        .unwrap();
+    deposit(&registry, 1, 2, 20.0).await;
+    registry.shutdown_all().await;
+
+    corrupt_snapshot(dir.path(), 1, 1);
+    assert!(store.load(1).await.unwrap().is_none());
+
+    registry.get_or_spawn(1).await.unwrap();
+    let state = registry.get_state(1).await.unwrap().unwrap();
+    assert_eq!(balances(&state), (30.0, 0.0, 30.0, false));
+}
@@ -0,0 +1,216 @@
+use async_trait::async_trait;
+use payment::adapter::{FileSnapshotStore, InMemoryJournal};
+use payment::domain::*;
+use payment::port::{Journal, Snapshotter};
+use payment::service::{AsOf, account_state_as_of};
+
+async fn append(
+    journal: &InMemoryJournal,
+    event: TransactionTypeEvent,
+    client_id: u16,
+    tx_id: u32,
+    timestamp: chrono::DateTime<chrono::Utc>,
+) {
+    journal
+        .append(
+            event,
+            EventMetadata {
+                client_id,
+                tx_id,
+                timestamp,
+                deduplication_key: DeduplicationKey::new(format!("test:{}:{}", client_id, tx_id)),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+/// Client 1: deposit 100 (seq 1), client 2 deposit (seq 2), withdraw 30 (seq 3), dispute 40 (seq 4)
+async fn journal(start: chrono::DateTime<chrono::Utc>) -> InMemoryJournal {
+    let journal = InMemoryJournal::new();
+    let minute = chrono::TimeDelta::minutes(1);
+
+    append(
+        &journal,
+        TransactionTypeEvent::Deposited(Deposited {
+            client_id: 1,
+            tx_id: 1,
+            amount: 100.0,
+        }),
+        1,
+        1,
+        start,
+    )
+    .await;
+    append(
+        &journal,
+        TransactionTypeEvent::Deposited(Deposited {
+            client_id: 2,
+            tx_id: 2,
+            amount: 5.0,
+        }),
+        2,
+        2,
+        start + minute,
+    )
+    .await;
+    append(
+        &journal,
+        TransactionTypeEvent::Withdrawn(Withdrawn {
+            client_id: 1,
+            tx_id: 3,
+            amount: 30.0,
+        }),
+        1,
+        3,
+        start + minute * 2,
+    )
+    .await;
+    append(
+        &journal,
+        TransactionTypeEvent::Disputed(Disputed {
+            client_id: 1,
+            tx_id: 3,
+            amount: 40.0,
+        }),
+        1,
+        4,
+        start + minute * 3,
+    )
+    .await;
+    journal
+}
+
+fn balances(state: &AccountState) -> (f64, f64, f64) {
+    match state {
+        AccountState::Active(s) => (s.available, s.held, s.total),
+        AccountState::Frozen(s) => (s.available, s.held, s.total),
+    }
+}
+
+#[tokio::test]
+async fn test_state_as_of_sequence_lists_balance_after_each_event() {
+    let journal = journal(chrono::Utc::now()).await;
+
+    let report = account_state_as_of(&journal, 1, AsOf::Sequence(3), None)
+        .await
+        .unwrap();
+
+    assert_eq!(report.last_sequence, Some(3));
+    assert_eq!(balances(&report.state), (70.0, 0.0, 70.0));
+    assert_eq!(report.history.len(), 2);
+    assert_eq!(report.history[0].available, 100.0);
+    assert_eq!(report.history[1].tx_id, 3);
+    assert_eq!(report.history[1].available, 70.0);
+}
+
+#[tokio::test]
+async fn test_state_as_of_timestamp_stops_at_later_events() {
+    let start = chrono::Utc::now() - chrono::TimeDelta::hours(1);
+    let journal = journal(start).await;
+
+    let before = account_state_as_of(
+        &journal,
+        1,
+        AsOf::Timestamp(start - chrono::TimeDelta::seconds(1)),
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(before.history.is_empty());
+    assert_eq!(before.last_sequence, None);
+
+    let report = account_state_as_of(
+        &journal,
+        1,
+        AsOf::Timestamp(start + chrono::TimeDelta::seconds(90)),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.last_sequence, Some(1));
+    assert_eq!(balances(&report.state), (100.0, 0.0, 100.0));
+}
+
+struct FixedSnapshot(u64, AccountState);
+
+#[async_trait]
+impl Snapshotter for FixedSnapshot {
+    async fn save(
+        &self,
+        _client_id: u16,
+        _sequence: u64,
+        _state: AccountState,
+    ) -> Result<(), PaymentError> {
+        Ok(())
+    }
+
+    async fn load(&self, _client_id: u16) -> Result<Option<(u64, AccountState)>, PaymentError> {
+        Ok(Some((self.0, self.1.clone())))
+    }
+}
+
+#[tokio::test]
+async fn test_state_as_of_starts_from_earlier_snapshot_only() {
+    let journal = journal(chrono::Utc::now()).await;
+    let snapshot_state = AccountState::Active(ActiveAccountState {
+        available: 70.0,
+        held: 0.0,
+        total: 70.0,
+        last_activity: chrono::Utc::now(),
+    });
+    let snapshot = FixedSnapshot(3, snapshot_state);
+
+    let report = account_state_as_of(&journal, 1, AsOf::Sequence(4), Some(&snapshot))
+        .await
+        .unwrap();
+    assert_eq!(report.snapshot_sequence, Some(3));
+    assert_eq!(report.history.len(), 1);
+    assert_eq!(balances(&report.state), (30.0, 40.0, 70.0));
+
+    // The snapshot is newer than the query, so the fold starts from the beginning
+    let report = account_state_as_of(&journal, 1, AsOf::Sequence(1), Some(&snapshot))
+        .await
+        .unwrap();
+    assert_eq!(report.snapshot_sequence, None);
+    assert_eq!(balances(&report.state), (100.0, 0.0, 100.0));
+}
+
+#[tokio::test]
+async fn test_state_as_of_uses_nearest_earlier_retained_snapshot() {
+    let start = chrono::Utc::now() - chrono::TimeDelta::hours(1);
+    let journal = journal(start).await;
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileSnapshotStore::new(dir.path())
+        .unwrap()
+        .with_retention(3);
+    for (sequence_nr, available, held) in [(1, 100.0, 0.0), (3, 70.0, 0.0), (4, 30.0, 40.0)] {
+        let state = AccountState::Active(ActiveAccountState {
+            available,
+            held,
+            total: available + held,
+            last_activity: chrono::Utc::now(),
+        });
+        store.save(1, sequence_nr, state).await.unwrap();
+    }
+
+    let report = account_state_as_of(&journal, 1, AsOf::Sequence(3), Some(&store))
+        .await
+        .unwrap();
+    assert_eq!(report.snapshot_sequence, Some(3));
+    assert!(report.history.is_empty());
+    assert_eq!(balances(&report.state), (70.0, 0.0, 70.0));
+
+    // Between the withdrawal (seq 3) and the dispute (seq 4)
+    let report = account_state_as_of(
+        &journal,
+        1,
+        AsOf::Timestamp(start + chrono::TimeDelta::seconds(150)),
+        Some(&store),
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.snapshot_sequence, Some(3));
+    assert_eq!(report.last_sequence, None);
+    assert_eq!(balances(&report.state), (70.0, 0.0, 70.0));
+}
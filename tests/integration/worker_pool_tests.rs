@@ -0,0 +1,162 @@
+use payment::adapter::{ClientRegistry, InMemoryDisputeIndex, InMemoryJournal};
+use payment::domain::*;
+use payment::port::{ClientExecutor, DisputeIndex, Journal};
+use payment::service::Orchestrator;
+use std::io::Write;
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+
+fn registry(journal: Arc<dyn Journal + Send + Sync>) -> ClientRegistry {
+    let dispute_index: Arc<dyn DisputeIndex> = Arc::new(InMemoryDisputeIndex::new());
+    ClientRegistry::with_namespace(
+        journal,
+        dispute_index,
+        format!("test-{}", uuid::Uuid::new_v4()),
+    )
+}
+
+fn metadata(key: String) -> CommandMetadata {
+    CommandMetadata {
+        deduplication_key: DeduplicationKey::new(key),
+    }
+}
+
+fn balances(state: &AccountState) -> (f64, f64, f64, bool) {
+    match state {
+        AccountState::Active(s) => (s.available, s.held, s.total, false),
+        AccountState::Frozen(s) => (s.available, s.held, s.total, true),
+    }
+}
+
+#[tokio::test]
+async fn test_pool_partitions_clients_across_workers() {
+    let registry = registry(Arc::new(InMemoryJournal::new()));
+    let pool = registry.worker_pool(4).await.unwrap();
+
+    assert_eq!(pool.worker_count(), 4);
+    let mut used = [false; 4];
+    for client_id in 0..64 {
+        let worker = pool.partition(client_id);
+        assert_eq!(worker, pool.partition(client_id));
+        used[worker] = true;
+    }
+    assert!(used.iter().all(|used| *used));
+    pool.shutdown_all().await;
+}
+
+#[tokio::test]
+async fn test_pool_preserves_per_client_order() {
+    let registry = registry(Arc::new(InMemoryJournal::new()));
+    let pool = registry.worker_pool(3).await.unwrap();
+
+    // Each withdrawal only succeeds if the deposit before it was applied
+    for client_id in 1..=10u16 {
+        for round in 0..5u32 {
+            let tx_id = client_id as u32 * 100 + round * 2;
+            pool.process_command(
+                client_id,
+                TransactionTypeCommand::Deposit(Deposit {
+                    client_id,
+                    tx_id,
+                    amount: 10.0,
+                }),
+                metadata(format!("deposit:{}", tx_id)),
+            )
+            .await
+            .unwrap();
+            pool.process_command(
+                client_id,
+                TransactionTypeCommand::Withdrawal(Withdraw {
+                    client_id,
+                    tx_id: tx_id + 1,
+                    amount: 10.0,
+                }),
+                metadata(format!("withdraw:{}", tx_id + 1)),
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    let states = pool.get_all_states().await.unwrap();
+    assert_eq!(states.len(), 10);
+    assert!(
+        states
+            .values()
+            .all(|state| balances(state) == (0.0, 0.0, 0.0, false))
+    );
+    pool.shutdown_all().await;
+}
+
+#[tokio::test]
+async fn test_pool_recovers_clients_from_the_journal() {
+    let journal: Arc<dyn Journal + Send + Sync> = Arc::new(InMemoryJournal::new());
+    let registry = registry(journal.clone());
+    registry
+        .process_command(
+            1,
+            TransactionTypeCommand::Deposit(Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: 50.0,
+            }),
+            metadata("deposit:1".to_string()),
+        )
+        .await
+        .unwrap();
+    registry.shutdown_all().await;
+
+    let pool = registry.worker_pool(2).await.unwrap();
+    pool.process_command(
+        1,
+        TransactionTypeCommand::Dispute(Dispute {
+            client_id: 1,
+            tx_id: 1,
+        }),
+        metadata("dispute:1".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let states = pool.get_all_states().await.unwrap();
+    assert_eq!(balances(&states[&1]), (0.0, 50.0, 50.0, false));
+    pool.shutdown_all().await;
+}
+
+#[tokio::test]
+async fn test_orchestrator_backends_agree() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type,client,tx,amount").unwrap();
+    for line in [
+        "deposit,1,1,100.0",
+        "deposit,2,2,200.0",
+        "withdrawal,1,3,30.0",
+        "dispute,2,2,",
+        "deposit,3,4,5.0",
+        "chargeback,2,2,",
+        "withdrawal,3,5,10.0",
+    ] {
+        writeln!(file, "{}", line).unwrap();
+    }
+    file.flush().unwrap();
+    let mode = OrchestratorMode::Csv {
+        file_path: file.path().to_str().unwrap().to_string(),
+    };
+
+    let actors =
+        Orchestrator::with_registry(registry(Arc::new(InMemoryJournal::new())), mode.clone())
+            .process()
+            .await
+            .unwrap();
+    let pool = Orchestrator::with_registry(registry(Arc::new(InMemoryJournal::new())), mode)
+        .with_backend(ExecutionBackend::WorkerPool { workers: 2 })
+        .process()
+        .await
+        .unwrap();
+
+    assert_eq!(actors.len(), 3);
+    for (client_id, state) in &actors {
+        assert_eq!(balances(state), balances(&pool[client_id]));
+    }
+    assert_eq!(balances(&pool[&2]), (0.0, 0.0, 0.0, true));
+}